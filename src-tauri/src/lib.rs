@@ -1,7 +1,15 @@
 mod map;
+mod navigation;
+mod routing;
+mod search;
+mod settings;
 mod storage;
 
-use map::{map_cmd, MapState};
+use map::{map_cmd, map_protocol, MapState};
+use navigation::{navigation_cmd, NavigationState};
+use routing::{routing_cmd, RoutingState};
+use search::{search_cmd, SearchState};
+use settings::{settings_cmd, SettingsState};
 use storage::{storage_cmd, StorageState};
 use tauri::Manager;
 
@@ -9,24 +17,76 @@ use tauri::Manager;
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .register_asynchronous_uri_scheme_protocol(
+            map_protocol::TILE_SCHEME,
+            map_protocol::handle_tile_request,
+        )
         .setup(|app| {
-            let storage_state = StorageState::new(app.handle())
+            let settings_path = settings::settings_path(app.handle())?;
+            let settings = settings::load_settings(&settings_path).unwrap_or_else(|e| {
+                eprintln!("Warning: Using the default settings: {}", e);
+                Default::default()
+            });
+
+            let storage_state = StorageState::new(app.handle(), &settings.storage)
                 .expect("Failed to initialize storage state");
             app.manage(storage_state);
+            storage::spawn_status_events(app.handle());
+
+            app.manage(MapState::with_settings(&settings.map));
+            app.manage(SettingsState::new(settings_path, settings));
+            app.manage(NavigationState::new());
+
+            app.manage(SearchState::new());
+            search::listen_for_locality_changes(app.handle());
 
-            app.manage(MapState::new());
+            app.manage(RoutingState::new());
+            routing::listen_for_locality_changes(app.handle());
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             map_cmd::init_pmtiles_reader,
-            map_cmd::get_pmtiles_tile,
             map_cmd::get_localities,
+            map_cmd::reload_localities,
+            map_cmd::get_tilejson,
+            map_cmd::query_features_at,
+            map_cmd::reverse_geocode,
+            map_cmd::get_tile_cache_stats,
+            map_cmd::get_tile_options,
+            map_cmd::set_tile_options,
+            map_cmd::set_locality_priority,
+            search_cmd::search_places,
+            routing_cmd::route,
+            navigation_cmd::start_navigation,
+            navigation_cmd::update_position,
+            navigation_cmd::stop_navigation,
+            navigation_cmd::get_active_route,
             storage_cmd::start_storage_node,
             storage_cmd::stop_storage_node,
             storage_cmd::connect_to_peer,
             storage_cmd::connect_to_peers,
+            storage_cmd::storage_status,
             storage_cmd::download_pmtiles_files,
+            storage_cmd::fetch_catalog,
+            storage_cmd::verify_catalog,
+            storage_cmd::list_catalog_regions,
+            storage_cmd::download_region,
+            storage_cmd::download_regions_in_bbox,
+            storage_cmd::cancel_download,
+            storage_cmd::get_download_options,
+            storage_cmd::set_download_options,
+            storage_cmd::verify_localities,
+            storage_cmd::repair_localities,
+            storage_cmd::get_installed_localities,
+            storage_cmd::delete_locality,
+            storage_cmd::check_locality_updates,
+            storage_cmd::update_localities,
+            storage_cmd::get_storage_usage,
+            storage_cmd::get_quota_options,
+            storage_cmd::set_quota_options,
+            settings_cmd::get_settings,
+            settings_cmd::update_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
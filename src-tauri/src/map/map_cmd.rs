@@ -1,8 +1,13 @@
+use pmtiles::tilejson::TileJSON;
 use tauri::State;
 
 use super::map_service;
 use super::map_state::MapState;
-use super::map_types::MultiPmtilesInfo;
+use super::map_types::{
+    LocalityChanges, LocalityMetadata, MultiPmtilesInfo, ReverseGeocodeResult, SourceKind,
+    TileCacheStats, TileOptions,
+};
+use crate::settings::change_settings;
 use crate::storage::StorageState;
 
 #[tauri::command]
@@ -12,17 +17,15 @@ pub async fn init_pmtiles_reader(
     storage_state: State<'_, StorageState>,
 ) -> Result<MultiPmtilesInfo, String> {
     let storage_manager = storage_state.storage_manager();
-    map_service::init_multi_reader(&app, &map_state, storage_manager.as_ref()).await
+    let info = map_service::init_multi_reader(&app, &map_state, storage_manager.as_ref()).await?;
+    crate::search::spawn_index_sync(&app);
+    crate::routing::spawn_graph_sync(&app);
+    Ok(info)
 }
 
 #[tauri::command]
-pub async fn get_pmtiles_tile(
-    z: u8,
-    x: u32,
-    y: u32,
-    state: State<'_, MapState>,
-) -> Result<Option<Vec<u8>>, String> {
-    map_service::get_tile(z, x, y, &state).await
+pub async fn reload_localities(state: State<'_, MapState>) -> Result<LocalityChanges, String> {
+    map_service::reload_localities(&state).await
 }
 
 #[tauri::command]
@@ -32,3 +35,71 @@ pub async fn get_localities(
     let metadata = state.locality_metadata.read().await;
     Ok(metadata.values().cloned().collect())
 }
+
+#[tauri::command]
+pub async fn get_tilejson(
+    source: SourceKind,
+    tile_url: String,
+    state: State<'_, MapState>,
+) -> Result<TileJSON, String> {
+    map_service::get_tilejson(source, tile_url, &state).await
+}
+
+#[tauri::command]
+pub async fn query_features_at(
+    lon: f64,
+    lat: f64,
+    zoom: u8,
+    radius_px: f64,
+    state: State<'_, MapState>,
+) -> Result<serde_json::Value, String> {
+    map_service::query_features_at(lon, lat, zoom, radius_px, &state).await
+}
+
+#[tauri::command]
+pub async fn reverse_geocode(
+    lon: f64,
+    lat: f64,
+    state: State<'_, MapState>,
+) -> Result<ReverseGeocodeResult, String> {
+    map_service::reverse_geocode(lon, lat, &state).await
+}
+
+#[tauri::command]
+pub async fn get_tile_cache_stats(state: State<'_, MapState>) -> Result<TileCacheStats, String> {
+    Ok(state.tile_cache.read().await.stats())
+}
+
+#[tauri::command]
+pub async fn get_tile_options(state: State<'_, MapState>) -> Result<TileOptions, String> {
+    Ok(*state.tile_options.read().await)
+}
+
+/// Changes the tile options and saves them with the settings.
+#[tauri::command]
+pub async fn set_tile_options(app: tauri::AppHandle, options: TileOptions) -> Result<(), String> {
+    change_settings(&app, |settings| settings.map.tile = options).await?;
+    Ok(())
+}
+
+/// Overrides the priority of a locality, or restores the one from its
+/// archive when `priority` is `None`, and saves it with the settings.
+#[tauri::command]
+pub async fn set_locality_priority(
+    app: tauri::AppHandle,
+    locality_id: String,
+    priority: Option<i32>,
+    state: State<'_, MapState>,
+) -> Result<LocalityMetadata, String> {
+    let updated = map_service::set_locality_priority(&locality_id, priority, &state).await?;
+
+    change_settings(&app, |settings| {
+        let overrides = &mut settings.map.priority_overrides;
+        match priority {
+            Some(priority) => overrides.insert(locality_id, priority),
+            None => overrides.remove(&locality_id),
+        };
+    })
+    .await?;
+    Ok(updated)
+}
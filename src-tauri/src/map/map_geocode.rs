@@ -0,0 +1,339 @@
+//! Reverse geocoding against decoded vector tiles: the labelled features
+//! nearest to a coordinate are picked per administrative level.
+
+use serde_json::Value as JsonValue;
+
+use super::map_mvt::{decode_geometry, Tile};
+use super::map_query::{feature_distance, feature_properties, lonlat_to_tile};
+use super::map_types::ReverseGeocodeResult;
+
+/// Zoom levels queried, from the street level down to where region labels live.
+pub const REVERSE_GEOCODE_ZOOMS: [u8; 3] = [14, 10, 6];
+
+const EARTH_CIRCUMFERENCE_M: f64 = 40_075_016.686;
+
+const HOUSENUMBER_KEYS: [&str; 3] = ["addr:housenumber", "addr_housenumber", "housenumber"];
+const ADDR_STREET_KEYS: [&str; 2] = ["addr:street", "addr_street"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Address,
+    Street,
+    Neighbourhood,
+    Locality,
+    Region,
+}
+
+impl Level {
+    /// Features further away than this don't describe the coordinate.
+    fn max_distance_m(self) -> f64 {
+        match self {
+            Level::Address => 50.0,
+            Level::Street => 200.0,
+            Level::Neighbourhood => 3_000.0,
+            Level::Locality => 25_000.0,
+            Level::Region => f64::INFINITY,
+        }
+    }
+
+    /// Classifies a feature from its layer (Protomaps or OpenMapTiles schema)
+    /// and its `kind`/`class` property.
+    fn of_feature(layer: &str, properties: &JsonValue) -> Option<Level> {
+        if HOUSENUMBER_KEYS.iter().any(|k| properties.get(k).is_some()) {
+            return Some(Level::Address);
+        }
+
+        let kind = properties
+            .get("kind")
+            .or_else(|| properties.get("class"))
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+
+        match layer {
+            "roads" | "transportation_name" => Some(Level::Street),
+            "places" | "place" => match kind {
+                "neighbourhood" | "macrohood" | "suburb" | "quarter" => Some(Level::Neighbourhood),
+                "locality" | "city" | "town" | "village" | "hamlet" => Some(Level::Locality),
+                "region" | "state" | "province" => Some(Level::Region),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    name: String,
+    distance_m: f64,
+}
+
+/// Keeps the nearest named feature per level across every tile added.
+#[derive(Debug)]
+pub struct GeocodeCollector {
+    lon: f64,
+    lat: f64,
+    address: Option<(Candidate, Option<String>)>,
+    street: Option<Candidate>,
+    neighbourhood: Option<Candidate>,
+    locality: Option<Candidate>,
+    region: Option<Candidate>,
+}
+
+impl GeocodeCollector {
+    pub fn new(lon: f64, lat: f64) -> Self {
+        Self {
+            lon,
+            lat,
+            address: None,
+            street: None,
+            neighbourhood: None,
+            locality: None,
+            region: None,
+        }
+    }
+
+    pub fn add_tile(&mut self, tile: &Tile, (z, x, y): (u8, u32, u32)) {
+        let [tx, ty] = lonlat_to_tile(self.lon, self.lat, z);
+        let meters_per_tile =
+            EARTH_CIRCUMFERENCE_M * self.lat.to_radians().cos() / 2f64.powi(i32::from(z));
+
+        for layer in &tile.layers {
+            let extent = f64::from(layer.extent());
+            let point = [(tx - f64::from(x)) * extent, (ty - f64::from(y)) * extent];
+
+            for feature in &layer.features {
+                let properties = feature_properties(layer, &feature.tags);
+                let Some(level) = Level::of_feature(&layer.name, &properties) else {
+                    continue;
+                };
+                let Some(distance) =
+                    feature_distance(feature.r#type(), &decode_geometry(&feature.geometry), point)
+                else {
+                    continue;
+                };
+                let distance_m = distance / extent * meters_per_tile;
+                if distance_m > level.max_distance_m() {
+                    continue;
+                }
+
+                let name = if level == Level::Address {
+                    first_str(&properties, &HOUSENUMBER_KEYS)
+                } else {
+                    first_str(&properties, &["name"])
+                };
+                let Some(name) = name else {
+                    continue;
+                };
+                let candidate = Candidate {
+                    name: name.to_string(),
+                    distance_m,
+                };
+
+                match level {
+                    Level::Address => {
+                        if is_closer(&candidate, self.address.as_ref().map(|(c, _)| c)) {
+                            let street =
+                                first_str(&properties, &ADDR_STREET_KEYS).map(str::to_string);
+                            self.address = Some((candidate, street));
+                        }
+                    }
+                    Level::Street => keep_closest(&mut self.street, candidate),
+                    Level::Neighbourhood => keep_closest(&mut self.neighbourhood, candidate),
+                    Level::Locality => keep_closest(&mut self.locality, candidate),
+                    Level::Region => keep_closest(&mut self.region, candidate),
+                }
+            }
+        }
+    }
+
+    /// `fallback` is the `(id, name)` of the archive covering the coordinate;
+    /// its name stands in for the locality when no place label was found nearby.
+    pub fn finish(self, fallback: Option<(&str, &str)>) -> ReverseGeocodeResult {
+        let street = self.street.map(|c| c.name);
+        let address = self.address.map(|(number, addr_street)| {
+            match addr_street.as_ref().or(street.as_ref()) {
+                Some(street) => format!("{} {}", number.name, street),
+                None => number.name,
+            }
+        });
+        let neighbourhood = self.neighbourhood.map(|c| c.name);
+        let locality = self
+            .locality
+            .map(|c| c.name)
+            .or_else(|| fallback.map(|(_, name)| name.to_string()));
+        let region = self.region.map(|c| c.name);
+
+        let mut parts: Vec<&String> = Vec::new();
+        for part in [
+            address.as_ref().or(street.as_ref()),
+            neighbourhood.as_ref(),
+            locality.as_ref(),
+            region.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if parts.last() != Some(&part) {
+                parts.push(part);
+            }
+        }
+        let label = (!parts.is_empty()).then(|| {
+            parts
+                .iter()
+                .map(|p| p.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+
+        ReverseGeocodeResult {
+            address,
+            street,
+            neighbourhood,
+            locality,
+            region,
+            locality_id: fallback.map(|(id, _)| id.to_string()),
+            label,
+        }
+    }
+}
+
+fn first_str<'a>(properties: &'a JsonValue, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .filter_map(|k| properties.get(k))
+        .find_map(|v| v.as_str().filter(|s| !s.is_empty()))
+}
+
+fn is_closer(candidate: &Candidate, current: Option<&Candidate>) -> bool {
+    current.is_none_or(|c| candidate.distance_m < c.distance_m)
+}
+
+fn keep_closest(slot: &mut Option<Candidate>, candidate: Candidate) {
+    if is_closer(&candidate, slot.as_ref()) {
+        *slot = Some(candidate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::map_mvt::{encode_geometry, Feature, GeomType, Layer, TilePoint, Value};
+    use crate::map::map_query::tile_to_lonlat;
+
+    fn string(v: &str) -> Value {
+        Value {
+            string_value: Some(v.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Geometry type, single-part geometry and `(key, value)` string properties.
+    type TestFeature<'a> = (GeomType, Vec<TilePoint>, &'a [(&'a str, &'a str)]);
+
+    fn layer(name: &str, features: &[TestFeature]) -> Layer {
+        let mut keys: Vec<String> = Vec::new();
+        let mut values = Vec::new();
+        let features = features
+            .iter()
+            .map(|(geom_type, points, properties)| {
+                let mut tags = Vec::new();
+                for (key, value) in properties.iter() {
+                    let key_index = keys.iter().position(|k| k == key).unwrap_or_else(|| {
+                        keys.push(key.to_string());
+                        keys.len() - 1
+                    });
+                    values.push(string(value));
+                    tags.extend([key_index as u32, values.len() as u32 - 1]);
+                }
+                Feature {
+                    id: None,
+                    tags,
+                    r#type: Some(*geom_type as i32),
+                    geometry: encode_geometry(*geom_type, std::slice::from_ref(points)),
+                }
+            })
+            .collect();
+
+        Layer {
+            version: 2,
+            name: name.to_string(),
+            features,
+            keys,
+            values,
+            extent: Some(4096),
+        }
+    }
+
+    #[test]
+    fn collector_picks_nearest_feature_per_level() {
+        // Center of a z14 tile, roughly 2.4 km wide at this latitude.
+        let (z, x, y) = (14, 4745, 5860);
+        let [lon, lat] = tile_to_lonlat([f64::from(x) + 0.5, f64::from(y) + 0.5], z);
+
+        let tile = Tile {
+            layers: vec![
+                layer(
+                    "roads",
+                    &[
+                        (
+                            GeomType::Linestring,
+                            vec![[0, 2060], [4096, 2060]],
+                            &[("kind", "minor_road"), ("name", "Elgin Street")],
+                        ),
+                        (
+                            GeomType::Linestring,
+                            vec![[0, 2300], [4096, 2300]],
+                            &[("kind", "major_road"), ("name", "Bank Street")],
+                        ),
+                    ],
+                ),
+                layer(
+                    "buildings",
+                    &[(
+                        GeomType::Polygon,
+                        vec![[2040, 2040], [2060, 2040], [2060, 2056], [2040, 2056]],
+                        &[("addr_housenumber", "24")],
+                    )],
+                ),
+                layer(
+                    "places",
+                    &[
+                        (
+                            GeomType::Point,
+                            vec![[2500, 2500]],
+                            &[("kind", "neighbourhood"), ("name", "Centretown")],
+                        ),
+                        (
+                            GeomType::Point,
+                            vec![[100, 100]],
+                            &[("kind", "locality"), ("name", "Ottawa")],
+                        ),
+                    ],
+                ),
+            ],
+        };
+
+        let mut collector = GeocodeCollector::new(lon, lat);
+        collector.add_tile(&tile, (z, x, y));
+        let result = collector.finish(None);
+
+        assert_eq!(result.street.as_deref(), Some("Elgin Street"));
+        assert_eq!(result.address.as_deref(), Some("24 Elgin Street"));
+        assert_eq!(result.neighbourhood.as_deref(), Some("Centretown"));
+        assert_eq!(result.locality.as_deref(), Some("Ottawa"));
+        assert_eq!(result.region, None);
+        assert_eq!(
+            result.label.as_deref(),
+            Some("24 Elgin Street, Centretown, Ottawa")
+        );
+    }
+
+    #[test]
+    fn collector_falls_back_to_locality_archive() {
+        let result = GeocodeCollector::new(-75.7, 45.4).finish(Some(("ottawa", "Ottawa")));
+        assert_eq!(result.locality.as_deref(), Some("Ottawa"));
+        assert_eq!(result.locality_id.as_deref(), Some("ottawa"));
+        assert_eq!(result.label.as_deref(), Some("Ottawa"));
+        assert_eq!(result.street, None);
+    }
+}
@@ -0,0 +1,236 @@
+//! Data derived from each vector locality, such as search indexes and road
+//! graphs, kept in memory and persisted on disk per locality.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{Listener, Manager};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+
+use super::map_mvt::{self, Tile};
+use super::map_service;
+use super::map_state::MapState;
+use super::map_types::{LocalityMetadata, SourceKind};
+use super::map_watcher::LOCALITIES_CHANGED_EVENT;
+
+/// A value built from one locality's archive and cached per locality.
+pub trait LocalityArtifact: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Directory under the app data directory the values are stored in.
+    const DIR_NAME: &'static str;
+
+    /// What the values are called in messages, e.g. "search index".
+    const DESCRIPTION: &'static str;
+
+    /// The cache holding the values in the app's state.
+    fn cache(app: &tauri::AppHandle) -> &LocalityCache<Self>;
+
+    /// Whether the value was built from the archive as it is now on disk.
+    fn is_current(&self, file_size: u64, modified_at: Option<u64>) -> bool;
+
+    fn build(
+        locality: &LocalityMetadata,
+        map_state: &tauri::State<'_, MapState>,
+    ) -> impl Future<Output = Result<Self, String>> + Send;
+
+    /// Rebuilds the fields skipped by serialization after loading.
+    fn prepare(&mut self) {}
+
+    /// Runs after every sync, still holding the sync lock.
+    fn synced(_app: &tauri::AppHandle) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+
+pub struct LocalityCache<T> {
+    /// Loaded values keyed by locality id.
+    values: RwLock<HashMap<String, Arc<T>>>,
+
+    /// Serializes synchronization with the loaded localities.
+    sync_lock: Mutex<()>,
+}
+
+impl<T: LocalityArtifact> LocalityCache<T> {
+    pub fn new() -> Self {
+        Self {
+            values: RwLock::new(HashMap::new()),
+            sync_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Arc<T>>> {
+        self.values.read().await
+    }
+
+    /// Loads or builds a value for every vector locality, and drops the
+    /// values of localities that are gone. Values are rebuilt only when the
+    /// archive's size or modification time changes.
+    async fn sync(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let map_state = app.state::<MapState>();
+        let _sync = self.sync_lock.lock().await;
+
+        let dir = cache_dir::<T>(app)?;
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            format!(
+                "Failed to create {} directory '{}': {}",
+                T::DESCRIPTION,
+                dir.display(),
+                e
+            )
+        })?;
+
+        let localities: Vec<LocalityMetadata> = map_state
+            .locality_metadata
+            .read()
+            .await
+            .values()
+            .filter(|l| l.source_kind == SourceKind::Vector)
+            .cloned()
+            .collect();
+
+        self.values
+            .write()
+            .await
+            .retain(|id, _| localities.iter().any(|l| &l.id == id));
+        remove_stale_files(&dir, &localities);
+
+        for locality in &localities {
+            let is_current = self
+                .values
+                .read()
+                .await
+                .get(&locality.id)
+                .is_some_and(|value| value.is_current(locality.file_size, locality.modified_at));
+            if is_current {
+                continue;
+            }
+
+            let path = dir.join(format!("{}.json", locality.id));
+            let value = match load::<T>(&path) {
+                Some(value) if value.is_current(locality.file_size, locality.modified_at) => value,
+                _ => match T::build(locality, &map_state).await {
+                    Ok(value) => {
+                        if let Err(e) = save(&path, &value) {
+                            eprintln!("Warning: {}", e);
+                        }
+                        value
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "Warning: Failed to build {} for {}: {}",
+                            T::DESCRIPTION,
+                            locality.id,
+                            e
+                        );
+                        continue;
+                    }
+                },
+            };
+
+            self.values
+                .write()
+                .await
+                .insert(locality.id.clone(), Arc::new(value));
+        }
+
+        T::synced(app).await;
+        Ok(())
+    }
+}
+
+impl<T: LocalityArtifact> Default for LocalityCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn cache_dir<T: LocalityArtifact>(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|p| p.join(T::DIR_NAME))
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+/// Brings the cached values in line with the loaded localities in the background.
+pub fn spawn_sync<T: LocalityArtifact>(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = T::cache(&app).sync(&app).await {
+            eprintln!("Warning: Failed to update {}: {}", T::DESCRIPTION, e);
+        }
+    });
+}
+
+/// Re-syncs the cached values whenever the pmtiles directory changes.
+pub fn listen_for_locality_changes<T: LocalityArtifact>(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    app.listen(LOCALITIES_CHANGED_EVENT, move |_| spawn_sync::<T>(&handle));
+}
+
+/// Decodes every tile of a locality at zoom `z`, skipping undecodable ones.
+pub async fn visit_locality_tiles(
+    locality_id: &str,
+    z: u8,
+    map_state: &tauri::State<'_, MapState>,
+    mut visit: impl FnMut(&Tile, (u8, u32, u32)) + Send,
+) -> Result<(), String> {
+    let coords = map_service::list_locality_tiles(locality_id, z, map_state).await?;
+
+    for coord in coords {
+        let (x, y) = (coord.x(), coord.y());
+        let Some(data) =
+            map_service::get_tile_from_locality(z, x, y, locality_id, map_state).await?
+        else {
+            continue;
+        };
+
+        match map_mvt::decode_tile(&data) {
+            Ok(tile) => visit(&tile, (z, x, y)),
+            Err(e) => eprintln!("Warning: Skipping tile {}/{}/{}: {}", z, x, y, e),
+        }
+    }
+
+    Ok(())
+}
+
+fn load<T: LocalityArtifact>(path: &Path) -> Option<T> {
+    let data = std::fs::read(path).ok()?;
+    let mut value: T = serde_json::from_slice(&data).ok()?;
+    value.prepare();
+    Some(value)
+}
+
+fn save<T: LocalityArtifact>(path: &Path, value: &T) -> Result<(), String> {
+    let data = serde_json::to_vec(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", T::DESCRIPTION, e))?;
+    let tmp_path = path.with_extension("json.tmp");
+
+    std::fs::write(&tmp_path, data)
+        .and_then(|_| std::fs::rename(&tmp_path, path))
+        .map_err(|e| {
+            format!(
+                "Failed to write {} '{}': {}",
+                T::DESCRIPTION,
+                path.display(),
+                e
+            )
+        })
+}
+
+fn remove_stale_files(dir: &Path, localities: &[LocalityMetadata]) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let is_stale = path.extension().is_some_and(|ext| ext == "json")
+            && path
+                .file_stem()
+                .is_some_and(|stem| !localities.iter().any(|l| l.id.as_str() == stem));
+        if is_stale {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,882 @@
+//! Minimal Mapbox Vector Tile (v2.1) codec used to composite tiles coming
+//! from several localities into a single tile, and to cut overzoomed tiles
+//! out of an archive's deepest zoom level.
+
+use prost::Message;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+const DEFAULT_LAYER_VERSION: u32 = 2;
+
+const CMD_MOVE_TO: u32 = 1;
+const CMD_LINE_TO: u32 = 2;
+const CMD_CLOSE_PATH: u32 = 7;
+
+/// Share of the extent kept around an overzoomed tile so that strokes and
+/// polygon edges don't show seams along tile boundaries.
+const OVERZOOM_BUFFER_RATIO: i64 = 64;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Tile {
+    #[prost(message, repeated, tag = "3")]
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Layer {
+    #[prost(uint32, required, tag = "15")]
+    pub version: u32,
+    #[prost(string, required, tag = "1")]
+    pub name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub features: Vec<Feature>,
+    #[prost(string, repeated, tag = "3")]
+    pub keys: Vec<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub values: Vec<Value>,
+    #[prost(uint32, optional, tag = "5", default = "4096")]
+    pub extent: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Feature {
+    #[prost(uint64, optional, tag = "1")]
+    pub id: Option<u64>,
+    #[prost(uint32, repeated, packed = "true", tag = "2")]
+    pub tags: Vec<u32>,
+    #[prost(enumeration = "GeomType", optional, tag = "3")]
+    pub r#type: Option<i32>,
+    #[prost(uint32, repeated, packed = "true", tag = "4")]
+    pub geometry: Vec<u32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Value {
+    #[prost(string, optional, tag = "1")]
+    pub string_value: Option<String>,
+    #[prost(float, optional, tag = "2")]
+    pub float_value: Option<f32>,
+    #[prost(double, optional, tag = "3")]
+    pub double_value: Option<f64>,
+    #[prost(int64, optional, tag = "4")]
+    pub int_value: Option<i64>,
+    #[prost(uint64, optional, tag = "5")]
+    pub uint_value: Option<u64>,
+    #[prost(sint64, optional, tag = "6")]
+    pub sint_value: Option<i64>,
+    #[prost(bool, optional, tag = "7")]
+    pub bool_value: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum GeomType {
+    Unknown = 0,
+    Point = 1,
+    Linestring = 2,
+    Polygon = 3,
+}
+
+pub type TilePoint = [i32; 2];
+
+/// Hashable form of a layer value, used to de-duplicate the values table.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ValueKey {
+    String(String),
+    Float(u32),
+    Double(u64),
+    Int(i64),
+    Uint(u64),
+    Sint(i64),
+    Bool(bool),
+    Empty,
+}
+
+impl From<&Value> for ValueKey {
+    fn from(value: &Value) -> Self {
+        if let Some(v) = &value.string_value {
+            ValueKey::String(v.clone())
+        } else if let Some(v) = value.float_value {
+            ValueKey::Float(v.to_bits())
+        } else if let Some(v) = value.double_value {
+            ValueKey::Double(v.to_bits())
+        } else if let Some(v) = value.int_value {
+            ValueKey::Int(v)
+        } else if let Some(v) = value.uint_value {
+            ValueKey::Uint(v)
+        } else if let Some(v) = value.sint_value {
+            ValueKey::Sint(v)
+        } else if let Some(v) = value.bool_value {
+            ValueKey::Bool(v)
+        } else {
+            ValueKey::Empty
+        }
+    }
+}
+
+pub fn decode_tile(data: &[u8]) -> Result<Tile, String> {
+    Tile::decode(data).map_err(|e| format!("Failed to decode vector tile: {}", e))
+}
+
+pub fn encode_tile(tile: &Tile) -> Vec<u8> {
+    tile.encode_to_vec()
+}
+
+/// Decodes a geometry command stream into parts: a single part holding every
+/// point for point features, one part per line string, or one part per ring
+/// (without the closing point) for polygons.
+pub fn decode_geometry(geometry: &[u32]) -> Vec<Vec<TilePoint>> {
+    let mut parts: Vec<Vec<TilePoint>> = Vec::new();
+    let mut cursor = [0i32; 2];
+    let mut i = 0;
+
+    while i < geometry.len() {
+        let command = geometry[i] & 0x7;
+        let count = (geometry[i] >> 3) as usize;
+        i += 1;
+
+        match command {
+            CMD_MOVE_TO | CMD_LINE_TO => {
+                for n in 0..count {
+                    if i + 1 >= geometry.len() {
+                        return parts;
+                    }
+                    cursor[0] += zigzag_decode(geometry[i]);
+                    cursor[1] += zigzag_decode(geometry[i + 1]);
+                    i += 2;
+
+                    if command == CMD_MOVE_TO && n == 0 {
+                        parts.push(Vec::new());
+                    }
+                    if let Some(part) = parts.last_mut() {
+                        part.push(cursor);
+                    }
+                }
+            }
+            CMD_CLOSE_PATH => {}
+            _ => return parts,
+        }
+    }
+
+    parts
+}
+
+/// Encodes parts produced by [`decode_geometry`] back into a command stream.
+pub fn encode_geometry(geom_type: GeomType, parts: &[Vec<TilePoint>]) -> Vec<u32> {
+    let mut geometry = Vec::new();
+    let mut cursor = [0i32; 2];
+
+    let mut push_point = |geometry: &mut Vec<u32>, point: &TilePoint| {
+        geometry.push(zigzag_encode(point[0] - cursor[0]));
+        geometry.push(zigzag_encode(point[1] - cursor[1]));
+        cursor = *point;
+    };
+
+    match geom_type {
+        GeomType::Point => {
+            let points: Vec<&TilePoint> = parts.iter().flatten().collect();
+            if !points.is_empty() {
+                geometry.push(command(CMD_MOVE_TO, points.len()));
+                for point in points {
+                    push_point(&mut geometry, point);
+                }
+            }
+        }
+        GeomType::Linestring | GeomType::Polygon => {
+            for part in parts.iter().filter(|part| part.len() >= 2) {
+                geometry.push(command(CMD_MOVE_TO, 1));
+                push_point(&mut geometry, &part[0]);
+                geometry.push(command(CMD_LINE_TO, part.len() - 1));
+                for point in &part[1..] {
+                    push_point(&mut geometry, point);
+                }
+                if geom_type == GeomType::Polygon {
+                    geometry.push(command(CMD_CLOSE_PATH, 1));
+                }
+            }
+        }
+        GeomType::Unknown => {}
+    }
+
+    geometry
+}
+
+fn command(id: u32, count: usize) -> u32 {
+    (id & 0x7) | ((count as u32) << 3)
+}
+
+fn zigzag_decode(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+fn zigzag_encode(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Accumulates features from several source layers sharing the same name
+/// into one layer, re-indexing keys and values and dropping duplicates.
+struct LayerBuilder {
+    name: String,
+    extent: u32,
+    version: u32,
+    features: Vec<Feature>,
+    keys: Vec<String>,
+    key_index: HashMap<String, u32>,
+    values: Vec<Value>,
+    value_index: HashMap<ValueKey, u32>,
+    seen_ids: HashSet<u64>,
+    seen_signatures: HashSet<u64>,
+}
+
+impl LayerBuilder {
+    fn new(name: &str, extent: u32, version: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            extent,
+            version,
+            features: Vec::new(),
+            keys: Vec::new(),
+            key_index: HashMap::new(),
+            values: Vec::new(),
+            value_index: HashMap::new(),
+            seen_ids: HashSet::new(),
+            seen_signatures: HashSet::new(),
+        }
+    }
+
+    fn add_layer(&mut self, layer: &Layer) {
+        let scale = self.extent as f64 / layer.extent() as f64;
+
+        for feature in &layer.features {
+            let geometry = if layer.extent() == self.extent {
+                feature.geometry.clone()
+            } else {
+                rescale_geometry(feature, scale)
+            };
+
+            let mut tags = Vec::with_capacity(feature.tags.len());
+            for pair in feature.tags.chunks_exact(2) {
+                let (Some(key), Some(value)) = (
+                    layer.keys.get(pair[0] as usize),
+                    layer.values.get(pair[1] as usize),
+                ) else {
+                    continue;
+                };
+                tags.push(self.key_id(key));
+                tags.push(self.value_id(value));
+            }
+
+            let is_duplicate = match feature.id {
+                Some(id) => !self.seen_ids.insert(id),
+                None => !self.seen_signatures.insert(feature_signature(
+                    feature.r#type,
+                    &geometry,
+                    &tags,
+                )),
+            };
+            if is_duplicate {
+                continue;
+            }
+
+            self.features.push(Feature {
+                id: feature.id,
+                tags,
+                r#type: feature.r#type,
+                geometry,
+            });
+        }
+    }
+
+    fn key_id(&mut self, key: &str) -> u32 {
+        if let Some(id) = self.key_index.get(key) {
+            return *id;
+        }
+        let id = self.keys.len() as u32;
+        self.keys.push(key.to_string());
+        self.key_index.insert(key.to_string(), id);
+        id
+    }
+
+    fn value_id(&mut self, value: &Value) -> u32 {
+        let key = ValueKey::from(value);
+        if let Some(id) = self.value_index.get(&key) {
+            return *id;
+        }
+        let id = self.values.len() as u32;
+        self.values.push(value.clone());
+        self.value_index.insert(key, id);
+        id
+    }
+
+    fn build(self) -> Layer {
+        Layer {
+            version: self.version,
+            name: self.name,
+            features: self.features,
+            keys: self.keys,
+            values: self.values,
+            extent: Some(self.extent),
+        }
+    }
+}
+
+fn rescale_geometry(feature: &Feature, scale: f64) -> Vec<u32> {
+    let parts: Vec<Vec<TilePoint>> = decode_geometry(&feature.geometry)
+        .into_iter()
+        .map(|part| {
+            part.into_iter()
+                .map(|[x, y]| {
+                    [
+                        (x as f64 * scale).round() as i32,
+                        (y as f64 * scale).round() as i32,
+                    ]
+                })
+                .collect()
+        })
+        .collect();
+    encode_geometry(feature.r#type(), &parts)
+}
+
+fn feature_signature(geom_type: Option<i32>, geometry: &[u32], tags: &[u32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    geom_type.hash(&mut hasher);
+    geometry.hash(&mut hasher);
+    tags.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Merges several encoded vector tiles into one. Layers are matched by name,
+/// and within a layer features are de-duplicated by id, or by geometry and
+/// properties when they have no id. Earlier tiles win.
+pub fn merge_tiles(tiles: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+    let mut builders: Vec<LayerBuilder> = Vec::new();
+
+    for data in tiles {
+        let tile = decode_tile(data)?;
+
+        for layer in &tile.layers {
+            let index = match builders.iter().position(|b| b.name == layer.name) {
+                Some(index) => index,
+                None => {
+                    let version = if layer.version > 0 {
+                        layer.version
+                    } else {
+                        DEFAULT_LAYER_VERSION
+                    };
+                    builders.push(LayerBuilder::new(&layer.name, layer.extent(), version));
+                    builders.len() - 1
+                }
+            };
+            builders[index].add_layer(layer);
+        }
+    }
+
+    let merged = Tile {
+        layers: builders.into_iter().map(LayerBuilder::build).collect(),
+    };
+
+    Ok(encode_tile(&merged))
+}
+
+/// Cuts the child tile `(child_x, child_y)`, `dz` levels below `data`, out
+/// of an encoded tile. Coordinates are scaled up by `2^dz` and geometry is
+/// clipped to the child extent plus a small buffer; features left without
+/// geometry and layers left without features are dropped.
+pub fn overzoom_tile(data: &[u8], dz: u8, child_x: u32, child_y: u32) -> Result<Vec<u8>, String> {
+    let tile = decode_tile(data)?;
+    let scale = 1i64 << dz;
+
+    let layers = tile
+        .layers
+        .into_iter()
+        .filter_map(|layer| {
+            let extent = i64::from(layer.extent());
+            let buffer = extent / OVERZOOM_BUFFER_RATIO;
+            let clip = ClipBox {
+                min: -buffer as f64,
+                max: (extent + buffer) as f64,
+            };
+            let origin = [i64::from(child_x) * extent, i64::from(child_y) * extent];
+
+            let features: Vec<Feature> = layer
+                .features
+                .iter()
+                .filter_map(|feature| {
+                    let geom_type = feature.r#type();
+                    let parts: Vec<Vec<[f64; 2]>> = decode_geometry(&feature.geometry)
+                        .into_iter()
+                        .map(|part| {
+                            part.into_iter()
+                                .map(|[x, y]| {
+                                    [
+                                        (i64::from(x) * scale - origin[0]) as f64,
+                                        (i64::from(y) * scale - origin[1]) as f64,
+                                    ]
+                                })
+                                .collect()
+                        })
+                        .collect();
+
+                    let clipped = clip.clip_parts(geom_type, parts);
+                    if clipped.is_empty() {
+                        return None;
+                    }
+
+                    Some(Feature {
+                        geometry: encode_geometry(geom_type, &clipped),
+                        ..feature.clone()
+                    })
+                })
+                .collect();
+
+            if features.is_empty() {
+                None
+            } else {
+                Some(Layer { features, ..layer })
+            }
+        })
+        .collect();
+
+    Ok(encode_tile(&Tile { layers }))
+}
+
+/// Clips a line string to the square `[min, max]` on both axes, splitting it
+/// wherever it leaves the window.
+pub fn clip_line(line: &[[f64; 2]], min: f64, max: f64) -> Vec<Vec<[f64; 2]>> {
+    ClipBox { min, max }.clip_line(line)
+}
+
+/// Square clipping window, in tile units, shared by both axes.
+#[derive(Clone, Copy)]
+struct ClipBox {
+    min: f64,
+    max: f64,
+}
+
+impl ClipBox {
+    fn contains(&self, [x, y]: [f64; 2]) -> bool {
+        (self.min..=self.max).contains(&x) && (self.min..=self.max).contains(&y)
+    }
+
+    fn clip_parts(&self, geom_type: GeomType, parts: Vec<Vec<[f64; 2]>>) -> Vec<Vec<TilePoint>> {
+        match geom_type {
+            GeomType::Point => {
+                let points: Vec<TilePoint> = parts
+                    .into_iter()
+                    .flatten()
+                    .filter(|point| self.contains(*point))
+                    .map(round_point)
+                    .collect();
+                if points.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![points]
+                }
+            }
+            GeomType::Linestring => parts
+                .iter()
+                .flat_map(|line| self.clip_line(line))
+                .filter_map(|line| dedup_rounded(&line, 2))
+                .collect(),
+            GeomType::Polygon => self.clip_polygon(&parts),
+            GeomType::Unknown => Vec::new(),
+        }
+    }
+
+    /// Clips a line string, splitting it wherever it leaves the window.
+    fn clip_line(&self, line: &[[f64; 2]]) -> Vec<Vec<[f64; 2]>> {
+        let mut lines = Vec::new();
+        let mut current: Vec<[f64; 2]> = Vec::new();
+
+        for segment in line.windows(2) {
+            match self.clip_segment(segment[0], segment[1]) {
+                Some((start, end)) => {
+                    if current.last() != Some(&start) {
+                        if current.len() >= 2 {
+                            lines.push(std::mem::take(&mut current));
+                        }
+                        current.clear();
+                        current.push(start);
+                    }
+                    current.push(end);
+                    if end != segment[1] {
+                        lines.push(std::mem::take(&mut current));
+                    }
+                }
+                None => {
+                    if current.len() >= 2 {
+                        lines.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                }
+            }
+        }
+        if current.len() >= 2 {
+            lines.push(current);
+        }
+
+        lines
+    }
+
+    /// Liang-Barsky segment clipping.
+    fn clip_segment(&self, a: [f64; 2], b: [f64; 2]) -> Option<([f64; 2], [f64; 2])> {
+        let delta = [b[0] - a[0], b[1] - a[1]];
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+
+        for axis in 0..2 {
+            for (p, q) in [
+                (-delta[axis], a[axis] - self.min),
+                (delta[axis], self.max - a[axis]),
+            ] {
+                if p == 0.0 {
+                    if q < 0.0 {
+                        return None;
+                    }
+                } else {
+                    let t = q / p;
+                    if p < 0.0 {
+                        t0 = t0.max(t);
+                    } else {
+                        t1 = t1.min(t);
+                    }
+                }
+            }
+        }
+
+        if t0 > t1 {
+            return None;
+        }
+
+        let at = |t: f64| [a[0] + t * delta[0], a[1] + t * delta[1]];
+        Some((
+            if t0 > 0.0 { at(t0) } else { a },
+            if t1 < 1.0 { at(t1) } else { b },
+        ))
+    }
+
+    /// Clips each ring with Sutherland-Hodgman. Interior rings following an
+    /// exterior ring that was clipped away are dropped with it.
+    fn clip_polygon(&self, rings: &[Vec<[f64; 2]>]) -> Vec<Vec<TilePoint>> {
+        let mut clipped = Vec::new();
+        let mut keep_interiors = false;
+
+        for ring in rings {
+            let is_exterior = ring_area(ring) > 0.0;
+            if !is_exterior && !keep_interiors {
+                continue;
+            }
+
+            let result = self
+                .clip_ring(ring)
+                .and_then(|ring| dedup_rounded(&ring, 3))
+                .filter(|ring| ring_area_rounded(ring) != 0);
+
+            if is_exterior {
+                keep_interiors = result.is_some();
+            }
+            clipped.extend(result);
+        }
+
+        clipped
+    }
+
+    fn clip_ring(&self, ring: &[[f64; 2]]) -> Option<Vec<[f64; 2]>> {
+        let mut output = ring.to_vec();
+
+        for axis in 0..2 {
+            for (bound, keep_above) in [(self.min, true), (self.max, false)] {
+                let inside = |p: &[f64; 2]| {
+                    if keep_above {
+                        p[axis] >= bound
+                    } else {
+                        p[axis] <= bound
+                    }
+                };
+                let input = std::mem::take(&mut output);
+                let mut previous = input.last().copied()?;
+
+                for point in input {
+                    if inside(&point) != inside(&previous) {
+                        let t = (bound - previous[axis]) / (point[axis] - previous[axis]);
+                        output.push([
+                            previous[0] + t * (point[0] - previous[0]),
+                            previous[1] + t * (point[1] - previous[1]),
+                        ]);
+                    }
+                    if inside(&point) {
+                        output.push(point);
+                    }
+                    previous = point;
+                }
+            }
+        }
+
+        (!output.is_empty()).then_some(output)
+    }
+}
+
+fn round_point([x, y]: [f64; 2]) -> TilePoint {
+    [x.round() as i32, y.round() as i32]
+}
+
+/// Rounds to tile units and drops repeated points, keeping the part only if
+/// it still has at least `min_points` points.
+fn dedup_rounded(part: &[[f64; 2]], min_points: usize) -> Option<Vec<TilePoint>> {
+    let mut points: Vec<TilePoint> = part.iter().copied().map(round_point).collect();
+    points.dedup();
+    (points.len() >= min_points).then_some(points)
+}
+
+/// Twice the signed ring area; positive for exterior rings in tile
+/// coordinates (y pointing down).
+fn ring_area(ring: &[[f64; 2]]) -> f64 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum()
+}
+
+fn ring_area_rounded(ring: &[TilePoint]) -> i64 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            i64::from(a[0]) * i64::from(b[1]) - i64::from(b[0]) * i64::from(a[1])
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string_value(v: &str) -> Value {
+        Value {
+            string_value: Some(v.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn point_feature(id: Option<u64>, x: i32, y: i32, tags: Vec<u32>) -> Feature {
+        Feature {
+            id,
+            tags,
+            r#type: Some(GeomType::Point as i32),
+            geometry: encode_geometry(GeomType::Point, &[vec![[x, y]]]),
+        }
+    }
+
+    fn encoded_tile(layers: Vec<Layer>) -> Vec<u8> {
+        encode_tile(&Tile { layers })
+    }
+
+    #[test]
+    fn geometry_round_trip() {
+        let rings = vec![
+            vec![[0, 0], [10, 0], [10, 10], [0, 10]],
+            vec![[2, 2], [2, 4], [4, 4]],
+        ];
+        let encoded = encode_geometry(GeomType::Polygon, &rings);
+        assert_eq!(decode_geometry(&encoded), rings);
+
+        let line = vec![vec![[5, 5], [-3, 8], [100, 4000]]];
+        let encoded = encode_geometry(GeomType::Linestring, &line);
+        assert_eq!(decode_geometry(&encoded), line);
+
+        let points = vec![vec![[1, 1], [2, 3]]];
+        let encoded = encode_geometry(GeomType::Point, &points);
+        assert_eq!(decode_geometry(&encoded), points);
+    }
+
+    #[test]
+    fn merge_tiles_deduplicates_by_feature_id() {
+        let ottawa = encoded_tile(vec![Layer {
+            version: 2,
+            name: "places".to_string(),
+            features: vec![
+                point_feature(Some(1), 10, 10, vec![0, 0]),
+                point_feature(Some(2), 20, 20, vec![0, 1]),
+            ],
+            keys: vec!["name".to_string()],
+            values: vec![string_value("Ottawa"), string_value("Bridge")],
+            extent: Some(4096),
+        }]);
+        let gatineau = encoded_tile(vec![Layer {
+            version: 2,
+            name: "places".to_string(),
+            features: vec![
+                point_feature(Some(2), 20, 20, vec![0, 0]),
+                point_feature(Some(3), 30, 30, vec![0, 1]),
+            ],
+            keys: vec!["name".to_string()],
+            values: vec![string_value("Bridge"), string_value("Gatineau")],
+            extent: Some(4096),
+        }]);
+
+        let merged = decode_tile(&merge_tiles(&[ottawa, gatineau]).unwrap()).unwrap();
+
+        assert_eq!(merged.layers.len(), 1);
+        let layer = &merged.layers[0];
+        let ids: Vec<_> = layer.features.iter().map(|f| f.id).collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(layer.keys, vec!["name".to_string()]);
+        assert_eq!(layer.values.len(), 3);
+
+        let names: Vec<_> = layer
+            .features
+            .iter()
+            .map(|f| {
+                layer.values[f.tags[1] as usize]
+                    .string_value
+                    .clone()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(names, vec!["Ottawa", "Bridge", "Gatineau"]);
+    }
+
+    #[test]
+    fn merge_tiles_deduplicates_features_without_id() {
+        let layer = Layer {
+            version: 2,
+            name: "water".to_string(),
+            features: vec![point_feature(None, 5, 5, vec![])],
+            keys: vec![],
+            values: vec![],
+            extent: Some(4096),
+        };
+        let other = Layer {
+            name: "roads".to_string(),
+            ..layer.clone()
+        };
+
+        let merged = decode_tile(
+            &merge_tiles(&[
+                encoded_tile(vec![layer.clone()]),
+                encoded_tile(vec![layer, other]),
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(merged.layers.len(), 2);
+        assert_eq!(merged.layers[0].features.len(), 1);
+        assert_eq!(merged.layers[1].name, "roads");
+    }
+
+    #[test]
+    fn merge_tiles_rescales_mismatched_extents() {
+        let make = |extent, x| Layer {
+            version: 2,
+            name: "pois".to_string(),
+            features: vec![point_feature(None, x, x, vec![])],
+            keys: vec![],
+            values: vec![],
+            extent: Some(extent),
+        };
+
+        let merged = decode_tile(
+            &merge_tiles(&[
+                encoded_tile(vec![make(4096, 100)]),
+                encoded_tile(vec![make(512, 100)]),
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+
+        let layer = &merged.layers[0];
+        assert_eq!(layer.extent(), 4096);
+        assert_eq!(
+            decode_geometry(&layer.features[1].geometry),
+            vec![vec![[800, 800]]]
+        );
+    }
+
+    fn single_layer_tile(features: Vec<Feature>) -> Vec<u8> {
+        encoded_tile(vec![Layer {
+            version: 2,
+            name: "layer".to_string(),
+            features,
+            keys: vec![],
+            values: vec![],
+            extent: Some(4096),
+        }])
+    }
+
+    fn overzoomed_parts(data: &[u8], dz: u8, x: u32, y: u32) -> Vec<Vec<Vec<TilePoint>>> {
+        decode_tile(&overzoom_tile(data, dz, x, y).unwrap())
+            .unwrap()
+            .layers
+            .iter()
+            .flat_map(|layer| &layer.features)
+            .map(|f| decode_geometry(&f.geometry))
+            .collect()
+    }
+
+    #[test]
+    fn overzoom_tile_scales_and_filters_points() {
+        let data = single_layer_tile(vec![
+            point_feature(Some(1), 100, 100, vec![]),
+            point_feature(Some(2), 3000, 100, vec![]),
+        ]);
+
+        assert_eq!(
+            overzoomed_parts(&data, 1, 0, 0),
+            vec![vec![vec![[200, 200]]]]
+        );
+        assert_eq!(
+            overzoomed_parts(&data, 1, 1, 0),
+            vec![vec![vec![[1904, 200]]]]
+        );
+        assert!(overzoomed_parts(&data, 1, 1, 1).is_empty());
+    }
+
+    #[test]
+    fn overzoom_tile_clips_lines_with_buffer() {
+        let line = vec![vec![[0, 1024], [4096, 1024]]];
+        let data = single_layer_tile(vec![Feature {
+            id: Some(1),
+            tags: vec![],
+            r#type: Some(GeomType::Linestring as i32),
+            geometry: encode_geometry(GeomType::Linestring, &line),
+        }]);
+
+        assert_eq!(
+            overzoomed_parts(&data, 1, 0, 0),
+            vec![vec![vec![[0, 2048], [4160, 2048]]]]
+        );
+        assert_eq!(
+            overzoomed_parts(&data, 1, 1, 0),
+            vec![vec![vec![[-64, 2048], [4096, 2048]]]]
+        );
+        assert!(overzoomed_parts(&data, 1, 0, 1).is_empty());
+    }
+
+    #[test]
+    fn overzoom_tile_clips_polygons() {
+        let square = vec![
+            vec![[0, 0], [4096, 0], [4096, 4096], [0, 4096]],
+            vec![[3500, 3500], [3500, 3600], [3600, 3600], [3600, 3500]],
+        ];
+        let data = single_layer_tile(vec![Feature {
+            id: Some(1),
+            tags: vec![],
+            r#type: Some(GeomType::Polygon as i32),
+            geometry: encode_geometry(GeomType::Polygon, &square),
+        }]);
+
+        let parts = overzoomed_parts(&data, 1, 0, 0);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].len(), 1);
+        let ring = &parts[0][0];
+        assert!(ring.iter().all(|p| p[0] >= -64 && p[0] <= 4160));
+        assert!(ring.contains(&[4160, 4160]));
+
+        let parts = overzoomed_parts(&data, 1, 1, 1);
+        assert_eq!(parts[0].len(), 2);
+    }
+}
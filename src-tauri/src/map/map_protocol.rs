@@ -0,0 +1,211 @@
+use pmtiles::TileType;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+
+use super::map_service;
+use super::map_state::MapState;
+use super::map_types::SourceKind;
+
+/// URI scheme serving tiles to the webview, e.g. `anymaps://localhost/tiles/{z}/{x}/{y}`
+/// (`http://anymaps.localhost/tiles/{z}/{x}/{y}` on Windows and Android).
+/// Raster and terrain archives are served under `/raster` and `/terrain`.
+pub const TILE_SCHEME: &str = "anymaps";
+
+const TILE_CACHE_CONTROL: &str = "public, max-age=3600";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TileRequest {
+    source: SourceKind,
+    z: u8,
+    x: u32,
+    y: u32,
+}
+
+pub fn handle_tile_request<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+
+    tauri::async_runtime::spawn(async move {
+        let response = match parse_tile_path(request.uri().path()) {
+            Some(tile) => serve_tile(&app, tile, &request).await,
+            None => empty_response(StatusCode::NOT_FOUND),
+        };
+        responder.respond(response);
+    });
+}
+
+async fn serve_tile<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    tile: TileRequest,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let Some(state) = app.try_state::<MapState>() else {
+        return empty_response(StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    match map_service::get_tile(tile.source, tile.z, tile.x, tile.y, &state).await {
+        Ok(Some(data)) => {
+            let content_type = match tile.source {
+                SourceKind::Vector => TileType::Mvt.content_type(),
+                SourceKind::Raster | SourceKind::Terrain => image_content_type(&data),
+            };
+            tile_response(data, content_type, if_none_match(request))
+        }
+        Ok(None) => empty_response(StatusCode::NO_CONTENT),
+        Err(e) => {
+            eprintln!(
+                "Warning: Failed to serve {:?} tile {}/{}/{}: {}",
+                tile.source, tile.z, tile.x, tile.y, e
+            );
+            empty_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Tiles come back decompressed from `get_tile`, so the body is sent as-is
+/// without a `Content-Encoding` header.
+fn tile_response(
+    data: Vec<u8>,
+    content_type: &str,
+    if_none_match: Option<&str>,
+) -> Response<Vec<u8>> {
+    let etag = tile_etag(&data);
+
+    let (status, body) = if if_none_match.is_some_and(|tag| tag == etag) {
+        (StatusCode::NOT_MODIFIED, Vec::new())
+    } else {
+        (StatusCode::OK, data)
+    };
+
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, TILE_CACHE_CONTROL)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_EXPOSE_HEADERS, "ETag")
+        .body(body)
+        .unwrap_or_else(|_| empty_response(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+fn empty_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        header::HeaderValue::from_static("*"),
+    );
+    response
+}
+
+fn if_none_match(request: &Request<Vec<u8>>) -> Option<&str> {
+    request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+}
+
+fn parse_tile_path(path: &str) -> Option<TileRequest> {
+    let mut segments = path.trim_matches('/').split('/');
+
+    let source = match segments.next()? {
+        "tiles" => SourceKind::Vector,
+        "raster" => SourceKind::Raster,
+        "terrain" => SourceKind::Terrain,
+        _ => return None,
+    };
+
+    let z = segments.next()?.parse().ok()?;
+    let x = segments.next()?.parse().ok()?;
+    let y = segments.next()?;
+    let y = y.split_once('.').map_or(y, |(y, _ext)| y).parse().ok()?;
+
+    if segments.next().is_some() {
+        return None;
+    }
+
+    Some(TileRequest { source, z, x, y })
+}
+
+/// Image archives may mix formats across localities, so the content type is
+/// sniffed from the tile itself.
+fn image_content_type(data: &[u8]) -> &'static str {
+    let tile_type = if data.starts_with(b"\x89PNG") {
+        TileType::Png
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        TileType::Jpeg
+    } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        TileType::Webp
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" && &data[8..12] == b"avif" {
+        TileType::Avif
+    } else {
+        return "application/octet-stream";
+    };
+    tile_type.content_type()
+}
+
+/// Strong ETag derived from the tile bytes (64-bit FNV-1a), stable across restarts.
+fn tile_etag(data: &[u8]) -> String {
+    let hash = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("\"{:016x}\"", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tile_path_valid() {
+        assert_eq!(
+            parse_tile_path("/tiles/14/4823/5890"),
+            Some(TileRequest {
+                source: SourceKind::Vector,
+                z: 14,
+                x: 4823,
+                y: 5890
+            })
+        );
+        assert_eq!(
+            parse_tile_path("/terrain/3/1/2.png"),
+            Some(TileRequest {
+                source: SourceKind::Terrain,
+                z: 3,
+                x: 1,
+                y: 2
+            })
+        );
+    }
+
+    #[test]
+    fn parse_tile_path_invalid() {
+        assert_eq!(parse_tile_path("/tiles/14/4823"), None);
+        assert_eq!(parse_tile_path("/other/14/4823/5890"), None);
+        assert_eq!(parse_tile_path("/tiles/a/b/c"), None);
+        assert_eq!(parse_tile_path("/tiles/1/2/3/4"), None);
+    }
+
+    #[test]
+    fn image_content_type_sniffs_format() {
+        assert_eq!(image_content_type(b"\x89PNG\r\n\x1a\n"), "image/png");
+        assert_eq!(image_content_type(b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        assert_eq!(image_content_type(b"tile"), "application/octet-stream");
+    }
+
+    #[test]
+    fn tile_response_honours_if_none_match() {
+        let etag = tile_etag(b"tile");
+
+        let response = tile_response(b"tile".to_vec(), "application/x-protobuf", None);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), b"tile");
+
+        let response = tile_response(b"tile".to_vec(), "application/x-protobuf", Some(&etag));
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! Hit-testing of decoded vector tiles, used to answer "what is here?"
+//! queries offline, and conversion of the matching features to GeoJSON.
+
+use serde_json::{json, Map, Value as JsonValue};
+use std::f64::consts::PI;
+
+use super::map_mvt::{decode_geometry, GeomType, Layer, Tile, TilePoint, Value};
+
+/// Size of a vector tile on screen, in CSS pixels, used to convert a query
+/// radius into tile units.
+const TILE_SIZE_PX: f64 = 512.0;
+
+/// Fractional tile position of a coordinate at zoom `z` (Web Mercator).
+pub fn lonlat_to_tile(lon: f64, lat: f64, z: u8) -> [f64; 2] {
+    let n = 2f64.powi(i32::from(z));
+    let lat = lat.clamp(-85.051_128_78, 85.051_128_78).to_radians();
+    [
+        (lon + 180.0) / 360.0 * n,
+        (1.0 - lat.tan().asinh() / PI) / 2.0 * n,
+    ]
+}
+
+/// Inverse of [`lonlat_to_tile`].
+pub fn tile_to_lonlat([tx, ty]: [f64; 2], z: u8) -> [f64; 2] {
+    let n = 2f64.powi(i32::from(z));
+    [
+        tx / n * 360.0 - 180.0,
+        (PI * (1.0 - 2.0 * ty / n)).sinh().atan().to_degrees(),
+    ]
+}
+
+/// Great-circle distance between two coordinates, in meters.
+pub fn haversine_distance_m(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+}
+
+/// Returns the features of tile `z/x/y` whose geometry contains `(lon, lat)`
+/// or lies within `radius_px` of it, as GeoJSON features. Points and lines
+/// come first, closest first, followed by the areas containing the point.
+pub fn query_tile(
+    tile: &Tile,
+    (z, x, y): (u8, u32, u32),
+    (lon, lat): (f64, f64),
+    radius_px: f64,
+) -> Vec<JsonValue> {
+    let [tx, ty] = lonlat_to_tile(lon, lat, z);
+    let mut hits: Vec<(bool, f64, JsonValue)> = Vec::new();
+
+    for layer in &tile.layers {
+        let extent = f64::from(layer.extent());
+        let point = [(tx - f64::from(x)) * extent, (ty - f64::from(y)) * extent];
+        let radius = radius_px.max(0.0) * extent / TILE_SIZE_PX;
+        let to_lonlat = |[px, py]: TilePoint| {
+            tile_to_lonlat(
+                [
+                    f64::from(x) + f64::from(px) / extent,
+                    f64::from(y) + f64::from(py) / extent,
+                ],
+                z,
+            )
+        };
+
+        for feature in &layer.features {
+            let geom_type = feature.r#type();
+            let parts = decode_geometry(&feature.geometry);
+
+            let Some(distance) = feature_distance(geom_type, &parts, point) else {
+                continue;
+            };
+            if distance > radius {
+                continue;
+            }
+
+            let Some(geometry) = geojson_geometry(geom_type, &parts, to_lonlat) else {
+                continue;
+            };
+
+            let mut geojson = json!({
+                "type": "Feature",
+                "layer": layer.name,
+                "geometry": geometry,
+                "properties": feature_properties(layer, &feature.tags),
+            });
+            if let Some(id) = feature.id {
+                geojson["id"] = json!(id);
+            }
+            let inside_area = geom_type == GeomType::Polygon && distance == 0.0;
+            hits.push((inside_area, distance, geojson));
+        }
+    }
+
+    hits.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    hits.into_iter().map(|(_, _, feature)| feature).collect()
+}
+
+/// Distance in tile units from `point` to the feature; zero inside polygons.
+pub(super) fn feature_distance(geom_type: GeomType, parts: &[Vec<TilePoint>], point: [f64; 2]) -> Option<f64> {
+    let as_f64 = |p: &TilePoint| [f64::from(p[0]), f64::from(p[1])];
+
+    match geom_type {
+        GeomType::Point => parts
+            .iter()
+            .flatten()
+            .map(|p| distance(as_f64(p), point))
+            .min_by(f64::total_cmp),
+        GeomType::Linestring => parts
+            .iter()
+            .flat_map(|line| line.windows(2))
+            .map(|s| segment_distance(point, as_f64(&s[0]), as_f64(&s[1])))
+            .min_by(f64::total_cmp),
+        GeomType::Polygon => {
+            // Even-odd over every ring handles holes and multipolygons alike.
+            let inside = parts
+                .iter()
+                .filter(|ring| ring_contains(ring, point))
+                .count()
+                % 2
+                == 1;
+            if inside {
+                return Some(0.0);
+            }
+            parts
+                .iter()
+                .flat_map(|ring| {
+                    (0..ring.len()).map(move |i| (&ring[i], &ring[(i + 1) % ring.len()]))
+                })
+                .map(|(a, b)| segment_distance(point, as_f64(a), as_f64(b)))
+                .min_by(f64::total_cmp)
+        }
+        GeomType::Unknown => None,
+    }
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+fn segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let length_sq = ab[0] * ab[0] + ab[1] * ab[1];
+    if length_sq == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / length_sq).clamp(0.0, 1.0);
+    distance(p, [a[0] + t * ab[0], a[1] + t * ab[1]])
+}
+
+fn ring_contains(ring: &[TilePoint], [px, py]: [f64; 2]) -> bool {
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+
+    for i in 0..ring.len() {
+        let (xi, yi) = (f64::from(ring[i][0]), f64::from(ring[i][1]));
+        let (xj, yj) = (f64::from(ring[j][0]), f64::from(ring[j][1]));
+        if (yi > py) != (yj > py) && px < (xj - xi) * (py - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+
+    inside
+}
+
+/// Twice the signed ring area; positive for exterior rings (y pointing down).
+fn ring_area(ring: &[TilePoint]) -> i64 {
+    (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            i64::from(a[0]) * i64::from(b[1]) - i64::from(b[0]) * i64::from(a[1])
+        })
+        .sum()
+}
+
+fn geojson_geometry(
+    geom_type: GeomType,
+    parts: &[Vec<TilePoint>],
+    to_lonlat: impl Fn(TilePoint) -> [f64; 2],
+) -> Option<JsonValue> {
+    let line = |part: &[TilePoint]| part.iter().map(|p| to_lonlat(*p)).collect::<Vec<_>>();
+
+    match geom_type {
+        GeomType::Point => {
+            let points: Vec<[f64; 2]> = parts.iter().flatten().map(|p| to_lonlat(*p)).collect();
+            match points.as_slice() {
+                [] => None,
+                [point] => Some(json!({ "type": "Point", "coordinates": point })),
+                _ => Some(json!({ "type": "MultiPoint", "coordinates": points })),
+            }
+        }
+        GeomType::Linestring => {
+            let lines: Vec<Vec<[f64; 2]>> = parts.iter().map(|part| line(part)).collect();
+            match lines.as_slice() {
+                [] => None,
+                [single] => Some(json!({ "type": "LineString", "coordinates": single })),
+                _ => Some(json!({ "type": "MultiLineString", "coordinates": lines })),
+            }
+        }
+        GeomType::Polygon => {
+            let mut polygons: Vec<Vec<Vec<[f64; 2]>>> = Vec::new();
+            for ring in parts.iter().filter(|ring| ring.len() >= 3) {
+                let mut coordinates = line(ring);
+                coordinates.push(coordinates[0]);
+                match polygons.last_mut() {
+                    Some(polygon) if ring_area(ring) < 0 => polygon.push(coordinates),
+                    _ => polygons.push(vec![coordinates]),
+                }
+            }
+            match polygons.as_slice() {
+                [] => None,
+                [single] => Some(json!({ "type": "Polygon", "coordinates": single })),
+                _ => Some(json!({ "type": "MultiPolygon", "coordinates": polygons })),
+            }
+        }
+        GeomType::Unknown => None,
+    }
+}
+
+pub(crate) fn feature_properties(layer: &Layer, tags: &[u32]) -> JsonValue {
+    let properties: Map<String, JsonValue> = tags
+        .chunks_exact(2)
+        .filter_map(|pair| {
+            let key = layer.keys.get(pair[0] as usize)?;
+            let value = layer.values.get(pair[1] as usize)?;
+            Some((key.clone(), value_to_json(value)))
+        })
+        .collect();
+    JsonValue::Object(properties)
+}
+
+fn value_to_json(value: &Value) -> JsonValue {
+    if let Some(v) = &value.string_value {
+        json!(v)
+    } else if let Some(v) = value.float_value {
+        json!(v)
+    } else if let Some(v) = value.double_value {
+        json!(v)
+    } else if let Some(v) = value.int_value.or(value.sint_value) {
+        json!(v)
+    } else if let Some(v) = value.uint_value {
+        json!(v)
+    } else if let Some(v) = value.bool_value {
+        json!(v)
+    } else {
+        JsonValue::Null
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::map_mvt::{encode_geometry, Feature};
+
+    fn feature(id: u64, geom_type: GeomType, parts: &[Vec<TilePoint>]) -> Feature {
+        Feature {
+            id: Some(id),
+            tags: vec![0, id as u32 - 1],
+            r#type: Some(geom_type as i32),
+            geometry: encode_geometry(geom_type, parts),
+        }
+    }
+
+    fn test_tile() -> Tile {
+        let name = |v: &str| Value {
+            string_value: Some(v.to_string()),
+            ..Default::default()
+        };
+        Tile {
+            layers: vec![Layer {
+                version: 2,
+                name: "places".to_string(),
+                features: vec![
+                    feature(1, GeomType::Point, &[vec![[2048, 2048]]]),
+                    feature(2, GeomType::Linestring, &[vec![[0, 2100], [4096, 2100]]]),
+                    feature(
+                        3,
+                        GeomType::Polygon,
+                        &[vec![[1000, 1000], [3000, 1000], [3000, 3000], [1000, 3000]]],
+                    ),
+                    feature(4, GeomType::Point, &[vec![[100, 100]]]),
+                ],
+                keys: vec!["name".to_string()],
+                values: vec![name("Cafe"), name("Main St"), name("Park"), name("Far")],
+                extent: Some(4096),
+            }],
+        }
+    }
+
+    #[test]
+    fn lonlat_tile_round_trip() {
+        let [tx, ty] = lonlat_to_tile(-75.7, 45.4, 14);
+        let [lon, lat] = tile_to_lonlat([tx, ty], 14);
+        assert!((lon - -75.7).abs() < 1e-9);
+        assert!((lat - 45.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn query_tile_returns_nearby_features_before_areas() {
+        // Center of tile 1/0/0
+        let [lon, lat] = tile_to_lonlat([0.5, 0.5], 1);
+
+        let hits = query_tile(&test_tile(), (1, 0, 0), (lon, lat), 10.0);
+        let names: Vec<_> = hits
+            .iter()
+            .map(|f| f["properties"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Cafe", "Main St", "Park"]);
+
+        assert_eq!(hits[0]["geometry"]["type"], "Point");
+        assert_eq!(hits[0]["layer"], "places");
+        assert_eq!(hits[0]["id"], 1);
+        let ring = hits[2]["geometry"]["coordinates"][0].as_array().unwrap();
+        assert_eq!(ring.len(), 5);
+        assert_eq!(ring.first(), ring.last());
+    }
+
+    #[test]
+    fn query_tile_respects_radius() {
+        let [lon, lat] = tile_to_lonlat([0.5, 0.5], 1);
+
+        let hits = query_tile(&test_tile(), (1, 0, 0), (lon, lat), 1.0);
+        let names: Vec<_> = hits
+            .iter()
+            .map(|f| f["properties"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Cafe", "Park"]);
+    }
+}
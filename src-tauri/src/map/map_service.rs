@@ -1,14 +1,31 @@
-use crate::map::map_state::{MapState, SpatialIndexEntry};
+use crate::map::map_geocode::{GeocodeCollector, REVERSE_GEOCODE_ZOOMS};
+use crate::map::map_state::{CachedTile, MapState, SpatialIndexEntry, TileKey};
 use crate::map::map_types::{
-    BoundingBox, CenterPoint, LocalityInfo, LocalityMetadata, MultiPmtilesInfo,
+    BoundingBox, CenterPoint, LocalityChanges, LocalityInfo, LocalityMetadata, MultiPmtilesInfo,
+    ReverseGeocodeResult, SourceKind, VectorLayerInfo,
 };
+use crate::map::{map_mvt, map_query, map_watcher};
 use crate::storage::StorageManager;
-use pmtiles::{AsyncPmTilesReader, MmapBackend, TileCoord};
+use futures_util::StreamExt;
+use pmtiles::tilejson::{tilejson, Bounds, Center, TileJSON, VectorLayer};
+use pmtiles::{AsyncPmTilesReader, Compression, MmapBackend, TileCoord, TileType};
 use rstar::AABB;
-use std::path::PathBuf;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::UNIX_EPOCH;
 use tauri::Manager;
 
+const DEFAULT_LOCALITY_PRIORITY: i32 = 0;
+
+/// Metadata keys holding the archive build date, most specific first.
+const BUILD_DATE_KEYS: &[&str] = &[
+    "build_date",
+    "planetiler:buildtime",
+    "planetiler:osm:osmosisreplicationtime",
+];
+
 pub fn get_pmtiles_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     app.path()
         .app_data_dir()
@@ -28,21 +45,35 @@ pub async fn discover_all_pmtiles_files(
         ));
     }
 
-    let mut entries = std::fs::read_dir(&pmtiles_dir).map_err(|e| {
+    let files = list_pmtiles_files(&pmtiles_dir)?;
+
+    if files.is_empty() {
+        return Err(format!(
+            "No PMTiles files found in '{}'. Files may not have been downloaded.",
+            pmtiles_dir.display()
+        ));
+    }
+
+    Ok(files)
+}
+
+/// Lists the `.pmtiles` archives in `dir`, which may be empty.
+fn list_pmtiles_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
         format!(
             "Failed to read PMTiles data directory '{}': {}",
-            pmtiles_dir.display(),
+            dir.display(),
             e
         )
     })?;
 
     let mut files = Vec::new();
 
-    while let Some(entry) = entries.next() {
+    for entry in entries {
         let entry = entry.map_err(|e| {
             format!(
                 "Failed to read directory entry in '{}': {}",
-                pmtiles_dir.display(),
+                dir.display(),
                 e
             )
         })?;
@@ -55,16 +86,22 @@ pub async fn discover_all_pmtiles_files(
         }
     }
 
-    if files.is_empty() {
-        return Err(format!(
-            "No PMTiles files found in '{}'. Files may not have been downloaded.",
-            pmtiles_dir.display()
-        ));
-    }
-
     Ok(files)
 }
 
+/// Size and modification time (seconds since the Unix epoch) of an archive,
+/// used to tell whether it changed on disk.
+fn file_stamp(file_path: &Path) -> Result<(u64, Option<u64>), String> {
+    let metadata = std::fs::metadata(file_path)
+        .map_err(|e| format!("Failed to stat '{}': {}", file_path.display(), e))?;
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Ok((metadata.len(), modified_at))
+}
+
 async fn extract_locality_metadata(
     filename: &str,
     file_path: &PathBuf,
@@ -78,26 +115,43 @@ async fn extract_locality_metadata(
         .map_err(|e| format!("Failed to initialize PMTiles reader for '{}': {}", filename, e))?;
 
     let header = reader.get_header();
+    let (file_size, modified_at) = file_stamp(file_path)?;
 
-    let (name, description) = match reader.get_metadata().await {
-        Ok(metadata_str) => {
-            // Parse JSON metadata
-            let parsed: Result<serde_json::Value, _> = serde_json::from_str(&metadata_str);
-            match parsed {
-                Ok(json) => {
-                    let name = json
-                        .get("name")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|| filename_to_locality_name(filename));
-                    let description = json.get("description").and_then(|v| v.as_str()).map(|s| s.to_string());
-                    (name, description)
-                }
-                Err(_) => (filename_to_locality_name(filename), None),
-            }
-        }
-        Err(_) => (filename_to_locality_name(filename), None),
-    };
+    // Parse JSON metadata
+    let json: Option<serde_json::Value> = reader
+        .get_metadata()
+        .await
+        .ok()
+        .and_then(|metadata_str| serde_json::from_str(&metadata_str).ok());
+    let json = json.as_ref();
+
+    let name = metadata_str(json, "name").unwrap_or_else(|| filename_to_locality_name(filename));
+    let description = metadata_str(json, "description");
+    let priority = json
+        .and_then(|j| j.get("priority"))
+        .and_then(|v| v.as_i64().or_else(|| v.as_str()?.parse().ok()))
+        .map(|p| p.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+        .unwrap_or(DEFAULT_LOCALITY_PRIORITY);
+    let build_date = BUILD_DATE_KEYS
+        .iter()
+        .find_map(|key| metadata_str(json, key));
+    let encoding = metadata_str(json, "encoding");
+    let source_kind = detect_source_kind(header.tile_type, json, encoding.as_deref());
+    let attribution = metadata_str(json, "attribution");
+    let version = metadata_str(json, "version");
+    let vector_layers = json
+        .and_then(|j| j.get("vector_layers"))
+        .and_then(|v| serde_json::from_value::<Vec<VectorLayer>>(v.clone()).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|layer| VectorLayerInfo {
+            id: layer.id,
+            fields: layer.fields,
+            description: layer.description,
+            min_zoom: layer.minzoom,
+            max_zoom: layer.maxzoom,
+        })
+        .collect();
 
     let id = filename
         .strip_suffix(".pmtiles")
@@ -122,9 +176,69 @@ async fn extract_locality_metadata(
         },
         min_zoom: header.min_zoom,
         max_zoom: header.max_zoom,
+        priority,
+        build_date,
+        file_size,
+        modified_at,
+        vector_layers,
+        attribution,
+        source_kind,
+        tile_type: tile_type_name(header.tile_type).to_string(),
+        tile_compression: compression_name(header.tile_compression).to_string(),
+        version,
+        encoding,
     })
 }
 
+/// Image archives are terrain when their metadata declares a DEM encoding or
+/// a `terrain`/`dem` type, and plain rasters otherwise.
+fn detect_source_kind(
+    tile_type: TileType,
+    json: Option<&serde_json::Value>,
+    encoding: Option<&str>,
+) -> SourceKind {
+    if tile_type == TileType::Mvt {
+        return SourceKind::Vector;
+    }
+
+    let declared_terrain = metadata_str(json, "type")
+        .is_some_and(|t| t.eq_ignore_ascii_case("terrain") || t.eq_ignore_ascii_case("dem"));
+    let dem_encoding = encoding.is_some_and(|e| e == "mapbox" || e == "terrarium");
+
+    if declared_terrain || dem_encoding {
+        SourceKind::Terrain
+    } else {
+        SourceKind::Raster
+    }
+}
+
+fn tile_type_name(tile_type: TileType) -> &'static str {
+    match tile_type {
+        TileType::Mvt => "mvt",
+        TileType::Png => "png",
+        TileType::Jpeg => "jpeg",
+        TileType::Webp => "webp",
+        TileType::Avif => "avif",
+        _ => "unknown",
+    }
+}
+
+fn compression_name(compression: Compression) -> &'static str {
+    match compression {
+        Compression::None => "none",
+        Compression::Gzip => "gzip",
+        Compression::Brotli => "brotli",
+        Compression::Zstd => "zstd",
+        _ => "unknown",
+    }
+}
+
+fn metadata_str(json: Option<&serde_json::Value>, key: &str) -> Option<String> {
+    json.and_then(|j| j.get(key))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
 fn filename_to_locality_name(filename: &str) -> String {
     filename
         .strip_suffix(".pmtiles")
@@ -151,40 +265,28 @@ pub async fn init_multi_reader(
     
     let files = discover_all_pmtiles_files(app).await?;
 
+    let _reload = state.reload_lock.lock().await;
     {
         let mut guard = state.pmtiles_dir.write().await;
-        *guard = Some(pmtiles_dir);
+        *guard = Some(pmtiles_dir.clone());
     }
 
-    let mut localities = Vec::new();
-    let mut spatial_entries = Vec::new();
-    let mut metadata_map = std::collections::HashMap::new();
-
-    for (filename, file_path) in files {
-        match extract_locality_metadata(&filename, &file_path).await {
-            Ok(metadata) => {
-                let info = LocalityInfo {
-                    id: metadata.id.clone(),
-                    bounds: metadata.bounds,
-                    file_path,
-                };
-
-                spatial_entries.push(SpatialIndexEntry::from_locality(&info));
-                metadata_map.insert(metadata.id.clone(), metadata.clone());
-                localities.push(metadata);
-            }
-            Err(e) => {
-                eprintln!("Warning: Failed to extract metadata from {}: {}", filename, e);
-            }
-        }
-    }
+    let localities = load_localities(files, &HashMap::new(), state).await;
 
     if localities.is_empty() {
         return Err("No valid PMTiles files found".to_string());
     }
 
-    let spatial_index = rstar::RTree::bulk_load(spatial_entries);
+    state.reader_cache.write().await.clear();
+    install_localities(&pmtiles_dir, &localities, state).await;
+    watch_pmtiles_dir(app, &pmtiles_dir, state);
+
+    let overzoom_levels = state.tile_options.read().await.overzoom_levels;
+    Ok(summarize_localities(localities, overzoom_levels))
+}
 
+/// Combined bounds, center and zoom range of a set of localities.
+fn summarize_localities(localities: Vec<LocalityMetadata>, overzoom_levels: u8) -> MultiPmtilesInfo {
     let combined_bounds = calculate_combined_bounds(&localities);
     let combined_center = CenterPoint {
         longitude: (combined_bounds.min_lon + combined_bounds.max_lon) / 2.0,
@@ -193,23 +295,270 @@ pub async fn init_multi_reader(
     };
     let min_zoom = localities.iter().map(|l| l.min_zoom).min().unwrap_or(0);
     let max_zoom = localities.iter().map(|l| l.max_zoom).max().unwrap_or(14);
+    let overzoom_max_zoom = max_zoom.saturating_add(overzoom_levels);
+
+    MultiPmtilesInfo {
+        localities,
+        combined_bounds,
+        combined_center,
+        min_zoom,
+        max_zoom,
+        overzoom_max_zoom,
+    }
+}
+
+/// Extracts metadata for `files`, reusing `previous` entries whose archive
+/// size and modification time are unchanged, and applies priority overrides.
+async fn load_localities(
+    files: Vec<(String, PathBuf)>,
+    previous: &HashMap<String, LocalityMetadata>,
+    state: &tauri::State<'_, MapState>,
+) -> Vec<LocalityMetadata> {
+    let priority_overrides = state.priority_overrides.read().await.clone();
+    let mut localities = Vec::new();
+
+    for (filename, file_path) in files {
+        let unchanged = previous.values().find(|l| {
+            l.filename == filename
+                && file_stamp(&file_path).ok() == Some((l.file_size, l.modified_at))
+        });
+
+        let mut metadata = match unchanged {
+            Some(metadata) => metadata.clone(),
+            None => match extract_locality_metadata(&filename, &file_path).await {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    eprintln!("Warning: Failed to extract metadata from {}: {}", filename, e);
+                    continue;
+                }
+            },
+        };
+
+        if let Some(priority) = priority_overrides.get(&metadata.id) {
+            metadata.priority = *priority;
+        }
+        localities.push(metadata);
+    }
+
+    localities
+}
+
+/// Replaces the spatial index and metadata map with `localities` and drops
+/// every cached tile.
+async fn install_localities(
+    pmtiles_dir: &Path,
+    localities: &[LocalityMetadata],
+    state: &tauri::State<'_, MapState>,
+) {
+    let mut spatial_entries: HashMap<SourceKind, Vec<SpatialIndexEntry>> = HashMap::new();
+    for metadata in localities {
+        spatial_entries
+            .entry(metadata.source_kind)
+            .or_default()
+            .push(SpatialIndexEntry::from_locality(&LocalityInfo {
+                id: metadata.id.clone(),
+                bounds: metadata.bounds,
+                file_path: pmtiles_dir.join(&metadata.filename),
+            }));
+    }
+    let metadata_map = localities
+        .iter()
+        .map(|metadata| (metadata.id.clone(), metadata.clone()))
+        .collect();
 
     {
         let mut guard = state.spatial_index.write().await;
-        *guard = spatial_index;
+        *guard = spatial_entries
+            .into_iter()
+            .map(|(kind, entries)| (kind, rstar::RTree::bulk_load(entries)))
+            .collect();
     }
     {
         let mut guard = state.locality_metadata.write().await;
         *guard = metadata_map;
     }
+    state.tile_cache.write().await.clear();
+}
 
-    Ok(MultiPmtilesInfo {
-        localities,
-        combined_bounds,
-        combined_center,
-        min_zoom,
-        max_zoom,
-    })
+fn watch_pmtiles_dir(
+    app: &tauri::AppHandle,
+    pmtiles_dir: &Path,
+    state: &tauri::State<'_, MapState>,
+) {
+    let mut watcher = state
+        .pmtiles_watcher
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    match map_watcher::watch_pmtiles_dir(app, pmtiles_dir) {
+        Ok(new_watcher) => *watcher = Some(new_watcher),
+        Err(e) => eprintln!("Warning: {}", e),
+    }
+}
+
+/// Rescans the pmtiles directory and brings the map state in line with it:
+/// the R-tree and metadata map are rebuilt, readers of removed or replaced
+/// archives are evicted and the tile cache is cleared.
+pub async fn reload_localities(
+    state: &tauri::State<'_, MapState>,
+) -> Result<LocalityChanges, String> {
+    let _reload = state.reload_lock.lock().await;
+
+    let pmtiles_dir = state
+        .pmtiles_dir
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| "PMTiles directory not set".to_string())?;
+
+    let files = list_pmtiles_files(&pmtiles_dir)?;
+    let previous = state.locality_metadata.read().await.clone();
+    let localities = load_localities(files, &previous, state).await;
+
+    let changes = diff_localities(&previous, &localities);
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+
+    {
+        let mut readers = state.reader_cache.write().await;
+        for id in changes
+            .removed
+            .iter()
+            .chain(changes.updated.iter().map(|l| &l.id))
+        {
+            readers.pop(id);
+        }
+    }
+    install_localities(&pmtiles_dir, &localities, state).await;
+
+    Ok(changes)
+}
+
+fn diff_localities(
+    previous: &HashMap<String, LocalityMetadata>,
+    current: &[LocalityMetadata],
+) -> LocalityChanges {
+    let mut changes = LocalityChanges::default();
+
+    for locality in current {
+        match previous.get(&locality.id) {
+            None => changes.added.push(locality.clone()),
+            Some(old)
+                if old.filename != locality.filename
+                    || old.file_size != locality.file_size
+                    || old.modified_at != locality.modified_at =>
+            {
+                changes.updated.push(locality.clone())
+            }
+            Some(_) => {}
+        }
+    }
+
+    changes.removed = previous
+        .keys()
+        .filter(|id| !current.iter().any(|l| &l.id == *id))
+        .cloned()
+        .collect();
+    changes.removed.sort();
+
+    changes
+}
+
+/// TileJSON 3.0 document describing every loaded locality of one source
+/// kind as a single source served from `tile_url`.
+pub async fn get_tilejson(
+    source: SourceKind,
+    tile_url: String,
+    state: &tauri::State<'_, MapState>,
+) -> Result<TileJSON, String> {
+    let mut localities: Vec<LocalityMetadata> = state
+        .locality_metadata
+        .read()
+        .await
+        .values()
+        .filter(|l| l.source_kind == source)
+        .cloned()
+        .collect();
+    if localities.is_empty() {
+        return Err(format!("No {:?} localities loaded", source).to_lowercase());
+    }
+    localities.sort_by(compare_locality_priority);
+
+    let overzoom_levels = match source {
+        SourceKind::Vector => state.tile_options.read().await.overzoom_levels,
+        SourceKind::Raster | SourceKind::Terrain => 0,
+    };
+    Ok(build_tilejson(
+        &summarize_localities(localities, overzoom_levels),
+        tile_url,
+    ))
+}
+
+fn build_tilejson(info: &MultiPmtilesInfo, tile_url: String) -> TileJSON {
+    let mut vector_layers: Vec<VectorLayer> = Vec::new();
+    let mut attributions: Vec<&str> = Vec::new();
+
+    for locality in &info.localities {
+        for layer in &locality.vector_layers {
+            match vector_layers.iter_mut().find(|l| l.id == layer.id) {
+                Some(existing) => {
+                    for (field, kind) in &layer.fields {
+                        existing
+                            .fields
+                            .entry(field.clone())
+                            .or_insert_with(|| kind.clone());
+                    }
+                    existing.minzoom = existing.minzoom.min(layer.min_zoom);
+                    existing.maxzoom = existing.maxzoom.max(layer.max_zoom);
+                    if existing.description.is_none() {
+                        existing.description = layer.description.clone();
+                    }
+                }
+                None => {
+                    let mut vector_layer = VectorLayer::new(layer.id.clone(), layer.fields.clone());
+                    vector_layer.description = layer.description.clone();
+                    vector_layer.minzoom = layer.min_zoom;
+                    vector_layer.maxzoom = layer.max_zoom;
+                    vector_layers.push(vector_layer);
+                }
+            }
+        }
+
+        if let Some(attribution) = locality.attribution.as_deref() {
+            if !attributions.contains(&attribution) {
+                attributions.push(attribution);
+            }
+        }
+    }
+
+    let bounds = &info.combined_bounds;
+    let mut tilejson = tilejson! {
+        tiles: vec![tile_url],
+        minzoom: info.min_zoom,
+        maxzoom: info.overzoom_max_zoom,
+        bounds: Bounds::new(bounds.min_lon, bounds.min_lat, bounds.max_lon, bounds.max_lat),
+        center: Center::new(
+            info.combined_center.longitude,
+            info.combined_center.latitude,
+            info.combined_center.zoom,
+        ),
+    };
+    tilejson.scheme = Some("xyz".to_string());
+    tilejson.vector_layers = (!vector_layers.is_empty()).then_some(vector_layers);
+    tilejson.attribution = (!attributions.is_empty()).then(|| attributions.join(" | "));
+    if let Some(encoding) = info.localities.iter().find_map(|l| l.encoding.clone()) {
+        tilejson
+            .other
+            .insert("encoding".to_string(), serde_json::Value::String(encoding));
+    }
+    if let [locality] = info.localities.as_slice() {
+        tilejson.name = Some(locality.name.clone());
+        tilejson.description = locality.description.clone();
+        tilejson.version = locality.version.clone();
+    }
+
+    tilejson
 }
 
 fn calculate_combined_bounds(localities: &[LocalityMetadata]) -> BoundingBox {
@@ -243,30 +592,121 @@ pub fn tile_to_bounds(z: u8, x: u32, y: u32) -> BoundingBox {
     BoundingBox::new(min_lon, min_lat, max_lon, max_lat)
 }
 
+/// Gets a tile for the map from the tile cache, or reads and caches it; the
+/// localities it comes from are marked as viewed either way.
 pub async fn get_tile(
+    source: SourceKind,
     z: u8,
     x: u32,
     y: u32,
     state: &tauri::State<'_, MapState>,
 ) -> Result<Option<Vec<u8>>, String> {
+    let key = TileKey { source, z, x, y };
+    {
+        let mut cache = state.tile_cache.write().await;
+        if let Some(tile) = cache.get(&key) {
+            for locality_id in cache.localities(&key) {
+                state.mark_viewed(locality_id);
+            }
+            return Ok(Some(tile));
+        }
+    }
+
+    let Some((tile, served)) = read_tile(source, z, x, y, state).await? else {
+        return Ok(None);
+    };
+    for locality_id in &served {
+        state.mark_viewed(locality_id);
+    }
+
+    state.tile_cache.write().await.put(
+        key,
+        CachedTile {
+            data: tile.clone(),
+            localities: served,
+        },
+    );
+    Ok(Some(tile))
+}
+
+/// Gets a tile for a lookup, such as a feature query, rather than for the
+/// map. A cached copy is used, but the tile is neither cached nor counted as
+/// viewed, so lookups away from the view leave the map's tiles and the
+/// eviction order alone.
+pub async fn get_lookup_tile(
+    source: SourceKind,
+    z: u8,
+    x: u32,
+    y: u32,
+    state: &tauri::State<'_, MapState>,
+) -> Result<Option<Vec<u8>>, String> {
+    let key = TileKey { source, z, x, y };
+    if let Some(tile) = state.tile_cache.read().await.peek(&key) {
+        return Ok(Some(tile));
+    }
+    Ok(read_tile(source, z, x, y, state)
+        .await?
+        .map(|(tile, _)| tile))
+}
+
+/// Reads a tile from the localities covering it, in priority order, along
+/// with the ids of the localities it was read from.
+async fn read_tile(
+    source: SourceKind,
+    z: u8,
+    x: u32,
+    y: u32,
+    state: &tauri::State<'_, MapState>,
+) -> Result<Option<(Vec<u8>, Vec<String>)>, String> {
     let tile_bounds = tile_to_bounds(z, x, y);
 
-    let candidates: Vec<String> = {
-        let index = state.spatial_index.read().await;
+    let candidates: Vec<(String, u8)> = {
+        let indexes = state.spatial_index.read().await;
+        let Some(index) = indexes.get(&source) else {
+            return Ok(None);
+        };
+        let metadata = state.locality_metadata.read().await;
         let query_envelope = AABB::from_corners(
             [tile_bounds.min_lon, tile_bounds.min_lat],
             [tile_bounds.max_lon, tile_bounds.max_lat],
         );
 
-        index
+        let mut localities: Vec<&LocalityMetadata> = index
             .locate_in_envelope_intersecting(&query_envelope)
-            .map(|entry| entry.locality_id.clone())
+            .filter_map(|entry| metadata.get(&entry.locality_id))
+            .collect();
+        localities.sort_by(|a, b| compare_locality_priority(a, b));
+
+        localities
+            .into_iter()
+            .map(|l| (l.id.clone(), l.max_zoom))
             .collect()
     };
 
-    for locality_id in candidates {
-        match get_tile_from_locality(z, x, y, &locality_id, state).await {
-            Ok(Some(tile)) => return Ok(Some(tile)),
+    // Only vector tiles can be clipped and merged; image sources serve the
+    // first archive holding the tile and leave overzooming to the map.
+    let options = *state.tile_options.read().await;
+    let is_vector = source == SourceKind::Vector;
+    let mut tiles = Vec::new();
+    let mut served = Vec::new();
+
+    for (locality_id, max_zoom) in candidates {
+        let result = if z <= max_zoom {
+            get_tile_from_locality(z, x, y, &locality_id, state).await
+        } else if is_vector && z - max_zoom <= options.overzoom_levels {
+            get_overzoomed_tile(z, x, y, max_zoom, &locality_id, state).await
+        } else {
+            continue;
+        };
+
+        match result {
+            Ok(Some(tile)) => {
+                served.push(locality_id);
+                tiles.push(tile);
+                if !(is_vector && options.composite) {
+                    break;
+                }
+            }
             Ok(None) => continue,
             Err(e) => {
                 eprintln!(
@@ -278,10 +718,191 @@ pub async fn get_tile(
         }
     }
 
-    Ok(None)
+    let tile = match tiles.len() {
+        0 => return Ok(None),
+        1 => tiles.swap_remove(0),
+        _ => map_mvt::merge_tiles(&tiles)?,
+    };
+    Ok(Some((tile, served)))
+}
+
+/// Features of the vector tile at `zoom` under `(lon, lat)` that contain the
+/// point or lie within `radius_px` of it, as a GeoJSON `FeatureCollection`.
+pub async fn query_features_at(
+    lon: f64,
+    lat: f64,
+    zoom: u8,
+    radius_px: f64,
+    state: &tauri::State<'_, MapState>,
+) -> Result<serde_json::Value, String> {
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(format!("Invalid coordinate: {}, {}", lon, lat));
+    }
+    if zoom > pmtiles::MAX_ZOOM {
+        return Err(format!("Invalid zoom level: {}", zoom));
+    }
+
+    let [tx, ty] = map_query::lonlat_to_tile(lon, lat, zoom);
+    let max_index = (1u32 << zoom) - 1;
+    let x = (tx.floor() as u32).min(max_index);
+    let y = (ty.floor() as u32).min(max_index);
+
+    let features = match get_lookup_tile(SourceKind::Vector, zoom, x, y, state).await? {
+        Some(data) => {
+            let tile = map_mvt::decode_tile(&data)?;
+            map_query::query_tile(&tile, (zoom, x, y), (lon, lat), radius_px)
+        }
+        None => Vec::new(),
+    };
+
+    Ok(serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features,
+    }))
+}
+
+/// Describes the coordinate from the labelled features of the vector tiles
+/// around it, falling back to the name of the locality archive covering it.
+pub async fn reverse_geocode(
+    lon: f64,
+    lat: f64,
+    state: &tauri::State<'_, MapState>,
+) -> Result<ReverseGeocodeResult, String> {
+    if !(-180.0..=180.0).contains(&lon) || !(-90.0..=90.0).contains(&lat) {
+        return Err(format!("Invalid coordinate: {}, {}", lon, lat));
+    }
+
+    let mut collector = GeocodeCollector::new(lon, lat);
+
+    // The tile holding the point and its neighbours, so that features just
+    // across a tile edge are considered too.
+    for z in REVERSE_GEOCODE_ZOOMS {
+        let [tx, ty] = map_query::lonlat_to_tile(lon, lat, z);
+        let max_index = (1u32 << z) - 1;
+        let x = (tx.floor() as u32).min(max_index);
+        let y = (ty.floor() as u32).min(max_index);
+
+        for ny in y.saturating_sub(1)..=(y + 1).min(max_index) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(max_index) {
+                let Some(data) = get_lookup_tile(SourceKind::Vector, z, nx, ny, state).await?
+                else {
+                    continue;
+                };
+                match map_mvt::decode_tile(&data) {
+                    Ok(tile) => collector.add_tile(&tile, (z, nx, ny)),
+                    Err(e) => eprintln!("Warning: Skipping tile {}/{}/{}: {}", z, nx, ny, e),
+                }
+            }
+        }
+    }
+
+    let metadata = state.locality_metadata.read().await;
+    let fallback = metadata
+        .values()
+        .filter(|l| l.source_kind == SourceKind::Vector && l.bounds.contains(lon, lat))
+        .min_by(|a, b| compare_locality_priority(a, b));
+
+    Ok(collector.finish(fallback.map(|l| (l.id.as_str(), l.name.as_str()))))
+}
+
+/// Orders localities covering the same tile: highest priority first, then the
+/// smallest (most detailed) bounds, then the most recent build, then by id so
+/// the order never depends on the R-tree layout.
+fn compare_locality_priority(a: &LocalityMetadata, b: &LocalityMetadata) -> Ordering {
+    b.priority
+        .cmp(&a.priority)
+        .then_with(|| a.bounds.area().total_cmp(&b.bounds.area()))
+        .then_with(|| b.build_date.cmp(&a.build_date))
+        .then_with(|| a.id.cmp(&b.id))
+}
+
+pub async fn set_locality_priority(
+    locality_id: &str,
+    priority: Option<i32>,
+    state: &tauri::State<'_, MapState>,
+) -> Result<LocalityMetadata, String> {
+    let archive_priority = match priority {
+        Some(_) => None,
+        None => {
+            let (filename, file_path) = locality_file(locality_id, state).await?;
+            Some(extract_locality_metadata(&filename, &file_path).await?.priority)
+        }
+    };
+
+    {
+        let mut overrides = state.priority_overrides.write().await;
+        match priority {
+            Some(priority) => overrides.insert(locality_id.to_string(), priority),
+            None => overrides.remove(locality_id),
+        };
+    }
+
+    let updated = {
+        let mut metadata = state.locality_metadata.write().await;
+        let locality = metadata
+            .get_mut(locality_id)
+            .ok_or_else(|| format!("Locality not found: {}", locality_id))?;
+        locality.priority = priority
+            .or(archive_priority)
+            .unwrap_or(DEFAULT_LOCALITY_PRIORITY);
+        locality.clone()
+    };
+
+    state.tile_cache.write().await.clear();
+
+    Ok(updated)
+}
+
+/// Replaces the priority overrides, re-prioritizing the loaded localities
+/// whose override changed.
+pub async fn set_priority_overrides(
+    overrides: &BTreeMap<String, i32>,
+    state: &tauri::State<'_, MapState>,
+) {
+    let previous = std::mem::replace(
+        &mut *state.priority_overrides.write().await,
+        overrides.clone().into_iter().collect(),
+    );
+
+    let loaded: Vec<String> = state.locality_metadata.read().await.keys().cloned().collect();
+    for locality_id in loaded {
+        let priority = overrides.get(&locality_id).copied();
+        if previous.get(&locality_id).copied() == priority {
+            continue;
+        }
+        if let Err(e) = set_locality_priority(&locality_id, priority, state).await {
+            eprintln!(
+                "Warning: Failed to update the priority of {}: {}",
+                locality_id, e
+            );
+        }
+    }
+}
+
+/// Coordinates of every tile stored at zoom `z` in a locality's archive.
+pub async fn list_locality_tiles(
+    locality_id: &str,
+    z: u8,
+    state: &tauri::State<'_, MapState>,
+) -> Result<Vec<TileCoord>, String> {
+    let reader = get_or_load_reader(locality_id, state).await?;
+    let mut entries = reader.entries();
+    let mut coords = Vec::new();
+
+    while let Some(entry) = entries.next().await {
+        let entry = entry.map_err(|e| format!("Failed to read tile directory: {}", e))?;
+        coords.extend(
+            entry
+                .iter_coords()
+                .map(TileCoord::from)
+                .filter(|coord| coord.z() == z),
+        );
+    }
+
+    Ok(coords)
 }
 
-async fn get_tile_from_locality(
+pub async fn get_tile_from_locality(
     z: u8,
     x: u32,
     y: u32,
@@ -299,6 +920,46 @@ async fn get_tile_from_locality(
     }
 }
 
+/// Builds a tile past the locality's `max_zoom` from its ancestor at `max_zoom`.
+async fn get_overzoomed_tile(
+    z: u8,
+    x: u32,
+    y: u32,
+    max_zoom: u8,
+    locality_id: &str,
+    state: &tauri::State<'_, MapState>,
+) -> Result<Option<Vec<u8>>, String> {
+    let dz = z - max_zoom;
+
+    let Some(parent) = get_tile_from_locality(max_zoom, x >> dz, y >> dz, locality_id, state).await?
+    else {
+        return Ok(None);
+    };
+
+    let mask = (1u32 << dz) - 1;
+    map_mvt::overzoom_tile(&parent, dz, x & mask, y & mask).map(Some)
+}
+
+async fn locality_file(
+    locality_id: &str,
+    state: &tauri::State<'_, MapState>,
+) -> Result<(String, PathBuf), String> {
+    let metadata = state.locality_metadata.read().await;
+    let locality = metadata
+        .get(locality_id)
+        .ok_or_else(|| format!("Locality not found: {}", locality_id))?;
+
+    let pmtiles_dir = state.pmtiles_dir.read().await;
+    let pmtiles_dir = pmtiles_dir
+        .as_ref()
+        .ok_or_else(|| "PMTiles directory not set".to_string())?;
+
+    Ok((
+        locality.filename.clone(),
+        pmtiles_dir.join(&locality.filename),
+    ))
+}
+
 async fn get_or_load_reader(
     locality_id: &str,
     state: &tauri::State<'_, MapState>,
@@ -310,22 +971,7 @@ async fn get_or_load_reader(
         }
     }
 
-    let (filename, file_path) = {
-        let metadata = state.locality_metadata.read().await;
-        let locality = metadata
-            .get(locality_id)
-            .ok_or_else(|| format!("Locality not found: {}", locality_id))?;
-
-        let pmtiles_dir = state.pmtiles_dir.read().await;
-        let pmtiles_dir = pmtiles_dir
-            .as_ref()
-            .ok_or_else(|| "PMTiles directory not set".to_string())?;
-
-        (
-            locality.filename.clone(),
-            pmtiles_dir.join(&locality.filename),
-        )
-    };
+    let (filename, file_path) = locality_file(locality_id, state).await?;
 
     let backend = MmapBackend::try_from(&file_path)
         .await
@@ -373,6 +1019,135 @@ mod tests {
         assert!((bounds.max_lon - 0.0).abs() < 0.01);
     }
 
+    fn test_locality(id: &str, bounds: BoundingBox) -> LocalityMetadata {
+        LocalityMetadata {
+            id: id.to_string(),
+            filename: format!("{}.pmtiles", id),
+            name: id.to_string(),
+            description: None,
+            bounds,
+            center: CenterPoint {
+                longitude: 0.0,
+                latitude: 0.0,
+                zoom: 10,
+            },
+            min_zoom: 0,
+            max_zoom: 14,
+            priority: 0,
+            build_date: None,
+            file_size: 0,
+            modified_at: None,
+            vector_layers: Vec::new(),
+            attribution: None,
+            source_kind: SourceKind::Vector,
+            tile_type: "mvt".to_string(),
+            tile_compression: "gzip".to_string(),
+            version: None,
+            encoding: None,
+        }
+    }
+
+    fn sorted_ids(mut localities: Vec<LocalityMetadata>) -> Vec<String> {
+        localities.sort_by(compare_locality_priority);
+        localities.into_iter().map(|l| l.id).collect()
+    }
+
+    #[test]
+    fn test_locality_priority_prefers_smaller_bounds() {
+        let region = test_locality("ontario", BoundingBox::new(-95.0, 41.0, -74.0, 57.0));
+        let city = test_locality("ottawa", BoundingBox::new(-76.5, 45.0, -75.0, 45.6));
+
+        assert_eq!(sorted_ids(vec![region, city]), vec!["ottawa", "ontario"]);
+    }
+
+    #[test]
+    fn test_locality_priority_order() {
+        let mut region = test_locality("ontario", BoundingBox::new(-95.0, 41.0, -74.0, 57.0));
+        region.priority = 1;
+        let city = test_locality("ottawa", BoundingBox::new(-76.5, 45.0, -75.0, 45.6));
+        let mut newer = test_locality("ottawa-new", BoundingBox::new(-76.5, 45.0, -75.0, 45.6));
+        newer.build_date = Some("2026-01-01".to_string());
+        let same = test_locality("ottawa-copy", BoundingBox::new(-76.5, 45.0, -75.0, 45.6));
+
+        assert_eq!(
+            sorted_ids(vec![same, city, newer, region]),
+            vec!["ontario", "ottawa-new", "ottawa", "ottawa-copy"]
+        );
+    }
+
+    #[test]
+    fn test_diff_localities() {
+        let ottawa = test_locality("ottawa", BoundingBox::new(-76.5, 45.0, -75.0, 45.6));
+        let gatineau = test_locality("gatineau", BoundingBox::new(-76.0, 45.3, -75.3, 45.7));
+        let montreal = test_locality("montreal", BoundingBox::new(-74.0, 45.4, -73.4, 45.7));
+        let previous: HashMap<String, LocalityMetadata> = [&ottawa, &gatineau]
+            .into_iter()
+            .map(|l| (l.id.clone(), l.clone()))
+            .collect();
+
+        assert!(diff_localities(&previous, &[ottawa.clone(), gatineau.clone()]).is_empty());
+
+        let mut replaced = ottawa.clone();
+        replaced.file_size = 1024;
+        let changes = diff_localities(&previous, &[replaced, montreal]);
+
+        let ids = |ls: &[LocalityMetadata]| ls.iter().map(|l| l.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&changes.added), vec!["montreal"]);
+        assert_eq!(ids(&changes.updated), vec!["ottawa"]);
+        assert_eq!(changes.removed, vec!["gatineau"]);
+    }
+
+    #[test]
+    fn test_build_tilejson_merges_vector_layers() {
+        let layer = |id: &str, field: &str, min_zoom, max_zoom| VectorLayerInfo {
+            id: id.to_string(),
+            fields: [(field.to_string(), "String".to_string())].into(),
+            description: None,
+            min_zoom: Some(min_zoom),
+            max_zoom: Some(max_zoom),
+        };
+        let mut ottawa = test_locality("ottawa", BoundingBox::new(-76.5, 45.0, -75.0, 45.6));
+        ottawa.vector_layers = vec![layer("roads", "kind", 4, 14)];
+        ottawa.attribution = Some("OpenStreetMap".to_string());
+        let mut gatineau = test_locality("gatineau", BoundingBox::new(-76.0, 45.3, -75.3, 45.7));
+        gatineau.vector_layers = vec![layer("roads", "name", 6, 15), layer("water", "kind", 0, 15)];
+        gatineau.attribution = Some("OpenStreetMap".to_string());
+
+        let info = summarize_localities(vec![ottawa, gatineau], 4);
+        let tilejson = build_tilejson(&info, "anymaps://localhost/tiles/{z}/{x}/{y}".to_string());
+
+        assert_eq!(tilejson.tilejson, "3.0.0");
+        assert_eq!(tilejson.tiles, vec!["anymaps://localhost/tiles/{z}/{x}/{y}"]);
+        assert_eq!(tilejson.maxzoom, Some(18));
+        assert_eq!(tilejson.attribution.as_deref(), Some("OpenStreetMap"));
+
+        let layers = tilejson.vector_layers.unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].id, "roads");
+        assert_eq!(layers[0].fields.len(), 2);
+        assert_eq!((layers[0].minzoom, layers[0].maxzoom), (Some(4), Some(15)));
+    }
+
+    #[test]
+    fn test_detect_source_kind() {
+        let terrain = serde_json::json!({ "encoding": "terrarium" });
+        let hillshade = serde_json::json!({ "name": "Hillshade" });
+
+        assert_eq!(detect_source_kind(TileType::Mvt, None, None), SourceKind::Vector);
+        assert_eq!(
+            detect_source_kind(TileType::Webp, Some(&hillshade), None),
+            SourceKind::Raster
+        );
+        assert_eq!(
+            detect_source_kind(TileType::Png, Some(&terrain), Some("terrarium")),
+            SourceKind::Terrain
+        );
+        assert_eq!(
+            detect_source_kind(TileType::Png, Some(&serde_json::json!({ "type": "dem" })), None),
+            SourceKind::Terrain
+        );
+    }
+
     #[test]
     fn test_calculate_combined_bounds() {
         let localities = vec![
@@ -389,6 +1164,17 @@ mod tests {
                 },
                 min_zoom: 0,
                 max_zoom: 14,
+                priority: 0,
+                build_date: None,
+                file_size: 0,
+                modified_at: None,
+                vector_layers: Vec::new(),
+                attribution: None,
+                source_kind: SourceKind::Vector,
+                tile_type: "mvt".to_string(),
+                tile_compression: "gzip".to_string(),
+                version: None,
+                encoding: None,
             },
             LocalityMetadata {
                 id: "gatineau".to_string(),
@@ -403,6 +1189,17 @@ mod tests {
                 },
                 min_zoom: 0,
                 max_zoom: 14,
+                priority: 0,
+                build_date: None,
+                file_size: 0,
+                modified_at: None,
+                vector_layers: Vec::new(),
+                attribution: None,
+                source_kind: SourceKind::Vector,
+                tile_type: "mvt".to_string(),
+                tile_compression: "gzip".to_string(),
+                version: None,
+                encoding: None,
             },
         ];
 
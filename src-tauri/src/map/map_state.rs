@@ -1,4 +1,8 @@
-use crate::map::map_types::{LocalityInfo, LocalityMetadata};
+use crate::map::map_types::{
+    LocalityInfo, LocalityMetadata, SourceKind, TileCacheStats, TileOptions,
+};
+use crate::map::map_watcher::PmtilesWatcher;
+use crate::settings::settings_types::MapSettings;
 use lru::LruCache;
 use nonzero_ext::nonzero;
 use pmtiles::{AsyncPmTilesReader, MmapBackend};
@@ -6,10 +10,12 @@ use rstar::{RTree, AABB};
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 use tokio::sync::RwLock;
 
-const MAX_CACHED_READERS: NonZeroUsize = nonzero!(10usize);
+pub const MAX_CACHED_READERS: NonZeroUsize = nonzero!(10usize);
+pub const TILE_CACHE_BUDGET_BYTES: usize = 64 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct SpatialIndexEntry {
@@ -43,23 +49,185 @@ impl SpatialIndexEntry {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub source: SourceKind,
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A decompressed tile and the localities it was read from.
+pub struct CachedTile {
+    pub data: Vec<u8>,
+    pub localities: Vec<String>,
+}
+
+impl From<Vec<u8>> for CachedTile {
+    fn from(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            localities: Vec::new(),
+        }
+    }
+}
+
+/// LRU cache of decompressed tiles, bounded by the total size of the cached tiles.
+pub struct TileCache {
+    entries: LruCache<TileKey, CachedTile>,
+    budget_bytes: usize,
+    used_bytes: usize,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl TileCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            budget_bytes,
+            used_bytes: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &TileKey) -> Option<Vec<u8>> {
+        match self.entries.get(key) {
+            Some(tile) => {
+                self.hits += 1;
+                Some(tile.data.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// The cached tile, leaving its recency and the hit counts as they are.
+    pub fn peek(&self, key: &TileKey) -> Option<Vec<u8>> {
+        self.entries.peek(key).map(|tile| tile.data.clone())
+    }
+
+    /// Localities the cached tile was read from, leaving its recency as is.
+    pub fn localities(&self, key: &TileKey) -> &[String] {
+        self.entries
+            .peek(key)
+            .map_or(&[], |tile| tile.localities.as_slice())
+    }
+
+    pub fn put(&mut self, key: TileKey, tile: impl Into<CachedTile>) {
+        let tile = tile.into();
+        if tile.data.len() > self.budget_bytes {
+            return;
+        }
+
+        self.used_bytes += tile.data.len();
+        if let Some(previous) = self.entries.put(key, tile) {
+            self.used_bytes -= previous.data.len();
+        }
+
+        self.evict_over_budget();
+    }
+
+    fn evict_over_budget(&mut self) {
+        while self.used_bytes > self.budget_bytes {
+            match self.entries.pop_lru() {
+                Some((_, evicted)) => {
+                    self.used_bytes -= evicted.data.len();
+                    self.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used_bytes = 0;
+    }
+
+    /// Changes the byte budget, evicting tiles until the cache fits in it.
+    pub fn set_budget(&mut self, budget_bytes: usize) {
+        self.budget_bytes = budget_bytes;
+        self.evict_over_budget();
+    }
+
+    pub fn stats(&self) -> TileCacheStats {
+        let lookups = self.hits + self.misses;
+        TileCacheStats {
+            entries: self.entries.len(),
+            used_bytes: self.used_bytes,
+            budget_bytes: self.budget_bytes,
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            hit_rate: if lookups > 0 {
+                self.hits as f64 / lookups as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
+
 pub struct MapState {
-    pub spatial_index: Arc<RwLock<RTree<SpatialIndexEntry>>>,
+    /// One R-tree per source kind, so each tile endpoint only sees its own archives.
+    pub spatial_index: Arc<RwLock<HashMap<SourceKind, RTree<SpatialIndexEntry>>>>,
 
     pub reader_cache: Arc<RwLock<LruCache<String, Arc<AsyncPmTilesReader<MmapBackend>>>>>,
 
+    /// When each locality last served a map tile, read from its archive or
+    /// the tile cache, for evicting the least recently viewed localities.
+    pub last_viewed: Arc<Mutex<HashMap<String, SystemTime>>>,
+
+    pub tile_cache: Arc<RwLock<TileCache>>,
+
+    pub tile_options: Arc<RwLock<TileOptions>>,
+
     pub locality_metadata: Arc<RwLock<HashMap<String, LocalityMetadata>>>,
 
     pub pmtiles_dir: Arc<RwLock<Option<PathBuf>>>,
+
+    /// User-set locality priorities, taking precedence over the archive metadata.
+    pub priority_overrides: Arc<RwLock<HashMap<String, i32>>>,
+
+    /// Keeps the pmtiles directory watcher alive once the readers are initialized.
+    pub pmtiles_watcher: Mutex<Option<PmtilesWatcher>>,
+
+    /// Serializes rescans of the pmtiles directory.
+    pub reload_lock: tokio::sync::Mutex<()>,
 }
 
 impl MapState {
     pub fn new() -> Self {
+        Self::with_settings(&MapSettings::default())
+    }
+
+    pub fn with_settings(settings: &MapSettings) -> Self {
         Self {
-            spatial_index: Arc::new(RwLock::new(RTree::new())),
-            reader_cache: Arc::new(RwLock::new(LruCache::new(MAX_CACHED_READERS))),
+            spatial_index: Arc::new(RwLock::new(HashMap::new())),
+            reader_cache: Arc::new(RwLock::new(LruCache::new(settings.max_cached_readers))),
+            last_viewed: Arc::new(Mutex::new(HashMap::new())),
+            tile_cache: Arc::new(RwLock::new(TileCache::new(settings.tile_cache_budget_bytes))),
+            tile_options: Arc::new(RwLock::new(settings.tile)),
             locality_metadata: Arc::new(RwLock::new(HashMap::new())),
             pmtiles_dir: Arc::new(RwLock::new(None)),
+            priority_overrides: Arc::new(RwLock::new(
+                settings.priority_overrides.clone().into_iter().collect(),
+            )),
+            pmtiles_watcher: Mutex::new(None),
+            reload_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Records that `locality_id` served a tile just now.
+    pub fn mark_viewed(&self, locality_id: &str) {
+        if let Ok(mut last_viewed) = self.last_viewed.lock() {
+            last_viewed.insert(locality_id.to_string(), SystemTime::now());
         }
     }
 
@@ -131,6 +299,89 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn tile_cache_evicts_by_byte_budget() {
+        let mut cache = TileCache::new(10);
+        let key = |x| TileKey {
+            source: SourceKind::Vector,
+            z: 14,
+            x,
+            y: 0,
+        };
+
+        cache.put(key(0), vec![0; 4]);
+        cache.put(key(1), vec![0; 4]);
+        assert!(cache.get(&key(0)).is_some());
+
+        // Evicts the least recently used tile (x = 1) to stay within 10 bytes.
+        cache.put(key(2), vec![0; 4]);
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.used_bytes, 8);
+        assert_eq!(stats.evictions, 1);
+        assert!(cache.get(&key(1)).is_none());
+        assert!(cache.get(&key(0)).is_some());
+        assert!(cache.get(&key(2)).is_some());
+    }
+
+    #[test]
+    fn tile_cache_shrinks_to_new_budget() {
+        let mut cache = TileCache::new(12);
+        for x in 0..3 {
+            let key = TileKey {
+                source: SourceKind::Vector,
+                z: 14,
+                x,
+                y: 0,
+            };
+            cache.put(key, vec![0; 4]);
+        }
+
+        cache.set_budget(5);
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.used_bytes, 4);
+        assert_eq!(stats.budget_bytes, 5);
+    }
+
+    #[test]
+    fn tile_cache_tracks_hits_and_misses() {
+        let mut cache = TileCache::new(1024);
+        let key = TileKey {
+            source: SourceKind::Vector,
+            z: 1,
+            x: 0,
+            y: 0,
+        };
+
+        assert!(cache.get(&key).is_none());
+        cache.put(key, vec![1, 2, 3]);
+        assert_eq!(cache.get(&key), Some(vec![1, 2, 3]));
+        assert_eq!(cache.peek(&key), Some(vec![1, 2, 3]));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hit_rate, 0.5);
+    }
+
+    #[test]
+    fn tile_cache_skips_tiles_over_budget() {
+        let mut cache = TileCache::new(2);
+        let key = TileKey {
+            source: SourceKind::Vector,
+            z: 0,
+            x: 0,
+            y: 0,
+        };
+        cache.put(key, vec![0; 3]);
+
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().used_bytes, 0);
+    }
+
     #[tokio::test]
     async fn map_state_initialization() {
         let state = MapState::new();
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -20,13 +21,21 @@ impl BoundingBox {
         }
     }
 
-    #[allow(dead_code)]
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        (self.min_lon..=self.max_lon).contains(&lon) && (self.min_lat..=self.max_lat).contains(&lat)
+    }
+
     pub fn intersects(&self, other: &BoundingBox) -> bool {
         self.min_lon <= other.max_lon
             && self.max_lon >= other.min_lon
             && self.min_lat <= other.max_lat
             && self.max_lat >= other.min_lat
     }
+
+    /// Area in square degrees, used to prefer more detailed extracts.
+    pub fn area(&self) -> f64 {
+        (self.max_lon - self.min_lon).max(0.0) * (self.max_lat - self.min_lat).max(0.0)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -37,6 +46,25 @@ pub struct CenterPoint {
     pub zoom: u8,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LonLat {
+    pub lon: f64,
+    pub lat: f64,
+}
+
+/// Kind of tiles an archive holds; each kind has its own spatial index and
+/// tile endpoint so sources can be layered on the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// Mapbox Vector Tile basemap.
+    Vector,
+    /// Image tiles such as hillshading or imagery.
+    Raster,
+    /// Elevation encoded as terrain-RGB or terrarium images.
+    Terrain,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LocalityMetadata {
@@ -48,6 +76,34 @@ pub struct LocalityMetadata {
     pub center: CenterPoint,
     pub min_zoom: u8,
     pub max_zoom: u8,
+    /// Higher priorities win when several localities cover the same tile.
+    pub priority: i32,
+    pub build_date: Option<String>,
+    pub file_size: u64,
+    /// Archive modification time, in seconds since the Unix epoch.
+    pub modified_at: Option<u64>,
+    pub vector_layers: Vec<VectorLayerInfo>,
+    pub attribution: Option<String>,
+    pub source_kind: SourceKind,
+    /// Tile format from the archive header, e.g. `mvt` or `png`.
+    pub tile_type: String,
+    /// Tile compression from the archive header, e.g. `gzip`.
+    pub tile_compression: String,
+    pub version: Option<String>,
+    /// DEM encoding of terrain archives, `mapbox` or `terrarium`.
+    pub encoding: Option<String>,
+}
+
+/// A `vector_layers` entry from the archive metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorLayerInfo {
+    pub id: String,
+    /// Attribute names mapped to their type, e.g. `"name": "String"`.
+    pub fields: BTreeMap<String, String>,
+    pub description: Option<String>,
+    pub min_zoom: Option<u8>,
+    pub max_zoom: Option<u8>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,4 +122,83 @@ pub struct MultiPmtilesInfo {
     pub combined_center: CenterPoint,
     pub min_zoom: u8,
     pub max_zoom: u8,
+    /// Deepest zoom served, overzooming past the archives' `max_zoom`.
+    pub overzoom_max_zoom: u8,
+}
+
+/// Localities added, replaced or removed by a rescan of the pmtiles directory.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalityChanges {
+    pub added: Vec<LocalityMetadata>,
+    pub updated: Vec<LocalityMetadata>,
+    pub removed: Vec<String>,
+}
+
+impl LocalityChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Upper bound for `TileOptions::overzoom_levels`.
+pub const MAX_OVERZOOM_LEVELS: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileOptions {
+    /// Merge the tiles of every locality covering a tile instead of serving the first one found.
+    pub composite: bool,
+    /// Zoom levels past a locality's `max_zoom` served by clipping its deepest tiles.
+    pub overzoom_levels: u8,
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self {
+            composite: true,
+            overzoom_levels: 4,
+        }
+    }
+}
+
+impl TileOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.overzoom_levels > MAX_OVERZOOM_LEVELS {
+            return Err(format!(
+                "Overzoom levels must be at most {}",
+                MAX_OVERZOOM_LEVELS
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileCacheStats {
+    pub entries: usize,
+    pub used_bytes: usize,
+    pub budget_bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub hit_rate: f64,
+}
+
+/// Human-readable description of a coordinate, most specific part first.
+/// Parts missing from the tiles are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseGeocodeResult {
+    /// House number and street, e.g. `24 Sussex Drive`.
+    pub address: Option<String>,
+    pub street: Option<String>,
+    pub neighbourhood: Option<String>,
+    pub locality: Option<String>,
+    pub region: Option<String>,
+    /// The locality archive covering the coordinate.
+    pub locality_id: Option<String>,
+    /// The parts above joined into a single label, e.g. `Main Street, Centretown, Ottawa`.
+    pub label: Option<String>,
 }
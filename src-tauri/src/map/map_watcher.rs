@@ -0,0 +1,67 @@
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use super::map_service;
+use super::map_state::MapState;
+
+/// Event emitted with a `LocalityChanges` payload whenever archives are
+/// added to, replaced in or removed from the pmtiles directory.
+pub const LOCALITIES_CHANGED_EVENT: &str = "localities-changed";
+
+/// Quiet period before a burst of filesystem events triggers a rescan, so an
+/// archive being copied or downloaded is only picked up once it settles.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(750);
+
+pub type PmtilesWatcher = Debouncer<RecommendedWatcher>;
+
+pub fn watch_pmtiles_dir(app: &tauri::AppHandle, dir: &Path) -> Result<PmtilesWatcher, String> {
+    let app = app.clone();
+
+    let mut debouncer =
+        new_debouncer(
+            WATCH_DEBOUNCE,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => {
+                    if events.iter().any(|event| is_pmtiles_path(&event.path)) {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move { reload_and_notify(&app).await });
+                    }
+                }
+                Err(e) => eprintln!("Warning: PMTiles directory watcher error: {}", e),
+            },
+        )
+        .map_err(|e| format!("Failed to create PMTiles directory watcher: {}", e))?;
+
+    debouncer
+        .watcher()
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch '{}': {}", dir.display(), e))?;
+
+    Ok(debouncer)
+}
+
+/// Rescans the pmtiles directory and emits `LOCALITIES_CHANGED_EVENT` when
+/// anything changed.
+pub async fn reload_and_notify(app: &tauri::AppHandle) {
+    let state = app.state::<MapState>();
+
+    match map_service::reload_localities(&state).await {
+        Ok(changes) if !changes.is_empty() => {
+            if let Err(e) = app.emit(LOCALITIES_CHANGED_EVENT, &changes) {
+                eprintln!(
+                    "Warning: Failed to emit {}: {}",
+                    LOCALITIES_CHANGED_EVENT, e
+                );
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: Failed to reload localities: {}", e),
+    }
+}
+
+fn is_pmtiles_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "pmtiles")
+}
@@ -1,6 +1,13 @@
 pub mod map_cmd;
-mod map_service;
-mod map_state;
+mod map_geocode;
+pub(crate) mod map_locality_cache;
+pub(crate) mod map_mvt;
+pub mod map_protocol;
+pub(crate) mod map_query;
+pub(crate) mod map_service;
+pub(crate) mod map_state;
 pub mod map_types;
+mod map_watcher;
 
 pub use map_state::MapState;
+pub use map_watcher::reload_and_notify;
@@ -0,0 +1,14 @@
+//! Live turn-by-turn navigation along a computed route
+//!
+//! This module provides:
+//! - A navigation session snapping position updates to the active route
+//! - Progress, remaining distance, ETA and next maneuver reported as events
+//! - Off-route detection with automatic rerouting to the same destination
+
+pub mod navigation_cmd;
+mod navigation_service;
+mod navigation_session;
+mod navigation_state;
+pub mod navigation_types;
+
+pub use navigation_state::NavigationState;
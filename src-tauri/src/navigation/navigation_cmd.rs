@@ -0,0 +1,44 @@
+use tauri::State;
+
+use super::navigation_service;
+use super::navigation_state::NavigationState;
+use super::navigation_types::{NavigationProgress, PositionFix};
+use crate::map::map_types::LonLat;
+use crate::routing::routing_types::{Language, Route, RoutingProfile};
+
+#[tauri::command]
+pub async fn start_navigation(
+    app: tauri::AppHandle,
+    from: LonLat,
+    to: LonLat,
+    profile: RoutingProfile,
+    language: Option<Language>,
+) -> Result<Route, String> {
+    navigation_service::start_navigation(&app, from, to, profile, language.unwrap_or_default())
+        .await
+}
+
+#[tauri::command]
+pub async fn update_position(
+    app: tauri::AppHandle,
+    fix: PositionFix,
+) -> Result<NavigationProgress, String> {
+    navigation_service::update_position(&app, fix).await
+}
+
+#[tauri::command]
+pub async fn stop_navigation(state: State<'_, NavigationState>) -> Result<(), String> {
+    navigation_service::stop_navigation(&state).await;
+    Ok(())
+}
+
+/// The route being followed, e.g. to redraw it after the webview reloads.
+#[tauri::command]
+pub async fn get_active_route(state: State<'_, NavigationState>) -> Result<Option<Route>, String> {
+    Ok(state
+        .session
+        .lock()
+        .await
+        .as_ref()
+        .map(|session| session.route().clone()))
+}
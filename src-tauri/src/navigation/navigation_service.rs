@@ -0,0 +1,99 @@
+use tauri::{Emitter, Manager};
+
+use super::navigation_session::NavigationSession;
+use super::navigation_state::NavigationState;
+use super::navigation_types::{NavigationProgress, NavigationReroute, PositionFix};
+use crate::map::map_types::LonLat;
+use crate::routing::routing_service;
+use crate::routing::routing_types::{Language, Route, RoutingProfile};
+use crate::routing::RoutingState;
+
+/// Emitted with a `NavigationProgress` for every position update.
+pub const NAVIGATION_PROGRESS_EVENT: &str = "navigation-progress";
+
+/// Emitted with a `NavigationReroute` once a new route replaced the one the
+/// user left.
+pub const NAVIGATION_REROUTED_EVENT: &str = "navigation-rerouted";
+
+/// Emitted when the destination is reached; the session ends with it.
+pub const NAVIGATION_ARRIVED_EVENT: &str = "navigation-arrived";
+
+pub async fn start_navigation(
+    app: &tauri::AppHandle,
+    from: LonLat,
+    to: LonLat,
+    profile: RoutingProfile,
+    language: Language,
+) -> Result<Route, String> {
+    let routing_state = app.state::<RoutingState>();
+    let route = routing_service::route(from, to, profile, language, &routing_state).await?;
+
+    let navigation_state = app.state::<NavigationState>();
+    *navigation_state.session.lock().await =
+        Some(NavigationSession::new(route.clone(), to, profile, language));
+
+    Ok(route)
+}
+
+pub async fn stop_navigation(state: &tauri::State<'_, NavigationState>) {
+    state.session.lock().await.take();
+}
+
+/// Advances the active session and reports through events. Leaving the route
+/// triggers a reroute from the current position to the same destination.
+pub async fn update_position(
+    app: &tauri::AppHandle,
+    fix: PositionFix,
+) -> Result<NavigationProgress, String> {
+    let navigation_state = app.state::<NavigationState>();
+    let mut guard = navigation_state.session.lock().await;
+    let session = guard
+        .as_mut()
+        .ok_or_else(|| "No navigation session is active".to_string())?;
+
+    let update = session.update(fix);
+    emit(app, NAVIGATION_PROGRESS_EVENT, &update.progress);
+
+    if update.arrived {
+        guard.take();
+        emit(app, NAVIGATION_ARRIVED_EVENT, &update.progress);
+    } else if update.off_route {
+        let routing_state = app.state::<RoutingState>();
+        let from = LonLat {
+            lon: fix.lon,
+            lat: fix.lat,
+        };
+
+        match routing_service::route(
+            from,
+            session.destination,
+            session.profile,
+            session.language,
+            &routing_state,
+        )
+        .await
+        {
+            Ok(route) => {
+                session.replace_route(route.clone());
+                emit(
+                    app,
+                    NAVIGATION_REROUTED_EVENT,
+                    &NavigationReroute {
+                        route,
+                        distance_from_route_m: update.progress.distance_from_route_m,
+                    },
+                );
+            }
+            // Retried on the next off-route fix.
+            Err(e) => eprintln!("Warning: Failed to reroute: {}", e),
+        }
+    }
+
+    Ok(update.progress)
+}
+
+fn emit<S: serde::Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: &S) {
+    if let Err(e) = app.emit(event, payload.clone()) {
+        eprintln!("Warning: Failed to emit {}: {}", event, e);
+    }
+}
@@ -0,0 +1,324 @@
+//! Tracking of the user's progress along an active route.
+//!
+//! Each fix is projected onto the route, never snapping back more than one
+//! stretch behind the previous fix, so loops and parallel carriageways
+//! don't make progress jump around.
+
+use super::navigation_types::{NavigationProgress, NavigationUpdate, PositionFix};
+use crate::map::map_query::haversine_distance_m;
+use crate::map::map_types::LonLat;
+use crate::routing::routing_types::{Language, Route, RoutingProfile};
+
+/// Fixes further than this from the route (plus their accuracy) are off route.
+const OFF_ROUTE_DISTANCE_M: f64 = 40.0;
+
+/// Accuracy beyond this doesn't widen the off-route threshold any further.
+const MAX_ACCURACY_ALLOWANCE_M: f64 = 50.0;
+
+/// Consecutive off-route fixes needed before rerouting, so a single noisy
+/// fix doesn't trigger it.
+const OFF_ROUTE_FIXES: u32 = 3;
+
+/// Remaining distance at which the destination counts as reached.
+const ARRIVAL_DISTANCE_M: f64 = 25.0;
+
+const EARTH_RADIUS_M: f64 = 6_371_008.8;
+
+pub struct NavigationSession {
+    route: Route,
+    pub destination: LonLat,
+    pub profile: RoutingProfile,
+    pub language: Language,
+    /// Distance along the route of each coordinate.
+    cumulative_m: Vec<f64>,
+    /// Index of the route stretch the last fix was matched to.
+    stretch: usize,
+    off_route_fixes: u32,
+}
+
+impl NavigationSession {
+    pub fn new(
+        route: Route,
+        destination: LonLat,
+        profile: RoutingProfile,
+        language: Language,
+    ) -> Self {
+        let cumulative_m = cumulative_distances(&route.coordinates);
+        Self {
+            route,
+            destination,
+            profile,
+            language,
+            cumulative_m,
+            stretch: 0,
+            off_route_fixes: 0,
+        }
+    }
+
+    pub fn route(&self) -> &Route {
+        &self.route
+    }
+
+    /// Switches to a new route, e.g. after rerouting, keeping the destination.
+    pub fn replace_route(&mut self, route: Route) {
+        self.cumulative_m = cumulative_distances(&route.coordinates);
+        self.route = route;
+        self.stretch = 0;
+        self.off_route_fixes = 0;
+    }
+
+    pub fn update(&mut self, fix: PositionFix) -> NavigationUpdate {
+        let coordinates = &self.route.coordinates;
+        let first = self.stretch.saturating_sub(1);
+
+        let (stretch, t, distance_from_route_m) = (first..coordinates.len().saturating_sub(1))
+            .map(|i| {
+                let (t, distance) = project(fix, coordinates[i], coordinates[i + 1]);
+                (i, t, distance)
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .unwrap_or((0, 0.0, 0.0));
+
+        let threshold = OFF_ROUTE_DISTANCE_M
+            + fix
+                .accuracy_m
+                .unwrap_or(0.0)
+                .clamp(0.0, MAX_ACCURACY_ALLOWANCE_M);
+        let on_route = distance_from_route_m <= threshold;
+        if on_route {
+            self.stretch = stretch;
+            self.off_route_fixes = 0;
+        } else {
+            self.off_route_fixes += 1;
+        }
+
+        let stretch_length = self.cumulative_m.get(stretch + 1).copied().unwrap_or(0.0)
+            - self.cumulative_m.get(stretch).copied().unwrap_or(0.0);
+        let distance_travelled_m =
+            self.cumulative_m.get(stretch).copied().unwrap_or(0.0) + t * stretch_length;
+        let total_m = self.cumulative_m.last().copied().unwrap_or(0.0);
+        let distance_remaining_m = (total_m - distance_travelled_m).max(0.0);
+        let duration_remaining_s = self.duration_after(distance_travelled_m);
+
+        let next_instruction_index = self
+            .route
+            .instructions
+            .iter()
+            .position(|i| self.cumulative_m[i.coordinate_index] > distance_travelled_m);
+        let next_instruction = next_instruction_index.map(|i| self.route.instructions[i].clone());
+        let distance_to_next_instruction_m = next_instruction
+            .as_ref()
+            .map(|i| self.cumulative_m[i.coordinate_index] - distance_travelled_m);
+
+        let snapped = match (coordinates.get(stretch), coordinates.get(stretch + 1)) {
+            (Some(a), Some(b)) => [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])],
+            _ => [fix.lon, fix.lat],
+        };
+
+        NavigationUpdate {
+            progress: NavigationProgress {
+                snapped,
+                distance_from_route_m,
+                distance_travelled_m,
+                distance_remaining_m,
+                duration_remaining_s,
+                eta_ms: fix.timestamp_ms + (duration_remaining_s * 1000.0).round() as u64,
+                next_instruction_index,
+                next_instruction,
+                distance_to_next_instruction_m,
+            },
+            off_route: self.off_route_fixes >= OFF_ROUTE_FIXES,
+            arrived: on_route && distance_remaining_m <= ARRIVAL_DISTANCE_M,
+        }
+    }
+
+    /// Travel time for the rest of the route, from each segment's own speed.
+    fn duration_after(&self, distance_travelled_m: f64) -> f64 {
+        self.route
+            .segments
+            .iter()
+            .map(|segment| {
+                let start = self.cumulative_m[segment.start_index];
+                let end = self.cumulative_m[segment.end_index];
+                if end <= start {
+                    return 0.0;
+                }
+                let remaining = (end - distance_travelled_m.max(start)).max(0.0);
+                segment.duration_s * remaining / (end - start)
+            })
+            .sum()
+    }
+}
+
+fn cumulative_distances(coordinates: &[[f64; 2]]) -> Vec<f64> {
+    let mut total = 0.0;
+    let mut cumulative = Vec::with_capacity(coordinates.len());
+    cumulative.push(0.0);
+    for pair in coordinates.windows(2) {
+        total += haversine_distance_m(pair[0][0], pair[0][1], pair[1][0], pair[1][1]);
+        cumulative.push(total);
+    }
+    cumulative.truncate(coordinates.len());
+    cumulative
+}
+
+/// Projects the fix onto the stretch `a`-`b`, returning the position along it
+/// (0 to 1) and the distance to it in meters, in a local planar approximation.
+fn project(fix: PositionFix, a: [f64; 2], b: [f64; 2]) -> (f64, f64) {
+    let scale_x = fix.lat.to_radians().cos() * EARTH_RADIUS_M;
+    let to_local = |[lon, lat]: [f64; 2]| {
+        [
+            (lon - fix.lon).to_radians() * scale_x,
+            (lat - fix.lat).to_radians() * EARTH_RADIUS_M,
+        ]
+    };
+    let (a, b) = (to_local(a), to_local(b));
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let length_sq = ab[0] * ab[0] + ab[1] * ab[1];
+
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (-(a[0] * ab[0] + a[1] * ab[1]) / length_sq).clamp(0.0, 1.0)
+    };
+    let closest = [a[0] + t * ab[0], a[1] + t * ab[1]];
+    (t, closest[0].hypot(closest[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::routing_types::{RoadClass, RouteSegment};
+
+    /// About 111 m per 0.001° of latitude.
+    const STEP: f64 = 0.001;
+
+    /// North on Elgin for ~222 m, then east on Wellington for ~157 m.
+    fn test_route() -> Route {
+        let segment = |name: &str, start_index, end_index, distance_m: f64| RouteSegment {
+            name: Some(name.to_string()),
+            road_ref: None,
+            class: RoadClass::Minor,
+            roundabout: false,
+            distance_m,
+            duration_s: distance_m / 10.0,
+            start_index,
+            end_index,
+            ends_at_junction: true,
+        };
+        let coordinates = vec![
+            [-75.7, 45.4],
+            [-75.7, 45.4 + STEP],
+            [-75.7, 45.4 + 2.0 * STEP],
+            [-75.7 + 2.0 * STEP, 45.4 + 2.0 * STEP],
+        ];
+        let cumulative = cumulative_distances(&coordinates);
+        let mut route = Route {
+            profile: RoutingProfile::Car,
+            distance_m: cumulative[3],
+            duration_s: cumulative[3] / 10.0,
+            coordinates,
+            segments: vec![
+                segment("Elgin", 0, 2, cumulative[2]),
+                segment("Wellington", 2, 3, cumulative[3] - cumulative[2]),
+            ],
+            instructions: Vec::new(),
+        };
+        route.instructions =
+            crate::routing::routing_instructions::build_instructions(&route, Language::En);
+        route
+    }
+
+    fn session() -> NavigationSession {
+        let route = test_route();
+        let destination = LonLat {
+            lon: route.coordinates[3][0],
+            lat: route.coordinates[3][1],
+        };
+        NavigationSession::new(route, destination, RoutingProfile::Car, Language::En)
+    }
+
+    /// Replays `(lon, lat)` fixes one second apart.
+    fn replay(session: &mut NavigationSession, trace: &[[f64; 2]]) -> Vec<NavigationUpdate> {
+        trace
+            .iter()
+            .enumerate()
+            .map(|(i, [lon, lat])| {
+                session.update(PositionFix {
+                    lon: *lon,
+                    lat: *lat,
+                    accuracy_m: Some(5.0),
+                    timestamp_ms: 1_000 * i as u64,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replay_tracks_progress_and_arrival() {
+        let mut session = session();
+        let total = session.route().distance_m;
+        let updates = replay(
+            &mut session,
+            &[
+                [-75.7, 45.4],
+                // A few meters east of Elgin, halfway up.
+                [-75.69995, 45.4 + STEP],
+                [-75.7 + STEP, 45.4 + 2.0 * STEP],
+                [-75.7 + 1.9 * STEP, 45.4 + 2.0 * STEP],
+            ],
+        );
+
+        let first = &updates[0].progress;
+        assert!(first.distance_travelled_m.abs() < 1e-6);
+        assert!((first.duration_remaining_s - total / 10.0).abs() < 1e-6);
+        assert_eq!(first.eta_ms, (total / 10.0 * 1000.0).round() as u64);
+        assert_eq!(
+            first.next_instruction.as_ref().unwrap().text,
+            "Turn right onto Wellington"
+        );
+
+        let halfway = &updates[1].progress;
+        assert!((halfway.distance_travelled_m - 111.2).abs() < 1.0);
+        assert!(halfway.distance_from_route_m > 3.0 && halfway.distance_from_route_m < 5.0);
+        assert!((halfway.distance_to_next_instruction_m.unwrap() - 111.2).abs() < 1.0);
+        assert!(updates[..3].iter().all(|u| !u.off_route && !u.arrived));
+
+        let last = &updates[3];
+        assert!(last.arrived);
+        assert_eq!(
+            last.progress.next_instruction.as_ref().unwrap().text,
+            "You have arrived at your destination"
+        );
+    }
+
+    #[test]
+    fn replay_detects_leaving_the_route() {
+        let mut session = session();
+        // Turning left instead of right at the end of Elgin.
+        let updates = replay(
+            &mut session,
+            &[
+                [-75.7, 45.4 + 1.5 * STEP],
+                [-75.7, 45.4 + 2.0 * STEP],
+                [-75.7 - STEP, 45.4 + 2.0 * STEP],
+                [-75.7 - 2.0 * STEP, 45.4 + 2.0 * STEP],
+                [-75.7 - 3.0 * STEP, 45.4 + 2.0 * STEP],
+            ],
+        );
+
+        let off_route: Vec<bool> = updates.iter().map(|u| u.off_route).collect();
+        assert_eq!(off_route, vec![false, false, false, false, true]);
+        // Progress stays where the user left the route.
+        assert!((updates[4].progress.distance_travelled_m - 222.4).abs() < 1.0);
+
+        session.replace_route(test_route());
+        let update = session.update(PositionFix {
+            lon: -75.7,
+            lat: 45.4,
+            accuracy_m: None,
+            timestamp_ms: 0,
+        });
+        assert!(!update.off_route);
+    }
+}
@@ -0,0 +1,23 @@
+use tokio::sync::Mutex;
+
+use super::navigation_session::NavigationSession;
+
+pub struct NavigationState {
+    /// The active session, if any. Held across rerouting so position updates
+    /// are applied one at a time.
+    pub session: Mutex<Option<NavigationSession>>,
+}
+
+impl NavigationState {
+    pub fn new() -> Self {
+        Self {
+            session: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for NavigationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
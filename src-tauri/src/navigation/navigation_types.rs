@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::routing::routing_types::{Instruction, Route};
+
+/// A position report from the device.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionFix {
+    pub lon: f64,
+    pub lat: f64,
+    /// Horizontal accuracy radius, in meters.
+    pub accuracy_m: Option<f64>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationProgress {
+    /// The fix projected onto the route.
+    pub snapped: [f64; 2],
+    pub distance_from_route_m: f64,
+    pub distance_travelled_m: f64,
+    pub distance_remaining_m: f64,
+    pub duration_remaining_s: f64,
+    /// Estimated arrival, in milliseconds since the Unix epoch.
+    pub eta_ms: u64,
+    /// Index in `Route::instructions` of the next maneuver.
+    pub next_instruction_index: Option<usize>,
+    pub next_instruction: Option<Instruction>,
+    pub distance_to_next_instruction_m: Option<f64>,
+}
+
+/// Outcome of feeding a fix to a navigation session.
+#[derive(Debug, Clone)]
+pub struct NavigationUpdate {
+    pub progress: NavigationProgress,
+    /// Set once the user has been away from the route for several fixes.
+    pub off_route: bool,
+    pub arrived: bool,
+}
+
+/// Payload of the rerouted event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NavigationReroute {
+    pub route: Route,
+    /// Distance from the abandoned route when the reroute was triggered.
+    pub distance_from_route_m: f64,
+}
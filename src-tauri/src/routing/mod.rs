@@ -0,0 +1,21 @@
+//! Offline routing over the downloaded localities
+//!
+//! This module provides:
+//! - Road graph extraction from the `roads` layer, with class, oneway and
+//!   access tags, cached on disk per locality
+//! - Stitching of neighbouring locality graphs into one routing network
+//! - A* routing for the car, bike and foot profiles
+//! - The `route` command returning geometry, distance and duration
+//! - Localized turn-by-turn instructions (English and French)
+
+mod routing_astar;
+pub mod routing_cmd;
+mod routing_graph;
+pub(crate) mod routing_instructions;
+pub(crate) mod routing_service;
+mod routing_state;
+mod routing_stitch;
+pub mod routing_types;
+
+pub use routing_service::{listen_for_locality_changes, spawn_graph_sync};
+pub use routing_state::RoutingState;
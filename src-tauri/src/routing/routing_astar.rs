@@ -0,0 +1,363 @@
+//! A* search over a road graph, from and to points snapped onto its edges.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::routing_graph::{RoadGraph, Snap};
+use super::routing_types::{Route, RouteSegment, RoutingProfile};
+use crate::map::map_query::haversine_distance_m;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct QueueItem {
+    /// Cost so far plus the heuristic.
+    estimate: f64,
+    node: u32,
+}
+
+impl Eq for QueueItem {}
+
+impl Ord for QueueItem {
+    /// Reversed so the max-heap pops the lowest estimate first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for QueueItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Portion of an edge travelled by the route, as distances from the edge's
+/// `from` node.
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    edge: u32,
+    start_m: f64,
+    end_m: f64,
+}
+
+/// Fastest route for the profile between two coordinates, or `None` when
+/// either can't be snapped to the graph or no path connects them.
+pub fn find_route(
+    graph: &RoadGraph,
+    from: [f64; 2],
+    to: [f64; 2],
+    profile: RoutingProfile,
+) -> Option<Route> {
+    let start = graph.snap(from, profile)?;
+    let goal = graph.snap(to, profile)?;
+    let steps = shortest_path(graph, &start, &goal, profile)?;
+    Some(build_route(graph, &steps, profile))
+}
+
+fn shortest_path(
+    graph: &RoadGraph,
+    start: &Snap,
+    goal: &Snap,
+    profile: RoutingProfile,
+) -> Option<Vec<Step>> {
+    let speed_ms = |edge: u32| {
+        profile
+            .speed_kmh(graph.edges[edge as usize].attributes.class)
+            .map(|kmh| kmh / 3.6)
+    };
+    let max_speed_ms = profile.max_speed_kmh() / 3.6;
+    let heuristic = |node: u32| {
+        let [lon, lat] = graph.nodes[node as usize];
+        haversine_distance_m(lon, lat, goal.point[0], goal.point[1]) / max_speed_ms
+    };
+
+    let start_edge = &graph.edges[start.edge as usize];
+    let goal_edge = &graph.edges[goal.edge as usize];
+
+    // Both points on the same edge: travelling along it directly is a candidate.
+    let mut best: Option<(f64, Vec<Step>)> = None;
+    if start.edge == goal.edge {
+        let forward = goal.offset_m >= start.offset_m;
+        if start_edge.attributes.allows(profile, forward) {
+            let cost = (goal.offset_m - start.offset_m).abs() / speed_ms(start.edge)?;
+            best = Some((
+                cost,
+                vec![Step {
+                    edge: start.edge,
+                    start_m: start.offset_m,
+                    end_m: goal.offset_m,
+                }],
+            ));
+        }
+    }
+
+    let mut cost = vec![f64::INFINITY; graph.nodes.len()];
+    // Edge used to reach each node, `None` for the ends of the start edge.
+    let mut previous: Vec<Option<u32>> = vec![None; graph.nodes.len()];
+    let mut queue = BinaryHeap::new();
+
+    let start_speed = speed_ms(start.edge)?;
+    for (node, forward, distance) in [
+        (start_edge.to, true, start_edge.length_m - start.offset_m),
+        (start_edge.from, false, start.offset_m),
+    ] {
+        let node_cost = distance / start_speed;
+        if start_edge.attributes.allows(profile, forward) && node_cost < cost[node as usize] {
+            cost[node as usize] = node_cost;
+            queue.push(QueueItem {
+                estimate: node_cost + heuristic(node),
+                node,
+            });
+        }
+    }
+
+    let goal_speed = speed_ms(goal.edge)?;
+    let mut goal_entry: Option<(u32, bool)> = None;
+
+    while let Some(QueueItem { estimate, node }) = queue.pop() {
+        if best
+            .as_ref()
+            .is_some_and(|(best_cost, _)| estimate >= *best_cost)
+        {
+            break;
+        }
+        let node_cost = cost[node as usize];
+        if estimate > node_cost + heuristic(node) {
+            continue; // stale entry
+        }
+
+        // Finishing along the goal edge from this node.
+        for (end, forward, distance) in [
+            (goal_edge.from, true, goal.offset_m),
+            (goal_edge.to, false, goal_edge.length_m - goal.offset_m),
+        ] {
+            if end == node && goal_edge.attributes.allows(profile, forward) {
+                let total = node_cost + distance / goal_speed;
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_cost, _)| total < *best_cost)
+                {
+                    best = Some((total, Vec::new()));
+                    goal_entry = Some((node, forward));
+                }
+            }
+        }
+
+        for &edge_index in &graph.adjacency[node as usize] {
+            let edge = &graph.edges[edge_index as usize];
+            let (next, forward) = if edge.from == node {
+                (edge.to, true)
+            } else {
+                (edge.from, false)
+            };
+            if !edge.attributes.allows(profile, forward) {
+                continue;
+            }
+            let Some(duration) = edge.duration_s(profile) else {
+                continue;
+            };
+
+            let next_cost = node_cost + duration;
+            if next_cost < cost[next as usize] {
+                cost[next as usize] = next_cost;
+                previous[next as usize] = Some(edge_index);
+                queue.push(QueueItem {
+                    estimate: next_cost + heuristic(next),
+                    node: next,
+                });
+            }
+        }
+    }
+
+    let (_, direct_steps) = best?;
+    let Some((entry, forward_into_goal)) = goal_entry else {
+        return Some(direct_steps);
+    };
+
+    // Walk back from the node where the goal edge was entered.
+    let mut steps = vec![Step {
+        edge: goal.edge,
+        start_m: if forward_into_goal {
+            0.0
+        } else {
+            goal_edge.length_m
+        },
+        end_m: goal.offset_m,
+    }];
+    let mut node = entry;
+    while let Some(edge_index) = previous[node as usize] {
+        let edge = &graph.edges[edge_index as usize];
+        let forward = edge.to == node;
+        steps.push(Step {
+            edge: edge_index,
+            start_m: if forward { 0.0 } else { edge.length_m },
+            end_m: if forward { edge.length_m } else { 0.0 },
+        });
+        node = if forward { edge.from } else { edge.to };
+    }
+    steps.push(Step {
+        edge: start.edge,
+        start_m: start.offset_m,
+        end_m: if node == start_edge.to {
+            start_edge.length_m
+        } else {
+            0.0
+        },
+    });
+    steps.reverse();
+
+    Some(steps)
+}
+
+fn build_route(graph: &RoadGraph, steps: &[Step], profile: RoutingProfile) -> Route {
+    let mut coordinates: Vec<[f64; 2]> = Vec::new();
+    let mut segments = Vec::new();
+
+    for step in steps {
+        let distance_m = (step.end_m - step.start_m).abs();
+        if distance_m == 0.0 {
+            continue;
+        }
+        let edge = &graph.edges[step.edge as usize];
+        let speed_ms = profile.speed_kmh(edge.attributes.class).unwrap_or(1.0) / 3.6;
+
+        let end_node = if step.end_m == edge.length_m {
+            Some(edge.to)
+        } else if step.end_m == 0.0 {
+            Some(edge.from)
+        } else {
+            None
+        };
+        let ends_at_junction =
+            end_node.is_some_and(|node| graph.adjacency[node as usize].len() > 2);
+
+        let geometry = edge.slice(step.start_m, step.end_m);
+        let start_index = coordinates.len().saturating_sub(1);
+        let skip = usize::from(coordinates.last() == geometry.first());
+        coordinates.extend(geometry.into_iter().skip(skip));
+
+        segments.push(RouteSegment {
+            name: edge.attributes.name.clone(),
+            road_ref: edge.attributes.road_ref.clone(),
+            class: edge.attributes.class,
+            roundabout: edge.attributes.roundabout,
+            distance_m,
+            duration_s: distance_m / speed_ms,
+            start_index: if segments.is_empty() { 0 } else { start_index },
+            end_index: coordinates.len() - 1,
+            ends_at_junction,
+        });
+    }
+
+    Route {
+        profile,
+        distance_m: segments.iter().map(|s| s.distance_m).sum(),
+        duration_s: segments.iter().map(|s| s.duration_s).sum(),
+        coordinates,
+        segments,
+        instructions: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::map_query::tile_to_lonlat;
+    use crate::routing::routing_graph::tests::roads_tile;
+    use crate::routing::routing_graph::GraphBuilder;
+
+    /// Coordinate of a point of tile 14/0/0, in tile units.
+    fn lonlat(x: f64, y: f64) -> [f64; 2] {
+        tile_to_lonlat([x / 4096.0, y / 4096.0], 14)
+    }
+
+    /// A one-way square block: the direct street A-B is one-way against the
+    /// direction of travel, so cars go around the block while pedestrians
+    /// walk straight there.
+    fn block_graph() -> RoadGraph {
+        let mut builder = GraphBuilder::new(14);
+        builder.add_tile(
+            &roads_tile(&[
+                (
+                    vec![[2000, 1000], [1000, 1000]],
+                    "residential",
+                    "North",
+                    true,
+                ),
+                (
+                    vec![[1000, 1000], [1000, 2000]],
+                    "residential",
+                    "West",
+                    false,
+                ),
+                (
+                    vec![[1000, 2000], [2000, 2000]],
+                    "residential",
+                    "South",
+                    false,
+                ),
+                (
+                    vec![[2000, 2000], [2000, 1000]],
+                    "residential",
+                    "East",
+                    false,
+                ),
+            ]),
+            (14, 0, 0),
+        );
+        builder.finish("block", 0, None)
+    }
+
+    fn names(route: &Route) -> Vec<&str> {
+        route
+            .segments
+            .iter()
+            .map(|s| s.name.as_deref().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn car_route_respects_oneway() {
+        let graph = block_graph();
+        let from = lonlat(1200.0, 1000.0);
+        let to = lonlat(1800.0, 1000.0);
+
+        let route = find_route(&graph, from, to, RoutingProfile::Car).unwrap();
+        assert_eq!(
+            names(&route),
+            vec!["North", "West", "South", "East", "North"]
+        );
+        assert_eq!(
+            route.segments.last().unwrap().end_index,
+            route.coordinates.len() - 1
+        );
+
+        let walk = find_route(&graph, from, to, RoutingProfile::Foot).unwrap();
+        assert_eq!(names(&walk), vec!["North"]);
+        assert!(walk.distance_m < route.distance_m);
+        let straight = haversine_distance_m(from[0], from[1], to[0], to[1]);
+        assert!((walk.distance_m - straight).abs() < 0.01);
+        assert!((walk.duration_s - walk.distance_m / (5.0 / 3.6)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn route_geometry_is_continuous() {
+        let graph = block_graph();
+        let route = find_route(
+            &graph,
+            lonlat(1000.0, 1500.0),
+            lonlat(2000.0, 1500.0),
+            RoutingProfile::Bike,
+        )
+        .unwrap();
+
+        assert_eq!(names(&route), vec!["West", "South", "East"]);
+        for pair in route.segments.windows(2) {
+            assert_eq!(pair[0].end_index, pair[1].start_index);
+        }
+        let [lon, lat] = route.coordinates[0];
+        let [expected_lon, expected_lat] = lonlat(1000.0, 1500.0);
+        assert!((lon - expected_lon).abs() < 1e-9 && (lat - expected_lat).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,17 @@
+use tauri::State;
+
+use super::routing_service;
+use super::routing_state::RoutingState;
+use super::routing_types::{Language, Route, RoutingProfile};
+use crate::map::map_types::LonLat;
+
+#[tauri::command]
+pub async fn route(
+    from: LonLat,
+    to: LonLat,
+    profile: RoutingProfile,
+    language: Option<Language>,
+    state: State<'_, RoutingState>,
+) -> Result<Route, String> {
+    routing_service::route(from, to, profile, language.unwrap_or_default(), &state).await
+}
@@ -0,0 +1,631 @@
+//! Road graph extraction from the `roads` layer of vector tiles.
+//!
+//! Lines are clipped to their own tile so each road is seen once, and the
+//! points where they cross tile edges are matched up with the neighbouring
+//! tile's. Graph nodes are junctions and dead ends; the vertices in between
+//! are kept as edge geometry.
+
+use rstar::primitives::{GeomWithData, Line};
+use rstar::RTree;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use super::routing_types::{RoadClass, RoutingProfile};
+use crate::map::map_mvt::{self, decode_geometry, GeomType, Tile};
+use crate::map::map_query::{feature_properties, haversine_distance_m, tile_to_lonlat};
+use crate::map::map_types::BoundingBox;
+
+/// Bumped whenever the extraction or the serialized format changes.
+pub const GRAPH_VERSION: u32 = 1;
+
+/// Layers holding routable lines in the Protomaps and OpenMapTiles schemas.
+const ROAD_LAYERS: [&str; 2] = ["roads", "transportation"];
+
+/// Crossing points computed on each side of a tile edge are matched when
+/// they are at most this far apart, in tile units.
+const EDGE_SNAP_TOLERANCE: f64 = 2.0;
+
+/// Points further than this from any usable road are not snapped to the graph.
+pub const MAX_SNAP_DISTANCE_M: f64 = 1_000.0;
+
+/// Which directions of an edge the OSM `oneway` tag allows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Oneway {
+    #[default]
+    No,
+    /// Only from `from` to `to`.
+    Forward,
+    /// Only from `to` to `from`.
+    Backward,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadAttributes {
+    pub class: RoadClass,
+    pub name: Option<String>,
+    pub road_ref: Option<String>,
+    pub oneway: Oneway,
+    pub roundabout: bool,
+    pub car: bool,
+    pub bike: bool,
+    pub foot: bool,
+}
+
+impl RoadAttributes {
+    /// Reads the routing tags of a road feature, or `None` for lines that
+    /// aren't roads (railways, ferries, ...).
+    pub fn from_properties(properties: &JsonValue) -> Option<Self> {
+        let prop = |key: &str| match properties.get(key)? {
+            JsonValue::String(s) => Some(s.clone()),
+            JsonValue::Number(n) => Some(n.to_string()),
+            JsonValue::Bool(b) => Some(b.to_string()),
+            _ => None,
+        };
+
+        let class = ["kind_detail", "subclass", "class", "highway"]
+            .iter()
+            .filter_map(|key| prop(key))
+            .find_map(|highway| RoadClass::from_highway(&highway))
+            .or_else(|| prop("kind").and_then(|kind| RoadClass::from_kind(&kind)))?;
+
+        let roundabout = prop("junction").as_deref() == Some("roundabout")
+            || is_yes(prop("roundabout").as_deref());
+        let oneway = match prop("oneway").as_deref() {
+            Some("-1" | "reverse") => Oneway::Backward,
+            value if is_yes(value) || roundabout => Oneway::Forward,
+            _ => Oneway::No,
+        };
+
+        let restricted = matches!(prop("access").as_deref(), Some("no" | "private"));
+        let allowed = |keys: &[&str]| {
+            let tag = keys.iter().find_map(|key| prop(key));
+            match tag.as_deref() {
+                Some("no" | "private") => false,
+                Some(_) => true,
+                None => !restricted,
+            }
+        };
+
+        Some(Self {
+            class,
+            name: prop("name").filter(|s| !s.is_empty()),
+            road_ref: prop("ref").filter(|s| !s.is_empty()),
+            oneway,
+            roundabout,
+            car: allowed(&["motor_vehicle", "motorcar"]),
+            bike: allowed(&["bicycle"]),
+            foot: allowed(&["foot"]),
+        })
+    }
+
+    /// Whether the profile may travel along the edge in the given direction.
+    pub fn allows(&self, profile: RoutingProfile, forward: bool) -> bool {
+        let access = match profile {
+            RoutingProfile::Car => self.car,
+            RoutingProfile::Bike => self.bike,
+            RoutingProfile::Foot => self.foot,
+        };
+        let direction = !profile.respects_oneway()
+            || match self.oneway {
+                Oneway::No => true,
+                Oneway::Forward => forward,
+                Oneway::Backward => !forward,
+            };
+        access && direction && profile.speed_kmh(self.class).is_some()
+    }
+}
+
+fn is_yes(value: Option<&str>) -> bool {
+    matches!(value, Some("yes" | "true" | "1"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadEdge {
+    pub from: u32,
+    pub to: u32,
+    /// `[lon, lat]` positions from `from` to `to`, both included.
+    pub geometry: Vec<[f64; 2]>,
+    pub length_m: f64,
+    pub attributes: RoadAttributes,
+}
+
+impl RoadEdge {
+    /// Travel time over the whole edge, or `None` if the profile can't use it.
+    pub fn duration_s(&self, profile: RoutingProfile) -> Option<f64> {
+        profile
+            .speed_kmh(self.attributes.class)
+            .map(|kmh| self.length_m / (kmh / 3.6))
+    }
+
+    /// Geometry between two distances from `from`, in travel order; `start_m`
+    /// is greater than `end_m` when travelling towards `from`.
+    pub fn slice(&self, start_m: f64, end_m: f64) -> Vec<[f64; 2]> {
+        let (lo, hi) = (start_m.min(end_m), start_m.max(end_m));
+        let mut points = vec![self.point_at(lo)];
+        let mut travelled = 0.0;
+
+        for pair in self.geometry.windows(2) {
+            travelled += haversine_distance_m(pair[0][0], pair[0][1], pair[1][0], pair[1][1]);
+            if travelled > lo && travelled < hi {
+                points.push(pair[1]);
+            }
+        }
+        points.push(self.point_at(hi));
+        points.dedup();
+
+        if start_m > end_m {
+            points.reverse();
+        }
+        points
+    }
+
+    /// Position at a distance from `from` along the edge.
+    pub fn point_at(&self, offset_m: f64) -> [f64; 2] {
+        let mut remaining = offset_m.max(0.0);
+
+        for pair in self.geometry.windows(2) {
+            let length = haversine_distance_m(pair[0][0], pair[0][1], pair[1][0], pair[1][1]);
+            if remaining <= length && length > 0.0 {
+                let t = remaining / length;
+                return [
+                    pair[0][0] + t * (pair[1][0] - pair[0][0]),
+                    pair[0][1] + t * (pair[1][1] - pair[0][1]),
+                ];
+            }
+            remaining -= length;
+        }
+
+        self.geometry.last().copied().unwrap_or_default()
+    }
+}
+
+/// Segment of an edge in the snapping index: `(edge, index of the segment)`.
+type SegmentEntry = GeomWithData<Line<[f64; 2]>, (u32, usize)>;
+
+/// Where a coordinate lands on the graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snap {
+    pub edge: u32,
+    /// Distance from the edge's `from` node to the snapped point.
+    pub offset_m: f64,
+    pub point: [f64; 2],
+    /// Distance from the original coordinate to the snapped point.
+    pub distance_m: f64,
+}
+
+/// Routable road graph of a locality, persisted between runs.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoadGraph {
+    pub version: u32,
+    pub locality_id: String,
+    pub file_size: u64,
+    pub modified_at: Option<u64>,
+    pub bounds: BoundingBox,
+    /// `[lon, lat]` of each node.
+    pub nodes: Vec<[f64; 2]>,
+    pub edges: Vec<RoadEdge>,
+
+    /// Edges touching each node.
+    #[serde(skip)]
+    pub adjacency: Vec<Vec<u32>>,
+
+    #[serde(skip)]
+    segments: RTree<SegmentEntry>,
+}
+
+impl RoadGraph {
+    pub fn new(
+        locality_id: &str,
+        file_size: u64,
+        modified_at: Option<u64>,
+        nodes: Vec<[f64; 2]>,
+        edges: Vec<RoadEdge>,
+    ) -> Self {
+        let bounds = nodes.iter().fold(
+            BoundingBox::new(f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |b, [lon, lat]| {
+                BoundingBox::new(
+                    b.min_lon.min(*lon),
+                    b.min_lat.min(*lat),
+                    b.max_lon.max(*lon),
+                    b.max_lat.max(*lat),
+                )
+            },
+        );
+
+        let mut graph = Self {
+            version: GRAPH_VERSION,
+            locality_id: locality_id.to_string(),
+            file_size,
+            modified_at,
+            bounds,
+            nodes,
+            edges,
+            adjacency: Vec::new(),
+            segments: RTree::new(),
+        };
+        graph.prepare();
+        graph
+    }
+
+    /// Rebuilds the lookup structures skipped by serialization.
+    pub fn prepare(&mut self) {
+        self.adjacency = vec![Vec::new(); self.nodes.len()];
+        for (i, edge) in self.edges.iter().enumerate() {
+            self.adjacency[edge.from as usize].push(i as u32);
+            if edge.to != edge.from {
+                self.adjacency[edge.to as usize].push(i as u32);
+            }
+        }
+
+        let segments = self
+            .edges
+            .iter()
+            .enumerate()
+            .flat_map(|(i, edge)| {
+                edge.geometry.windows(2).enumerate().map(move |(j, pair)| {
+                    GeomWithData::new(Line::new(pair[0], pair[1]), (i as u32, j))
+                })
+            })
+            .collect();
+        self.segments = RTree::bulk_load(segments);
+    }
+
+    pub fn is_current(&self, file_size: u64, modified_at: Option<u64>) -> bool {
+        self.version == GRAPH_VERSION
+            && self.file_size == file_size
+            && self.modified_at == modified_at
+    }
+
+    /// Snaps a coordinate to the nearest edge the profile can use.
+    pub fn snap(&self, [lon, lat]: [f64; 2], profile: RoutingProfile) -> Option<Snap> {
+        let entry = self
+            .segments
+            .nearest_neighbor_iter(&[lon, lat])
+            .find(|entry| {
+                let attributes = &self.edges[entry.data.0 as usize].attributes;
+                attributes.allows(profile, true) || attributes.allows(profile, false)
+            })?;
+
+        let (edge_index, segment) = entry.data;
+        let edge = &self.edges[edge_index as usize];
+        let point = entry.geom().nearest_point(&[lon, lat]);
+
+        let offset_m = edge.geometry[..=segment]
+            .windows(2)
+            .map(|p| haversine_distance_m(p[0][0], p[0][1], p[1][0], p[1][1]))
+            .sum::<f64>()
+            + haversine_distance_m(
+                edge.geometry[segment][0],
+                edge.geometry[segment][1],
+                point[0],
+                point[1],
+            );
+        let distance_m = haversine_distance_m(lon, lat, point[0], point[1]);
+
+        (distance_m <= MAX_SNAP_DISTANCE_M).then_some(Snap {
+            edge: edge_index,
+            offset_m: offset_m.min(edge.length_m),
+            point,
+            distance_m,
+        })
+    }
+}
+
+/// A road clipped to one tile, as a list of node ids.
+struct Way {
+    nodes: Vec<u32>,
+    attributes: RoadAttributes,
+}
+
+/// Accumulates the roads of every tile of a locality at a single zoom level.
+pub struct GraphBuilder {
+    zoom: u8,
+    /// Node positions in global tile units (`tile * extent + offset`).
+    points: Vec<[f64; 2]>,
+    vertex_ids: HashMap<[i64; 2], u32>,
+    /// Nodes lying on tile edges, keyed by axis and edge coordinate.
+    edge_crossings: HashMap<(usize, i64), Vec<(f64, u32)>>,
+    extent: Option<f64>,
+    ways: Vec<Way>,
+}
+
+impl GraphBuilder {
+    pub fn new(zoom: u8) -> Self {
+        Self {
+            zoom,
+            points: Vec::new(),
+            vertex_ids: HashMap::new(),
+            edge_crossings: HashMap::new(),
+            extent: None,
+            ways: Vec::new(),
+        }
+    }
+
+    pub fn add_tile(&mut self, tile: &Tile, (z, x, y): (u8, u32, u32)) {
+        debug_assert_eq!(z, self.zoom);
+
+        for layer in tile
+            .layers
+            .iter()
+            .filter(|l| ROAD_LAYERS.contains(&l.name.as_str()))
+        {
+            // Global tile units only line up between tiles of the same extent.
+            let extent = *self.extent.get_or_insert(f64::from(layer.extent()));
+            let scale = extent / f64::from(layer.extent());
+            let origin = [f64::from(x) * extent, f64::from(y) * extent];
+
+            for feature in &layer.features {
+                if feature.r#type() != GeomType::Linestring {
+                    continue;
+                }
+                let properties = feature_properties(layer, &feature.tags);
+                let Some(attributes) = RoadAttributes::from_properties(&properties) else {
+                    continue;
+                };
+
+                for part in decode_geometry(&feature.geometry) {
+                    let line: Vec<[f64; 2]> = part
+                        .iter()
+                        .map(|p| [f64::from(p[0]) * scale, f64::from(p[1]) * scale])
+                        .collect();
+
+                    for clipped in map_mvt::clip_line(&line, 0.0, extent) {
+                        let mut nodes: Vec<u32> = clipped
+                            .iter()
+                            .map(|p| self.node_id(*p, origin, extent))
+                            .collect();
+                        nodes.dedup();
+                        if nodes.len() >= 2 {
+                            self.ways.push(Way {
+                                nodes,
+                                attributes: attributes.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Vertices inside a tile are matched exactly; points on a tile edge are
+    /// matched with the crossing computed by the neighbouring tile.
+    fn node_id(&mut self, local: [f64; 2], origin: [f64; 2], extent: f64) -> u32 {
+        let on_edge = (0..2).find_map(|axis| {
+            [0.0, extent]
+                .into_iter()
+                .find(|edge| (local[axis] - edge).abs() < 1e-6)
+                .map(|edge| (axis, origin[axis] + edge))
+        });
+
+        match on_edge {
+            Some((axis, edge)) => {
+                let key = (axis, edge as i64);
+                let along = origin[1 - axis] + local[1 - axis];
+                let existing = self.edge_crossings.get(&key).and_then(|crossings| {
+                    crossings
+                        .iter()
+                        .find(|(other, _)| (other - along).abs() <= EDGE_SNAP_TOLERANCE)
+                });
+                if let Some(&(_, id)) = existing {
+                    return id;
+                }
+                let mut point = [0.0; 2];
+                point[axis] = edge;
+                point[1 - axis] = along;
+                let id = self.push_point(point);
+                self.edge_crossings
+                    .entry(key)
+                    .or_default()
+                    .push((along, id));
+                id
+            }
+            None => {
+                let point = [origin[0] + local[0], origin[1] + local[1]];
+                let key = [point[0].round() as i64, point[1].round() as i64];
+                if let Some(&id) = self.vertex_ids.get(&key) {
+                    return id;
+                }
+                let id = self.push_point(point);
+                self.vertex_ids.insert(key, id);
+                id
+            }
+        }
+    }
+
+    fn push_point(&mut self, point: [f64; 2]) -> u32 {
+        self.points.push(point);
+        (self.points.len() - 1) as u32
+    }
+
+    /// Splits the ways at junctions and dead ends to form the graph edges.
+    pub fn finish(self, locality_id: &str, file_size: u64, modified_at: Option<u64>) -> RoadGraph {
+        let extent = self.extent.unwrap_or(4096.0);
+        let zoom = self.zoom;
+        let to_lonlat = |[gx, gy]: [f64; 2]| tile_to_lonlat([gx / extent, gy / extent], zoom);
+
+        let mut uses = vec![0u32; self.points.len()];
+        for way in &self.ways {
+            for &node in &way.nodes {
+                uses[node as usize] += 1;
+            }
+            // Endpoints always become graph nodes.
+            uses[way.nodes[0] as usize] += 1;
+            uses[way.nodes[way.nodes.len() - 1] as usize] += 1;
+        }
+
+        let mut graph_ids: Vec<Option<u32>> = vec![None; self.points.len()];
+        let mut nodes: Vec<[f64; 2]> = Vec::new();
+        let mut graph_id = |point: u32, nodes: &mut Vec<[f64; 2]>| {
+            *graph_ids[point as usize].get_or_insert_with(|| {
+                nodes.push(to_lonlat(self.points[point as usize]));
+                (nodes.len() - 1) as u32
+            })
+        };
+
+        let mut edges = Vec::new();
+        for way in &self.ways {
+            let mut start = 0;
+            for i in 1..way.nodes.len() {
+                if uses[way.nodes[i] as usize] < 2 && i < way.nodes.len() - 1 {
+                    continue;
+                }
+                let geometry: Vec<[f64; 2]> = way.nodes[start..=i]
+                    .iter()
+                    .map(|&p| to_lonlat(self.points[p as usize]))
+                    .collect();
+                let length_m = geometry
+                    .windows(2)
+                    .map(|p| haversine_distance_m(p[0][0], p[0][1], p[1][0], p[1][1]))
+                    .sum();
+
+                edges.push(RoadEdge {
+                    from: graph_id(way.nodes[start], &mut nodes),
+                    to: graph_id(way.nodes[i], &mut nodes),
+                    geometry,
+                    length_m,
+                    attributes: way.attributes.clone(),
+                });
+                start = i;
+            }
+        }
+
+        RoadGraph::new(locality_id, file_size, modified_at, nodes, edges)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::map::map_mvt::{encode_geometry, Feature, Layer, TilePoint, Value};
+
+    fn string(v: &str) -> Value {
+        Value {
+            string_value: Some(v.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// A `roads` layer whose features each carry `kind_detail`, `name` and,
+    /// when set, `oneway`.
+    pub(crate) fn roads_tile(roads: &[(Vec<TilePoint>, &str, &str, bool)]) -> Tile {
+        let mut values = Vec::new();
+        let features = roads
+            .iter()
+            .map(|(points, kind_detail, name, oneway)| {
+                let mut tags = Vec::new();
+                let mut tag = |key: u32, value: &str| {
+                    values.push(string(value));
+                    tags.extend([key, values.len() as u32 - 1]);
+                };
+                tag(0, kind_detail);
+                tag(1, name);
+                if *oneway {
+                    tag(2, "yes");
+                }
+                Feature {
+                    id: None,
+                    tags,
+                    r#type: Some(GeomType::Linestring as i32),
+                    geometry: encode_geometry(GeomType::Linestring, std::slice::from_ref(points)),
+                }
+            })
+            .collect();
+
+        Tile {
+            layers: vec![Layer {
+                version: 2,
+                name: "roads".to_string(),
+                features,
+                keys: vec!["kind_detail".into(), "name".into(), "oneway".into()],
+                values,
+                extent: Some(4096),
+            }],
+        }
+    }
+
+    #[test]
+    fn road_attributes_from_properties() {
+        let attributes = RoadAttributes::from_properties(&serde_json::json!({
+            "kind": "major_road",
+            "kind_detail": "primary_link",
+            "name": "Rideau Street",
+            "oneway": "yes",
+        }))
+        .unwrap();
+        assert_eq!(attributes.class, RoadClass::Primary);
+        assert_eq!(attributes.oneway, Oneway::Forward);
+        assert!(attributes.allows(RoutingProfile::Car, true));
+        assert!(!attributes.allows(RoutingProfile::Car, false));
+        assert!(attributes.allows(RoutingProfile::Foot, false));
+
+        let omt = RoadAttributes::from_properties(&serde_json::json!({
+            "class": "path",
+            "subclass": "cycleway",
+            "oneway": -1,
+        }))
+        .unwrap();
+        assert_eq!(omt.class, RoadClass::Cycleway);
+        assert_eq!(omt.oneway, Oneway::Backward);
+        assert!(!omt.allows(RoutingProfile::Car, false));
+
+        let private = RoadAttributes::from_properties(&serde_json::json!({
+            "kind_detail": "service",
+            "access": "private",
+            "foot": "yes",
+        }))
+        .unwrap();
+        assert!(!private.car && !private.bike && private.foot);
+
+        assert!(RoadAttributes::from_properties(&serde_json::json!({ "kind": "rail" })).is_none());
+    }
+
+    #[test]
+    fn builder_splits_at_junctions_and_joins_tiles() {
+        let mut builder = GraphBuilder::new(14);
+        // A street crossing from tile 0 into tile 1 and a side street
+        // branching off it in tile 0.
+        builder.add_tile(
+            &roads_tile(&[
+                (
+                    vec![[1000, 2048], [3000, 2048], [4200, 2048]],
+                    "residential",
+                    "Main",
+                    false,
+                ),
+                (
+                    vec![[3000, 2048], [3000, 1000]],
+                    "residential",
+                    "Side",
+                    false,
+                ),
+            ]),
+            (14, 0, 0),
+        );
+        builder.add_tile(
+            &roads_tile(&[(
+                vec![[-104, 2048], [1000, 2048]],
+                "residential",
+                "Main",
+                false,
+            )]),
+            (14, 1, 0),
+        );
+        let graph = builder.finish("test", 0, None);
+
+        // Dead ends at both ends of Main and Side, the junction, and the tile
+        // crossing shared by both halves of Main.
+        assert_eq!(graph.nodes.len(), 5);
+        assert_eq!(graph.edges.len(), 4);
+
+        let degree = |i: usize| graph.adjacency[i].len();
+        let mut degrees: Vec<usize> = (0..graph.nodes.len()).map(degree).collect();
+        degrees.sort();
+        assert_eq!(degrees, vec![1, 1, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,474 @@
+//! Turn-by-turn instructions for a computed route.
+//!
+//! Consecutive segments of the same road are grouped into legs; a maneuver
+//! is emitted where the road changes, where the route turns sharply at a
+//! junction, and around roundabouts.
+
+use super::routing_types::{Instruction, Language, ManeuverType, Route, RouteSegment};
+
+/// Turns sharper than this at a junction are announced even on the same road.
+const TURN_THRESHOLD_DEG: f64 = 45.0;
+
+/// Consecutive segments announced as a single instruction, inclusive.
+#[derive(Debug, Clone, Copy)]
+struct Leg {
+    first: usize,
+    last: usize,
+    roundabout: bool,
+}
+
+pub fn build_instructions(route: &Route, language: Language) -> Vec<Instruction> {
+    let segments = &route.segments;
+    let Some(last_segment) = segments.last() else {
+        return Vec::new();
+    };
+
+    let legs = group_legs(route);
+    let mut instructions = Vec::new();
+    let mut i = 0;
+
+    while i < legs.len() {
+        let leg = legs[i];
+        let first = &segments[leg.first];
+        let bearing_after = bearing_after(route, first);
+        let bearing_before = match leg.first {
+            0 => 0.0,
+            n => bearing_before(route, &segments[n - 1]),
+        };
+
+        // A roundabout is announced together with the road it exits onto.
+        let (maneuver, exit, covered) = if i == 0 {
+            (ManeuverType::Depart, None, &legs[i..=i])
+        } else if leg.roundabout {
+            let exits = segments[leg.first..=leg.last]
+                .iter()
+                .filter(|s| s.ends_at_junction)
+                .count()
+                .max(1) as u32;
+            let end = (i + 1).min(legs.len() - 1);
+            (ManeuverType::Roundabout, Some(exits), &legs[i..=end])
+        } else {
+            let turn = turn_maneuver(bearing_after - bearing_before);
+            (turn, None, &legs[i..=i])
+        };
+
+        let onto = &segments[covered[covered.len() - 1].first];
+        let road_name = if maneuver == ManeuverType::Roundabout && covered.len() == 1 {
+            None
+        } else {
+            road_label(onto).map(str::to_string)
+        };
+        let covered_segments = &segments[covered[0].first..=covered[covered.len() - 1].last];
+        let distance_m: f64 = covered_segments.iter().map(|s| s.distance_m).sum();
+
+        instructions.push(Instruction {
+            maneuver,
+            text: maneuver_text(
+                language,
+                maneuver,
+                road_name.as_deref(),
+                exit,
+                bearing_after,
+            ),
+            road_name,
+            distance_m,
+            distance_text: format_distance(distance_m, language),
+            duration_s: covered_segments.iter().map(|s| s.duration_s).sum(),
+            bearing_before,
+            bearing_after,
+            roundabout_exit: exit,
+            coordinate_index: first.start_index,
+            location: route.coordinates[first.start_index],
+        });
+
+        i += covered.len();
+    }
+
+    let arrival = last_segment.end_index;
+    instructions.push(Instruction {
+        maneuver: ManeuverType::Arrive,
+        text: maneuver_text(language, ManeuverType::Arrive, None, None, 0.0),
+        road_name: None,
+        distance_m: 0.0,
+        distance_text: format_distance(0.0, language),
+        duration_s: 0.0,
+        bearing_before: bearing_before(route, last_segment),
+        bearing_after: 0.0,
+        roundabout_exit: None,
+        coordinate_index: arrival,
+        location: route.coordinates[arrival],
+    });
+
+    instructions
+}
+
+fn group_legs(route: &Route) -> Vec<Leg> {
+    let segments = &route.segments;
+    let mut legs: Vec<Leg> = Vec::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        match legs.last_mut() {
+            Some(leg) if same_leg(route, &segments[i - 1], segment) => leg.last = i,
+            _ => legs.push(Leg {
+                first: i,
+                last: i,
+                roundabout: segment.roundabout,
+            }),
+        }
+    }
+
+    legs
+}
+
+/// Whether `segment` carries on along the same road as `previous` without a
+/// maneuver worth announcing.
+fn same_leg(route: &Route, previous: &RouteSegment, segment: &RouteSegment) -> bool {
+    if segment.roundabout != previous.roundabout {
+        return false;
+    }
+    if segment.roundabout {
+        return true;
+    }
+
+    let turn = normalize_angle(bearing_after(route, segment) - bearing_before(route, previous));
+    let turns_at_junction = previous.ends_at_junction && turn.abs() >= TURN_THRESHOLD_DEG;
+    road_label(segment) == road_label(previous) && !turns_at_junction
+}
+
+fn road_label(segment: &RouteSegment) -> Option<&str> {
+    segment.name.as_deref().or(segment.road_ref.as_deref())
+}
+
+/// Bearing of the last stretch of a segment. A segment collapsed to a single
+/// point takes the bearing leading into it, or out of it at the route start.
+fn bearing_before(route: &Route, segment: &RouteSegment) -> f64 {
+    let end = segment.end_index;
+    (0..end)
+        .rev()
+        .find_map(|i| stretch_bearing(route, i))
+        .or_else(|| (end..route.coordinates.len()).find_map(|i| stretch_bearing(route, i)))
+        .unwrap_or(0.0)
+}
+
+/// Bearing of the first stretch of a segment. A segment collapsed to a single
+/// point takes the bearing leading out of it, or into it at the route end.
+fn bearing_after(route: &Route, segment: &RouteSegment) -> f64 {
+    let start = segment.start_index;
+    (start..route.coordinates.len())
+        .find_map(|i| stretch_bearing(route, i))
+        .or_else(|| (0..start).rev().find_map(|i| stretch_bearing(route, i)))
+        .unwrap_or(0.0)
+}
+
+/// Bearing from coordinate `i` to the next one, unless they coincide.
+fn stretch_bearing(route: &Route, i: usize) -> Option<f64> {
+    let from = *route.coordinates.get(i)?;
+    let to = *route.coordinates.get(i.checked_add(1)?)?;
+    (from != to).then(|| bearing(from, to))
+}
+
+/// Initial great-circle bearing from `a` to `b`, in degrees clockwise from north.
+fn bearing(a: [f64; 2], b: [f64; 2]) -> f64 {
+    let (phi1, phi2) = (a[1].to_radians(), b[1].to_radians());
+    let d_lambda = (b[0] - a[0]).to_radians();
+    let y = d_lambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * d_lambda.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Maps an angle to `(-180, 180]`.
+fn normalize_angle(angle: f64) -> f64 {
+    let angle = angle.rem_euclid(360.0);
+    if angle > 180.0 {
+        angle - 360.0
+    } else {
+        angle
+    }
+}
+
+/// Classifies the change of bearing at a junction; positive turns are to the right.
+fn turn_maneuver(turn: f64) -> ManeuverType {
+    let turn = normalize_angle(turn);
+    match turn.abs() {
+        a if a < 20.0 => ManeuverType::Continue,
+        a if a >= 170.0 => ManeuverType::UTurn,
+        a if a < TURN_THRESHOLD_DEG && turn > 0.0 => ManeuverType::SlightRight,
+        a if a < TURN_THRESHOLD_DEG => ManeuverType::SlightLeft,
+        a if a < 135.0 && turn > 0.0 => ManeuverType::Right,
+        a if a < 135.0 => ManeuverType::Left,
+        _ if turn > 0.0 => ManeuverType::SharpRight,
+        _ => ManeuverType::SharpLeft,
+    }
+}
+
+fn maneuver_text(
+    language: Language,
+    maneuver: ManeuverType,
+    road: Option<&str>,
+    exit: Option<u32>,
+    bearing: f64,
+) -> String {
+    use ManeuverType::*;
+
+    let exit = exit.unwrap_or(1);
+    match language {
+        Language::En => {
+            let onto = |action: &str| match road {
+                Some(road) => format!("{} onto {}", action, road),
+                None => action.to_string(),
+            };
+            match maneuver {
+                Depart => match road {
+                    Some(road) => format!("Head {} on {}", cardinal(bearing, language), road),
+                    None => format!("Head {}", cardinal(bearing, language)),
+                },
+                Continue => match road {
+                    Some(road) => format!("Continue onto {}", road),
+                    None => "Continue straight".to_string(),
+                },
+                SlightLeft => onto("Turn slightly left"),
+                Left => onto("Turn left"),
+                SharpLeft => onto("Turn sharp left"),
+                SlightRight => onto("Turn slightly right"),
+                Right => onto("Turn right"),
+                SharpRight => onto("Turn sharp right"),
+                UTurn => onto("Make a U-turn"),
+                Roundabout => onto(&format!(
+                    "At the roundabout, take the {} exit",
+                    ordinal(exit, language)
+                )),
+                Arrive => "You have arrived at your destination".to_string(),
+            }
+        }
+        Language::Fr => {
+            let onto = |action: &str| match road {
+                Some(road) => format!("{} sur {}", action, road),
+                None => action.to_string(),
+            };
+            match maneuver {
+                Depart => onto(&format!("Dirigez-vous {}", cardinal(bearing, language))),
+                Continue => match road {
+                    Some(road) => format!("Continuez sur {}", road),
+                    None => "Continuez tout droit".to_string(),
+                },
+                SlightLeft => onto("Tournez légèrement à gauche"),
+                Left => onto("Tournez à gauche"),
+                SharpLeft => onto("Tournez franchement à gauche"),
+                SlightRight => onto("Tournez légèrement à droite"),
+                Right => onto("Tournez à droite"),
+                SharpRight => onto("Tournez franchement à droite"),
+                UTurn => onto("Faites demi-tour"),
+                Roundabout => onto(&format!(
+                    "Au rond-point, prenez la {} sortie",
+                    ordinal(exit, language)
+                )),
+                Arrive => "Vous êtes arrivé à destination".to_string(),
+            }
+        }
+    }
+}
+
+/// Compass direction of a bearing, phrased to follow "Head" / "Dirigez-vous".
+fn cardinal(bearing: f64, language: Language) -> &'static str {
+    let sector = ((bearing.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8;
+    match language {
+        Language::En => [
+            "north",
+            "northeast",
+            "east",
+            "southeast",
+            "south",
+            "southwest",
+            "west",
+            "northwest",
+        ][sector],
+        Language::Fr => [
+            "vers le nord",
+            "vers le nord-est",
+            "vers l'est",
+            "vers le sud-est",
+            "vers le sud",
+            "vers le sud-ouest",
+            "vers l'ouest",
+            "vers le nord-ouest",
+        ][sector],
+    }
+}
+
+fn ordinal(n: u32, language: Language) -> String {
+    match language {
+        Language::En => {
+            let suffix = match (n % 10, n % 100) {
+                (_, 11..=13) => "th",
+                (1, _) => "st",
+                (2, _) => "nd",
+                (3, _) => "rd",
+                _ => "th",
+            };
+            format!("{}{}", n, suffix)
+        }
+        Language::Fr if n == 1 => "1re".to_string(),
+        Language::Fr => format!("{}e", n),
+    }
+}
+
+/// Rounds to 10 m under a kilometre, to 100 m under 10 km, and to the
+/// kilometre beyond.
+pub fn format_distance(distance_m: f64, language: Language) -> String {
+    let text = if distance_m < 1_000.0 {
+        format!("{} m", ((distance_m / 10.0).round() * 10.0) as u64)
+    } else if distance_m < 10_000.0 {
+        format!("{:.1} km", distance_m / 1_000.0)
+    } else {
+        format!("{} km", (distance_m / 1_000.0).round() as u64)
+    };
+
+    match language {
+        Language::En => text,
+        Language::Fr => text.replace('.', ","),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::routing_types::{RoadClass, RoutingProfile};
+
+    fn segment(
+        name: &str,
+        (start_index, end_index): (usize, usize),
+        roundabout: bool,
+        ends_at_junction: bool,
+    ) -> RouteSegment {
+        RouteSegment {
+            name: Some(name.to_string()),
+            road_ref: None,
+            class: RoadClass::Minor,
+            roundabout,
+            distance_m: 100.0,
+            duration_s: 10.0,
+            start_index,
+            end_index,
+            ends_at_junction,
+        }
+    }
+
+    /// North on Elgin, right onto Wellington, three junctions around a
+    /// roundabout and out onto Rideau.
+    fn test_route() -> Route {
+        Route {
+            profile: RoutingProfile::Car,
+            distance_m: 700.0,
+            duration_s: 70.0,
+            coordinates: vec![
+                [0.0, 0.0],
+                [0.0, 0.001],
+                [0.001, 0.001],
+                [0.0015, 0.0015],
+                [0.002, 0.001],
+                [0.0015, 0.0005],
+                [0.0015, -0.001],
+            ],
+            segments: vec![
+                segment("Elgin", (0, 1), false, true),
+                segment("Wellington", (1, 2), false, true),
+                segment("Rond-point", (2, 3), true, true),
+                segment("Rond-point", (3, 4), true, true),
+                segment("Rond-point", (4, 5), true, true),
+                segment("Rideau", (5, 6), false, false),
+            ],
+            instructions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn instructions_for_turns_and_roundabouts() {
+        let instructions = build_instructions(&test_route(), Language::En);
+
+        let summary: Vec<_> = instructions
+            .iter()
+            .map(|i| (i.maneuver, i.text.as_str(), i.distance_m))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ManeuverType::Depart, "Head north on Elgin", 100.0),
+                (ManeuverType::Right, "Turn right onto Wellington", 100.0),
+                (
+                    ManeuverType::Roundabout,
+                    "At the roundabout, take the 3rd exit onto Rideau",
+                    400.0
+                ),
+                (
+                    ManeuverType::Arrive,
+                    "You have arrived at your destination",
+                    0.0
+                ),
+            ]
+        );
+        assert_eq!(instructions[2].roundabout_exit, Some(3));
+        assert_eq!(instructions[2].coordinate_index, 2);
+        assert!((instructions[1].bearing_before - 0.0).abs() < 1e-6);
+        assert!((instructions[1].bearing_after - 90.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn instructions_in_french() {
+        let texts: Vec<_> = build_instructions(&test_route(), Language::Fr)
+            .into_iter()
+            .map(|i| i.text)
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Dirigez-vous vers le nord sur Elgin",
+                "Tournez à droite sur Wellington",
+                "Au rond-point, prenez la 3e sortie sur Rideau",
+                "Vous êtes arrivé à destination",
+            ]
+        );
+    }
+
+    #[test]
+    fn same_road_segments_are_merged() {
+        let mut route = test_route();
+        route.coordinates.truncate(3);
+        route.coordinates[2] = [0.0, 0.002];
+        route.segments = vec![
+            segment("Elgin", (0, 1), false, true),
+            segment("Elgin", (1, 2), false, false),
+        ];
+
+        let instructions = build_instructions(&route, Language::En);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].distance_m, 200.0);
+        assert_eq!(instructions[0].distance_text, "200 m");
+    }
+
+    #[test]
+    fn collapsed_segments_take_neighbouring_bearings() {
+        let mut route = test_route();
+        route.coordinates = vec![[0.0, 0.0], [0.0, 0.0], [0.001, 0.0], [0.001, 0.0]];
+        route.segments = vec![
+            segment("Elgin", (0, 0), false, true),
+            segment("Wellington", (0, 2), false, true),
+            segment("Rideau", (2, 3), false, false),
+        ];
+
+        let instructions = build_instructions(&route, Language::En);
+        assert_eq!(instructions.len(), 4);
+        assert!((instructions[0].bearing_after - 90.0).abs() < 1e-3);
+        assert!((instructions[2].bearing_after - 90.0).abs() < 1e-3);
+        assert!((instructions[3].bearing_before - 90.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn format_distance_rounds_per_magnitude() {
+        assert_eq!(format_distance(296.0, Language::En), "300 m");
+        assert_eq!(format_distance(1_234.0, Language::En), "1.2 km");
+        assert_eq!(format_distance(1_234.0, Language::Fr), "1,2 km");
+        assert_eq!(format_distance(15_600.0, Language::Fr), "16 km");
+        assert_eq!(ordinal(1, Language::Fr), "1re");
+        assert_eq!(ordinal(12, Language::En), "12th");
+    }
+}
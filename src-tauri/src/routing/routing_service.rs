@@ -0,0 +1,146 @@
+use rstar::AABB;
+use std::future::Future;
+use std::sync::Arc;
+use tauri::Manager;
+
+use super::routing_astar::find_route;
+use super::routing_graph::{GraphBuilder, RoadGraph};
+use super::routing_instructions::build_instructions;
+use super::routing_state::RoutingState;
+use super::routing_stitch::stitch_graphs;
+use super::routing_types::{Language, Route, RoutingProfile};
+use crate::map::map_locality_cache::{self, visit_locality_tiles, LocalityArtifact, LocalityCache};
+use crate::map::map_types::{LocalityMetadata, LonLat, SourceKind};
+use crate::map::MapState;
+
+/// Zoom level roads are read at; Protomaps and OpenMapTiles archives carry
+/// every road with full detail from z14.
+const ROUTING_ZOOM: u8 = 14;
+
+pub fn spawn_graph_sync(app: &tauri::AppHandle) {
+    map_locality_cache::spawn_sync::<RoadGraph>(app);
+}
+
+pub fn listen_for_locality_changes(app: &tauri::AppHandle) {
+    map_locality_cache::listen_for_locality_changes::<RoadGraph>(app);
+}
+
+impl LocalityArtifact for RoadGraph {
+    const DIR_NAME: &'static str = "routing";
+    const DESCRIPTION: &'static str = "routing graph";
+
+    fn cache(app: &tauri::AppHandle) -> &LocalityCache<Self> {
+        &app.state::<RoutingState>().inner().graphs
+    }
+
+    fn is_current(&self, file_size: u64, modified_at: Option<u64>) -> bool {
+        self.is_current(file_size, modified_at)
+    }
+
+    fn build(
+        locality: &LocalityMetadata,
+        map_state: &tauri::State<'_, MapState>,
+    ) -> impl Future<Output = Result<Self, String>> + Send {
+        build_graph(locality, map_state)
+    }
+
+    fn prepare(&mut self) {
+        self.prepare();
+    }
+
+    /// Restitches the network from the synced graphs.
+    async fn synced(app: &tauri::AppHandle) {
+        let routing_state = app.state::<RoutingState>();
+        let map_state = app.state::<MapState>();
+        let network = build_network(&routing_state, &map_state).await;
+        *routing_state.network.write().await = Some(Arc::new(network));
+    }
+}
+
+/// Stitches the loaded graphs together, pairing the localities whose bounds
+/// touch or overlap in the map's spatial index.
+async fn build_network(
+    routing_state: &tauri::State<'_, RoutingState>,
+    map_state: &tauri::State<'_, MapState>,
+) -> RoadGraph {
+    let graphs = routing_state.graphs.read().await;
+    let mut ids: Vec<&String> = graphs.keys().collect();
+    ids.sort();
+
+    let mut neighbours = Vec::new();
+    if let Some(index) = map_state
+        .spatial_index
+        .read()
+        .await
+        .get(&SourceKind::Vector)
+    {
+        for (i, id) in ids.iter().enumerate() {
+            let bounds = graphs[*id].bounds;
+            let envelope = AABB::from_corners(
+                [bounds.min_lon, bounds.min_lat],
+                [bounds.max_lon, bounds.max_lat],
+            );
+            for entry in index.locate_in_envelope_intersecting(&envelope) {
+                if let Some(j) = ids.iter().position(|other| **other == entry.locality_id) {
+                    if i < j {
+                        neighbours.push((i, j));
+                    }
+                }
+            }
+        }
+    }
+
+    let graph_refs: Vec<&RoadGraph> = ids.iter().map(|id| graphs[*id].as_ref()).collect();
+    stitch_graphs(&graph_refs, &neighbours)
+}
+
+async fn build_graph(
+    locality: &LocalityMetadata,
+    map_state: &tauri::State<'_, MapState>,
+) -> Result<RoadGraph, String> {
+    let z = locality.max_zoom.min(ROUTING_ZOOM);
+    let mut builder = GraphBuilder::new(z);
+    visit_locality_tiles(&locality.id, z, map_state, |tile, coord| {
+        builder.add_tile(tile, coord)
+    })
+    .await?;
+
+    Ok(builder.finish(&locality.id, locality.file_size, locality.modified_at))
+}
+
+/// Routes over the network of every loaded locality, so a route may cross
+/// any number of archives.
+pub async fn route(
+    from: LonLat,
+    to: LonLat,
+    profile: RoutingProfile,
+    language: Language,
+    state: &tauri::State<'_, RoutingState>,
+) -> Result<Route, String> {
+    for point in [from, to] {
+        if !(-180.0..=180.0).contains(&point.lon) || !(-90.0..=90.0).contains(&point.lat) {
+            return Err(format!("Invalid coordinate: {}, {}", point.lon, point.lat));
+        }
+    }
+
+    let network = state
+        .network
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| "Routing graphs are not loaded yet".to_string())?;
+    for point in [from, to] {
+        if !network.bounds.contains(point.lon, point.lat) {
+            return Err(format!(
+                "No routing graph covers {}, {}",
+                point.lon, point.lat
+            ));
+        }
+    }
+
+    let mut route = find_route(&network, [from.lon, from.lat], [to.lon, to.lat], profile)
+        .ok_or_else(|| format!("No {:?} route found between the points", profile))?;
+    route.instructions = build_instructions(&route, language);
+
+    Ok(route)
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::routing_graph::RoadGraph;
+use crate::map::map_locality_cache::LocalityCache;
+
+pub struct RoutingState {
+    /// Loaded road graphs keyed by locality id.
+    pub graphs: LocalityCache<RoadGraph>,
+
+    /// All the locality graphs stitched together, rebuilt after every sync.
+    pub network: RwLock<Option<Arc<RoadGraph>>>,
+}
+
+impl RoutingState {
+    pub fn new() -> Self {
+        Self {
+            graphs: LocalityCache::new(),
+            network: RwLock::new(None),
+        }
+    }
+}
+
+impl Default for RoutingState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
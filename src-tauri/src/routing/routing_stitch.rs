@@ -0,0 +1,182 @@
+//! Stitching of the per-locality road graphs into a single network.
+//!
+//! A road leaving an archive ends in a dead end at the archive's edge; the
+//! neighbouring archive has a node at the same position, since both were cut
+//! from the same tile grid. Dead ends are merged with the nearest node of
+//! each neighbouring graph when they are close enough.
+
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+
+use super::routing_graph::{RoadEdge, RoadGraph};
+use crate::map::map_query::haversine_distance_m;
+
+/// Nodes of neighbouring graphs closer than this are the same place.
+const STITCH_TOLERANCE_M: f64 = 2.0;
+
+/// Identifier of the network built from several localities.
+pub const NETWORK_ID: &str = "network";
+
+/// Merges `graphs` into one graph. `neighbours` lists the pairs of graphs,
+/// by index, whose areas touch or overlap.
+pub fn stitch_graphs(graphs: &[&RoadGraph], neighbours: &[(usize, usize)]) -> RoadGraph {
+    let offsets: Vec<usize> = graphs
+        .iter()
+        .scan(0, |offset, graph| {
+            let start = *offset;
+            *offset += graph.nodes.len();
+            Some(start)
+        })
+        .collect();
+    let node_count = graphs.iter().map(|g| g.nodes.len()).sum();
+    let mut merged = UnionFind::new(node_count);
+
+    for &(a, b) in neighbours {
+        for (from, to) in [(a, b), (b, a)] {
+            let targets: RTree<GeomWithData<[f64; 2], usize>> = RTree::bulk_load(
+                graphs[to]
+                    .nodes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, point)| GeomWithData::new(*point, i))
+                    .collect(),
+            );
+
+            let dead_ends = (0..graphs[from].nodes.len())
+                .filter(|&node| graphs[from].adjacency[node].len() == 1);
+            for node in dead_ends {
+                let [lon, lat] = graphs[from].nodes[node];
+                let Some(nearest) = targets.nearest_neighbor(&[lon, lat]) else {
+                    continue;
+                };
+                let [target_lon, target_lat] = *nearest.geom();
+                if haversine_distance_m(lon, lat, target_lon, target_lat) <= STITCH_TOLERANCE_M {
+                    merged.union(offsets[from] + node, offsets[to] + nearest.data);
+                }
+            }
+        }
+    }
+
+    let mut ids: Vec<Option<u32>> = vec![None; node_count];
+    let mut nodes = Vec::new();
+    let mut node_id = |global: usize, point: [f64; 2]| {
+        let root = merged.find(global);
+        *ids[root].get_or_insert_with(|| {
+            nodes.push(point);
+            (nodes.len() - 1) as u32
+        })
+    };
+
+    let mut edges = Vec::new();
+    for (graph, offset) in graphs.iter().zip(&offsets) {
+        for edge in &graph.edges {
+            let from = node_id(offset + edge.from as usize, graph.nodes[edge.from as usize]);
+            let to = node_id(offset + edge.to as usize, graph.nodes[edge.to as usize]);
+            edges.push(RoadEdge {
+                from,
+                to,
+                ..edge.clone()
+            });
+        }
+    }
+
+    RoadGraph::new(NETWORK_ID, 0, None, nodes, edges)
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::map_query::tile_to_lonlat;
+    use crate::routing::routing_astar::find_route;
+    use crate::routing::routing_graph::tests::roads_tile;
+    use crate::routing::routing_graph::GraphBuilder;
+    use crate::routing::routing_types::RoutingProfile;
+
+    /// A street running east across tiles 14/0/0 (first locality) and
+    /// 14/1/0 (second locality).
+    fn locality_graphs() -> (RoadGraph, RoadGraph) {
+        let mut west = GraphBuilder::new(14);
+        west.add_tile(
+            &roads_tile(&[(
+                vec![[1000, 2048], [4160, 2048]],
+                "residential",
+                "Main",
+                false,
+            )]),
+            (14, 0, 0),
+        );
+        let mut east = GraphBuilder::new(14);
+        east.add_tile(
+            &roads_tile(&[(
+                vec![[-64, 2048], [3000, 2048]],
+                "residential",
+                "Main",
+                false,
+            )]),
+            (14, 1, 0),
+        );
+        (west.finish("west", 0, None), east.finish("east", 0, None))
+    }
+
+    fn lonlat(tx: f64, x: f64) -> [f64; 2] {
+        tile_to_lonlat([tx + x / 4096.0, 0.5], 14)
+    }
+
+    #[test]
+    fn stitched_graphs_route_across_localities() {
+        let (west, east) = locality_graphs();
+        let from = lonlat(0.0, 2000.0);
+        let to = lonlat(1.0, 2000.0);
+
+        let network = stitch_graphs(&[&west, &east], &[(0, 1)]);
+        // The dead ends at the shared tile edge became a single node.
+        assert_eq!(network.nodes.len(), west.nodes.len() + east.nodes.len() - 1);
+
+        let route = find_route(&network, from, to, RoutingProfile::Car).unwrap();
+        let expected = haversine_distance_m(from[0], from[1], to[0], to[1]);
+        assert!((route.distance_m - expected).abs() < 0.1);
+        assert_eq!(route.segments.len(), 2);
+    }
+
+    #[test]
+    fn graphs_that_are_not_neighbours_stay_apart() {
+        let (west, east) = locality_graphs();
+        let network = stitch_graphs(&[&west, &east], &[]);
+
+        assert_eq!(network.nodes.len(), west.nodes.len() + east.nodes.len());
+        assert!(find_route(
+            &network,
+            lonlat(0.0, 2000.0),
+            lonlat(1.0, 2000.0),
+            RoutingProfile::Car
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoutingProfile {
+    Car,
+    Bike,
+    Foot,
+}
+
+impl RoutingProfile {
+    /// Travel speed on a road class, or `None` when the class can't be used.
+    pub fn speed_kmh(self, class: RoadClass) -> Option<f64> {
+        use RoadClass::*;
+
+        match self {
+            RoutingProfile::Car => match class {
+                Motorway => Some(110.0),
+                Trunk => Some(90.0),
+                Primary => Some(70.0),
+                Secondary => Some(60.0),
+                Tertiary => Some(50.0),
+                Minor => Some(35.0),
+                Service => Some(20.0),
+                Track => Some(15.0),
+                Path | Footway | Cycleway | Steps | Pedestrian => None,
+            },
+            RoutingProfile::Bike => match class {
+                Motorway | Trunk | Steps => None,
+                Cycleway => Some(20.0),
+                Primary | Secondary | Tertiary | Minor => Some(18.0),
+                Service => Some(16.0),
+                Path => Some(14.0),
+                Track => Some(12.0),
+                Footway | Pedestrian => Some(6.0),
+            },
+            RoutingProfile::Foot => match class {
+                Motorway | Trunk => None,
+                Steps => Some(3.0),
+                _ => Some(5.0),
+            },
+        }
+    }
+
+    /// Fastest speed of the profile, used by the A* heuristic.
+    pub fn max_speed_kmh(self) -> f64 {
+        match self {
+            RoutingProfile::Car => 110.0,
+            RoutingProfile::Bike => 20.0,
+            RoutingProfile::Foot => 5.0,
+        }
+    }
+
+    pub fn respects_oneway(self) -> bool {
+        self != RoutingProfile::Foot
+    }
+}
+
+/// Road hierarchy shared by the Protomaps (`kind_detail`) and OpenMapTiles
+/// (`class`/`subclass`) schemas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoadClass {
+    Motorway,
+    Trunk,
+    Primary,
+    Secondary,
+    Tertiary,
+    /// Residential, unclassified and living streets.
+    Minor,
+    Service,
+    Track,
+    Path,
+    Footway,
+    Cycleway,
+    Steps,
+    Pedestrian,
+}
+
+impl RoadClass {
+    /// Maps an OSM `highway` value (or a schema's equivalent) to a class;
+    /// `_link` roads share the class of the road they connect to.
+    pub fn from_highway(highway: &str) -> Option<Self> {
+        let class = match highway.trim_end_matches("_link") {
+            "motorway" => RoadClass::Motorway,
+            "trunk" => RoadClass::Trunk,
+            "primary" => RoadClass::Primary,
+            "secondary" => RoadClass::Secondary,
+            "tertiary" => RoadClass::Tertiary,
+            "minor" | "residential" | "unclassified" | "living_street" | "road" => RoadClass::Minor,
+            "service" => RoadClass::Service,
+            "track" => RoadClass::Track,
+            "path" | "bridleway" => RoadClass::Path,
+            "footway" | "sidewalk" | "crossing" | "corridor" => RoadClass::Footway,
+            "cycleway" => RoadClass::Cycleway,
+            "steps" => RoadClass::Steps,
+            "pedestrian" => RoadClass::Pedestrian,
+            _ => return None,
+        };
+        Some(class)
+    }
+
+    /// Fallback for the coarse Protomaps `kind` when no detail is available.
+    pub fn from_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "highway" => Some(RoadClass::Motorway),
+            "major_road" => Some(RoadClass::Primary),
+            "medium_road" => Some(RoadClass::Tertiary),
+            "minor_road" => Some(RoadClass::Minor),
+            "path" => Some(RoadClass::Path),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Route {
+    pub profile: RoutingProfile,
+    pub distance_m: f64,
+    pub duration_s: f64,
+    /// `[lon, lat]` positions from the start to the destination.
+    pub coordinates: Vec<[f64; 2]>,
+    /// The roads travelled, in order.
+    pub segments: Vec<RouteSegment>,
+    pub instructions: Vec<Instruction>,
+}
+
+/// Part of a route along a single graph edge.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteSegment {
+    pub name: Option<String>,
+    pub road_ref: Option<String>,
+    pub class: RoadClass,
+    pub roundabout: bool,
+    pub distance_m: f64,
+    pub duration_s: f64,
+    /// Range of `Route::coordinates` covered by the segment, inclusive.
+    pub start_index: usize,
+    pub end_index: usize,
+    /// Whether other roads meet the route where the segment ends.
+    pub ends_at_junction: bool,
+}
+
+/// Language of the instruction texts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Fr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ManeuverType {
+    Depart,
+    Continue,
+    SlightLeft,
+    Left,
+    SharpLeft,
+    SlightRight,
+    Right,
+    SharpRight,
+    UTurn,
+    Roundabout,
+    Arrive,
+}
+
+/// A maneuver and the stretch of road travelled after it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Instruction {
+    pub maneuver: ManeuverType,
+    /// Localized text, e.g. `Turn left onto Rideau Street`.
+    pub text: String,
+    pub road_name: Option<String>,
+    /// Distance to the next maneuver.
+    pub distance_m: f64,
+    /// Localized `distance_m`, e.g. `300 m` or `1.2 km`.
+    pub distance_text: String,
+    pub duration_s: f64,
+    /// Bearings of the route just before and after the maneuver, in degrees
+    /// clockwise from north.
+    pub bearing_before: f64,
+    pub bearing_after: f64,
+    /// Exit taken when `maneuver` is `roundabout`, counting from 1.
+    pub roundabout_exit: Option<u32>,
+    /// Index in `Route::coordinates` where the maneuver happens.
+    pub coordinate_index: usize,
+    pub location: [f64; 2],
+}
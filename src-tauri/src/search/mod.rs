@@ -0,0 +1,18 @@
+//! Offline place search over the downloaded localities
+//!
+//! This module provides:
+//! - Per-locality full-text indexes built from the `places`, `pois` and `roads`
+//!   label features at each archive's highest zoom
+//! - Persistence of the indexes and their token tables, rebuilt only when an
+//!   archive changes
+//! - Prefix and fuzzy matching over the token tables, ranked by distance and
+//!   place importance
+
+pub mod search_cmd;
+mod search_index;
+mod search_service;
+mod search_state;
+pub mod search_types;
+
+pub use search_service::{listen_for_locality_changes, spawn_index_sync};
+pub use search_state::SearchState;
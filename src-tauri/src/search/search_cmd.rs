@@ -0,0 +1,16 @@
+use tauri::State;
+
+use super::search_service;
+use super::search_state::SearchState;
+use super::search_types::PlaceResult;
+use crate::map::map_types::LonLat;
+
+#[tauri::command]
+pub async fn search_places(
+    query: String,
+    near: Option<LonLat>,
+    limit: Option<usize>,
+    state: State<'_, SearchState>,
+) -> Result<Vec<PlaceResult>, String> {
+    search_service::search_places(&query, near, limit, &state).await
+}
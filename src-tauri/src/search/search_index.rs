@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use super::search_types::{PlaceKind, SearchEntry};
+use crate::map::map_mvt::{decode_geometry, GeomType, Layer, Tile, TilePoint, Value};
+use crate::map::map_query::{lonlat_to_tile, tile_to_lonlat};
+
+/// Bumped whenever the extraction or the on-disk format changes, so stale
+/// indexes are rebuilt.
+pub const INDEX_VERSION: u32 = 2;
+
+/// Road segments sharing a name are kept once per tile at this zoom, so a
+/// long street yields a few entries rather than one per tile.
+const ROAD_DEDUP_ZOOM: u8 = 12;
+
+/// Features without an id sharing a name are kept once per tile at this zoom.
+const POINT_DEDUP_ZOOM: u8 = 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalityIndex {
+    pub version: u32,
+    pub locality_id: String,
+    pub file_size: u64,
+    pub modified_at: Option<u64>,
+    pub entries: Vec<SearchEntry>,
+    /// Distinct normalized name tokens, sorted so a prefix is a range.
+    tokens: Vec<String>,
+    /// Positions in `entries` of the names containing each token.
+    postings: Vec<Vec<u32>>,
+}
+
+impl LocalityIndex {
+    pub fn new(
+        locality_id: &str,
+        file_size: u64,
+        modified_at: Option<u64>,
+        entries: Vec<SearchEntry>,
+    ) -> Self {
+        let mut table: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            for token in tokenize(&entry.name) {
+                let posting = table.entry(token).or_default();
+                if posting.last() != Some(&(i as u32)) {
+                    posting.push(i as u32);
+                }
+            }
+        }
+        let (tokens, postings) = table.into_iter().unzip();
+
+        Self {
+            version: INDEX_VERSION,
+            locality_id: locality_id.to_string(),
+            file_size,
+            modified_at,
+            entries,
+            tokens,
+            postings,
+        }
+    }
+
+    /// Whether the index was built from the archive as it is now on disk.
+    pub fn is_current(&self, file_size: u64, modified_at: Option<u64>) -> bool {
+        self.version == INDEX_VERSION
+            && self.file_size == file_size
+            && self.modified_at == modified_at
+    }
+
+    /// Entries matching every query token, with their text match score.
+    pub fn search<'a>(
+        &'a self,
+        query: &'a [String],
+    ) -> impl Iterator<Item = (f64, &'a SearchEntry)> + 'a {
+        self.candidates(query).into_iter().filter_map(move |i| {
+            let entry = &self.entries[i as usize];
+            Some((match_score(query, &tokenize(&entry.name))?, entry))
+        })
+    }
+
+    /// Positions of the entries with a token matching each query token.
+    fn candidates(&self, query: &[String]) -> Vec<u32> {
+        let mut candidates: Option<Vec<u32>> = None;
+
+        for (i, query_token) in query.iter().enumerate() {
+            let is_last = i + 1 == query.len();
+            let mut matches: Vec<u32> = self
+                .matching_tokens(query_token, is_last)
+                .flat_map(|t| self.postings[t].iter().copied())
+                .collect();
+            matches.sort_unstable();
+            matches.dedup();
+
+            let matches = match candidates {
+                Some(previous) => previous
+                    .into_iter()
+                    .filter(|i| matches.binary_search(i).is_ok())
+                    .collect(),
+                None => matches,
+            };
+            if matches.is_empty() {
+                return matches;
+            }
+            candidates = Some(matches);
+        }
+
+        candidates.unwrap_or_default()
+    }
+
+    /// Tokens matching `query` exactly, as a prefix or with a few typos.
+    /// Typos are only looked for among tokens sharing the first letter.
+    fn matching_tokens<'a>(
+        &'a self,
+        query: &'a str,
+        is_last: bool,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let first_letter = query.chars().next().map_or(0, char::len_utf8);
+        self.prefix_range(&query[..first_letter])
+            .filter(move |&t| token_score(query, &self.tokens[t], is_last).is_some())
+    }
+
+    fn prefix_range(&self, prefix: &str) -> Range<usize> {
+        let start = self.tokens.partition_point(|t| t.as_str() < prefix);
+        let len = self.tokens[start..].partition_point(|t| t.starts_with(prefix));
+        start..start + len
+    }
+}
+
+/// Lowercases, strips diacritics and turns punctuation into spaces.
+pub fn normalize(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect()
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    normalize(text)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Scores how well `name` matches `query`, or `None` when a query token has
+/// no match. Every token may match exactly or with a few typos; the last one
+/// may also be a prefix, as it is usually still being typed.
+pub fn match_score(query: &[String], name: &[String]) -> Option<f64> {
+    if query.is_empty() || name.is_empty() {
+        return None;
+    }
+
+    let mut total = 0.0;
+    for (i, query_token) in query.iter().enumerate() {
+        let is_last = i + 1 == query.len();
+        total += name
+            .iter()
+            .filter_map(|name_token| token_score(query_token, name_token, is_last))
+            .fold(None, |best: Option<f64>, score| {
+                Some(best.map_or(score, |b| b.max(score)))
+            })?;
+    }
+
+    let mut score = total / query.len() as f64;
+    if query == name {
+        score += 0.5;
+    }
+    // Favour names without many extra words.
+    score *= 0.8 + 0.2 * (query.len() as f64 / name.len().max(query.len()) as f64);
+
+    Some(score)
+}
+
+fn token_score(query: &str, name: &str, is_last: bool) -> Option<f64> {
+    if query == name {
+        return Some(1.0);
+    }
+    if is_last && name.starts_with(query) {
+        return Some(0.8);
+    }
+
+    let query_len = query.chars().count();
+    let allowed = match query_len {
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+    let distance = if is_last {
+        // A word still being typed is compared against name prefixes of similar length.
+        (query_len - allowed..=query_len + allowed)
+            .map(|len| levenshtein(query, &name.chars().take(len).collect::<String>()))
+            .min()?
+    } else {
+        levenshtein(query, name)
+    };
+
+    (distance <= allowed).then_some(0.6 - 0.1 * distance as f64)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum DedupKey {
+    Id(PlaceKind, u64),
+    Cell(PlaceKind, String, u8, u32, u32),
+}
+
+/// Collects searchable label features from the tiles of one archive.
+#[derive(Default)]
+pub struct EntryCollector {
+    entries: Vec<SearchEntry>,
+    seen: HashSet<DedupKey>,
+}
+
+impl EntryCollector {
+    pub fn add_tile(&mut self, tile: &Tile, (z, x, y): (u8, u32, u32)) {
+        for layer in &tile.layers {
+            let Some(kind) = PlaceKind::from_layer(&layer.name) else {
+                continue;
+            };
+            let extent = layer.extent() as i32;
+
+            for feature in &layer.features {
+                let properties = Properties {
+                    layer,
+                    tags: &feature.tags,
+                };
+                let Some(name) = properties.string("name").filter(|n| !n.trim().is_empty()) else {
+                    continue;
+                };
+
+                let parts = decode_geometry(&feature.geometry);
+                let Some([px, py]) = label_point(feature.r#type(), &parts) else {
+                    continue;
+                };
+                // Buffered features are picked up by the tile they belong to.
+                if !(0..extent).contains(&px) || !(0..extent).contains(&py) {
+                    continue;
+                }
+
+                let [lon, lat] = tile_to_lonlat(
+                    [
+                        f64::from(x) + f64::from(px) / f64::from(extent),
+                        f64::from(y) + f64::from(py) / f64::from(extent),
+                    ],
+                    z,
+                );
+
+                let key = match (kind, feature.id) {
+                    (PlaceKind::Place | PlaceKind::Poi, Some(id)) => DedupKey::Id(kind, id),
+                    _ => {
+                        let zoom = if kind == PlaceKind::Road {
+                            ROAD_DEDUP_ZOOM
+                        } else {
+                            POINT_DEDUP_ZOOM
+                        };
+                        let [cx, cy] = lonlat_to_tile(lon, lat, zoom);
+                        DedupKey::Cell(kind, normalize(&name), zoom, cx as u32, cy as u32)
+                    }
+                };
+                if !self.seen.insert(key) {
+                    continue;
+                }
+
+                self.entries.push(SearchEntry {
+                    name,
+                    kind,
+                    class: properties.string("kind"),
+                    lon,
+                    lat,
+                    importance: importance(kind, &properties),
+                });
+            }
+        }
+    }
+
+    pub fn finish(self) -> Vec<SearchEntry> {
+        self.entries
+    }
+}
+
+/// Point used to place a feature: the point itself, the middle vertex of a
+/// line, or the center of a polygon's outer ring bounding box.
+fn label_point(geom_type: GeomType, parts: &[Vec<TilePoint>]) -> Option<TilePoint> {
+    let first = parts.first()?;
+    match geom_type {
+        GeomType::Point => first.first().copied(),
+        GeomType::Linestring => first.get(first.len() / 2).copied(),
+        GeomType::Polygon => {
+            let min_x = first.iter().map(|p| p[0]).min()?;
+            let max_x = first.iter().map(|p| p[0]).max()?;
+            let min_y = first.iter().map(|p| p[1]).min()?;
+            let max_y = first.iter().map(|p| p[1]).max()?;
+            Some([min_x + (max_x - min_x) / 2, min_y + (max_y - min_y) / 2])
+        }
+        GeomType::Unknown => None,
+    }
+}
+
+/// Features shown from lower zooms and more populated places rank higher.
+fn importance(kind: PlaceKind, properties: &Properties) -> f64 {
+    let base = match kind {
+        PlaceKind::Place => 0.5,
+        PlaceKind::Poi => 0.2,
+        PlaceKind::Road => 0.1,
+    };
+    let min_zoom = properties
+        .number("min_zoom")
+        .map_or(0.0, |z| (1.0 - z / 16.0).clamp(0.0, 1.0));
+    let population = properties
+        .number("population_rank")
+        .map_or(0.0, |rank| (rank / 15.0).clamp(0.0, 1.0));
+
+    (base + 0.5 * min_zoom.max(population)).min(1.0)
+}
+
+struct Properties<'a> {
+    layer: &'a Layer,
+    tags: &'a [u32],
+}
+
+impl Properties<'_> {
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.tags.chunks_exact(2).find_map(|pair| {
+            (self.layer.keys.get(pair[0] as usize)? == key)
+                .then(|| self.layer.values.get(pair[1] as usize))
+                .flatten()
+        })
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        self.get(key)?.string_value.clone()
+    }
+
+    fn number(&self, key: &str) -> Option<f64> {
+        let value = self.get(key)?;
+        value
+            .double_value
+            .or(value.float_value.map(f64::from))
+            .or(value.int_value.or(value.sint_value).map(|v| v as f64))
+            .or(value.uint_value.map(|v| v as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::map_mvt::{encode_geometry, Feature};
+
+    fn tokens(text: &str) -> Vec<String> {
+        tokenize(text)
+    }
+
+    #[test]
+    fn normalize_strips_diacritics_and_punctuation() {
+        assert_eq!(
+            tokenize("Rue Sainte-Catherine"),
+            vec!["rue", "sainte", "catherine"]
+        );
+        assert_eq!(normalize("Gatineau–Montréal"), "gatineau montreal");
+    }
+
+    #[test]
+    fn match_score_handles_prefix_and_typos() {
+        let name = tokens("Parliament Hill");
+
+        assert!(match_score(&tokens("parliament hill"), &name).is_some());
+        assert!(match_score(&tokens("parl"), &name).is_some());
+        assert!(match_score(&tokens("parliment"), &name).is_some());
+        assert!(match_score(&tokens("hill parlia"), &name).is_some());
+        assert!(match_score(&tokens("museum"), &name).is_none());
+        assert!(match_score(&tokens("pa hill"), &name).is_none());
+
+        let exact = match_score(&tokens("parliament hill"), &name).unwrap();
+        let prefix = match_score(&tokens("parliament hi"), &name).unwrap();
+        let typo = match_score(&tokens("parliment hill"), &name).unwrap();
+        assert!(exact > prefix && prefix > typo);
+    }
+
+    #[test]
+    fn index_finds_candidates_through_token_table() {
+        let entry = |name: &str| SearchEntry {
+            name: name.to_string(),
+            kind: PlaceKind::Poi,
+            class: None,
+            lon: -75.7,
+            lat: 45.4,
+            importance: 0.5,
+        };
+        let index = LocalityIndex::new(
+            "ottawa",
+            0,
+            None,
+            vec![
+                entry("Parliament Hill"),
+                entry("Hill Park"),
+                entry("Parc Jacques-Cartier"),
+            ],
+        );
+        let found = |query: &str| {
+            let mut names: Vec<_> = index
+                .search(&tokens(query))
+                .map(|(_, e)| e.name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(index.prefix_range("par"), 3..6);
+        assert_eq!(found("hill"), vec!["Hill Park", "Parliament Hill"]);
+        assert_eq!(found("jacq"), vec!["Parc Jacques-Cartier"]);
+        assert_eq!(found("parliment hi"), vec!["Parliament Hill"]);
+        assert_eq!(found("park hill"), vec!["Hill Park"]);
+        assert!(found("museum").is_empty());
+    }
+
+    #[test]
+    fn entry_collector_extracts_and_deduplicates_labels() {
+        let string = |v: &str| Value {
+            string_value: Some(v.to_string()),
+            ..Default::default()
+        };
+        let point = |id, x, y, tags: Vec<u32>| Feature {
+            id,
+            tags,
+            r#type: Some(GeomType::Point as i32),
+            geometry: encode_geometry(GeomType::Point, &[vec![[x, y]]]),
+        };
+        let tile = Tile {
+            layers: vec![
+                Layer {
+                    version: 2,
+                    name: "pois".to_string(),
+                    features: vec![
+                        point(Some(7), 100, 100, vec![0, 0, 1, 1]),
+                        point(Some(7), 100, 100, vec![0, 0, 1, 1]),
+                        point(Some(8), -20, 100, vec![0, 2]),
+                        point(Some(9), 200, 200, vec![]),
+                    ],
+                    keys: vec!["name".to_string(), "kind".to_string()],
+                    values: vec![string("Café Nord"), string("cafe"), string("Outside")],
+                    extent: Some(4096),
+                },
+                Layer {
+                    version: 2,
+                    name: "water".to_string(),
+                    features: vec![point(Some(1), 10, 10, vec![0, 0])],
+                    keys: vec!["name".to_string()],
+                    values: vec![string("River")],
+                    extent: Some(4096),
+                },
+            ],
+        };
+
+        let mut collector = EntryCollector::default();
+        collector.add_tile(&tile, (14, 4700, 5800));
+        let entries = collector.finish();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "Café Nord");
+        assert_eq!(entries[0].kind, PlaceKind::Poi);
+        assert_eq!(entries[0].class.as_deref(), Some("cafe"));
+    }
+}
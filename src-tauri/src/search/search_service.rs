@@ -0,0 +1,185 @@
+use std::future::Future;
+use tauri::Manager;
+
+use super::search_index::{normalize, tokenize, EntryCollector, LocalityIndex};
+use super::search_state::SearchState;
+use super::search_types::PlaceResult;
+use crate::map::map_locality_cache::{self, visit_locality_tiles, LocalityArtifact, LocalityCache};
+use crate::map::map_query::haversine_distance_m;
+use crate::map::map_types::{LocalityMetadata, LonLat};
+use crate::map::MapState;
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+const MAX_SEARCH_LIMIT: usize = 50;
+
+/// Distance from the `near` point at which a result's score is halved.
+const PROXIMITY_HALF_SCORE_KM: f64 = 25.0;
+
+/// Results with the same name and kind closer than this are the same place
+/// seen through overlapping localities.
+const DUPLICATE_DISTANCE_M: f64 = 200.0;
+
+pub fn spawn_index_sync(app: &tauri::AppHandle) {
+    map_locality_cache::spawn_sync::<LocalityIndex>(app);
+}
+
+pub fn listen_for_locality_changes(app: &tauri::AppHandle) {
+    map_locality_cache::listen_for_locality_changes::<LocalityIndex>(app);
+}
+
+impl LocalityArtifact for LocalityIndex {
+    const DIR_NAME: &'static str = "search";
+    const DESCRIPTION: &'static str = "search index";
+
+    fn cache(app: &tauri::AppHandle) -> &LocalityCache<Self> {
+        &app.state::<SearchState>().inner().indexes
+    }
+
+    fn is_current(&self, file_size: u64, modified_at: Option<u64>) -> bool {
+        self.is_current(file_size, modified_at)
+    }
+
+    fn build(
+        locality: &LocalityMetadata,
+        map_state: &tauri::State<'_, MapState>,
+    ) -> impl Future<Output = Result<Self, String>> + Send {
+        build_index(locality, map_state)
+    }
+}
+
+async fn build_index(
+    locality: &LocalityMetadata,
+    map_state: &tauri::State<'_, MapState>,
+) -> Result<LocalityIndex, String> {
+    let mut collector = EntryCollector::default();
+    visit_locality_tiles(&locality.id, locality.max_zoom, map_state, |tile, coord| {
+        collector.add_tile(tile, coord)
+    })
+    .await?;
+
+    Ok(LocalityIndex::new(
+        &locality.id,
+        locality.file_size,
+        locality.modified_at,
+        collector.finish(),
+    ))
+}
+
+pub async fn search_places(
+    query: &str,
+    near: Option<LonLat>,
+    limit: Option<usize>,
+    state: &tauri::State<'_, SearchState>,
+) -> Result<Vec<PlaceResult>, String> {
+    let tokens = tokenize(query);
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let indexes = state.indexes.read().await;
+    let candidates = indexes
+        .values()
+        .flat_map(|index| {
+            index.search(&tokens).map(|(text_score, entry)| {
+                let distance_m =
+                    near.map(|p| haversine_distance_m(p.lon, p.lat, entry.lon, entry.lat));
+                PlaceResult {
+                    name: entry.name.clone(),
+                    kind: entry.kind,
+                    class: entry.class.clone(),
+                    lon: entry.lon,
+                    lat: entry.lat,
+                    locality_id: index.locality_id.clone(),
+                    distance_m,
+                    score: rank(text_score, entry.importance, distance_m),
+                }
+            })
+        })
+        .collect();
+
+    Ok(rank_results(candidates, limit))
+}
+
+fn rank(text_score: f64, importance: f64, distance_m: Option<f64>) -> f64 {
+    let proximity = distance_m.map_or(1.0, |d| 1.0 / (1.0 + d / 1000.0 / PROXIMITY_HALF_SCORE_KM));
+    text_score * (0.6 + 0.4 * importance) * proximity
+}
+
+/// Sorts by descending score, drops duplicates from overlapping localities
+/// and keeps the best `limit` results.
+fn rank_results(mut candidates: Vec<PlaceResult>, limit: usize) -> Vec<PlaceResult> {
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut results: Vec<PlaceResult> = Vec::new();
+    for candidate in candidates {
+        let is_duplicate = results.iter().any(|r| {
+            r.kind == candidate.kind
+                && normalize(&r.name) == normalize(&candidate.name)
+                && haversine_distance_m(r.lon, r.lat, candidate.lon, candidate.lat)
+                    < DUPLICATE_DISTANCE_M
+        });
+        if !is_duplicate {
+            results.push(candidate);
+            if results.len() == limit {
+                break;
+            }
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::search_types::PlaceKind;
+
+    fn result(name: &str, locality_id: &str, lon: f64, score: f64) -> PlaceResult {
+        PlaceResult {
+            name: name.to_string(),
+            kind: PlaceKind::Poi,
+            class: None,
+            lon,
+            lat: 45.42,
+            locality_id: locality_id.to_string(),
+            distance_m: None,
+            score,
+        }
+    }
+
+    #[test]
+    fn rank_prefers_closer_and_more_important_places() {
+        assert!(rank(1.0, 0.5, Some(1_000.0)) > rank(1.0, 0.5, Some(50_000.0)));
+        assert!(rank(1.0, 0.9, None) > rank(1.0, 0.1, None));
+    }
+
+    #[test]
+    fn rank_results_deduplicates_overlapping_localities() {
+        let results = rank_results(
+            vec![
+                result("Rideau Centre", "ontario", -75.6920, 0.7),
+                result("Rideau Centre", "ottawa", -75.6921, 0.9),
+                result("Rideau Hall", "ottawa", -75.7046, 0.8),
+                result("Rideau Centre", "ottawa", -75.5, 0.1),
+            ],
+            10,
+        );
+
+        let summary: Vec<_> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.locality_id.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Rideau Centre", "ottawa"),
+                ("Rideau Hall", "ottawa"),
+                ("Rideau Centre", "ottawa"),
+            ]
+        );
+        assert_eq!(rank_results(results, 1).len(), 1);
+    }
+}
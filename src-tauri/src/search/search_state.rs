@@ -0,0 +1,21 @@
+use super::search_index::LocalityIndex;
+use crate::map::map_locality_cache::LocalityCache;
+
+pub struct SearchState {
+    /// Loaded indexes keyed by locality id.
+    pub indexes: LocalityCache<LocalityIndex>,
+}
+
+impl SearchState {
+    pub fn new() -> Self {
+        Self {
+            indexes: LocalityCache::new(),
+        }
+    }
+}
+
+impl Default for SearchState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// Vector tile layer a searchable feature comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceKind {
+    Place,
+    Poi,
+    Road,
+}
+
+impl PlaceKind {
+    pub fn from_layer(layer: &str) -> Option<Self> {
+        match layer {
+            "places" => Some(PlaceKind::Place),
+            "pois" => Some(PlaceKind::Poi),
+            "roads" => Some(PlaceKind::Road),
+            _ => None,
+        }
+    }
+}
+
+/// A labelled feature stored in a locality's search index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchEntry {
+    pub name: String,
+    pub kind: PlaceKind,
+    /// The feature's `kind` property, e.g. `locality`, `cafe` or `major_road`.
+    pub class: Option<String>,
+    pub lon: f64,
+    pub lat: f64,
+    /// Between 0 and 1, higher for cities and major roads than for minor POIs.
+    pub importance: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceResult {
+    pub name: String,
+    pub kind: PlaceKind,
+    pub class: Option<String>,
+    pub lon: f64,
+    pub lat: f64,
+    pub locality_id: String,
+    /// Distance from the `near` point, in meters, when one was given.
+    pub distance_m: Option<f64>,
+    pub score: f64,
+}
@@ -0,0 +1,17 @@
+//! User-editable settings, persisted in the app config directory
+//!
+//! This module provides:
+//! - Typed settings for the Storage node, downloads, the storage quota and
+//!   the map caches, with defaults and validation
+//! - A versioned settings file, migrated when an older version is loaded
+//! - The `get_settings` and `update_settings` commands, applying changes to
+//!   the running app where possible
+
+pub mod settings_cmd;
+mod settings_migrate;
+mod settings_service;
+mod settings_state;
+pub mod settings_types;
+
+pub use settings_service::{change_settings, load_settings, settings_path};
+pub use settings_state::SettingsState;
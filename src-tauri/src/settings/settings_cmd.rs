@@ -0,0 +1,24 @@
+use tauri::State;
+
+use super::settings_service::replace_settings;
+use super::settings_state::SettingsState;
+use super::settings_types::{Settings, SettingsUpdate};
+
+#[tauri::command]
+pub async fn get_settings(state: State<'_, SettingsState>) -> Result<Settings, String> {
+    Ok(state.settings.read().await.clone())
+}
+
+/// Validates and saves `settings`, then applies them to the running app.
+#[tauri::command]
+pub async fn update_settings(
+    app: tauri::AppHandle,
+    settings: Settings,
+) -> Result<SettingsUpdate, String> {
+    let restart_required = replace_settings(&app, &settings).await?;
+
+    Ok(SettingsUpdate {
+        settings,
+        restart_required,
+    })
+}
@@ -0,0 +1,68 @@
+//! Upgrades of settings files written by older versions.
+//!
+//! Each step turns a document of one version into the next; loading a file
+//! runs the steps from its version up to `SETTINGS_VERSION`.
+
+use serde_json::{Map, Value};
+
+use super::settings_types::SETTINGS_VERSION;
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[from_unversioned];
+
+/// Upgrades `document` to `SETTINGS_VERSION`. Returns whether it changed.
+pub fn migrate(document: &mut Value) -> Result<bool, String> {
+    let object = document
+        .as_object_mut()
+        .ok_or_else(|| "Settings must be a JSON object".to_string())?;
+
+    let version = match object.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid settings version {}", version))?,
+    };
+    if version > SETTINGS_VERSION {
+        return Err(format!(
+            "Settings version {} is newer than this app supports ({})",
+            version, SETTINGS_VERSION
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(object);
+    }
+    object.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    Ok(version != SETTINGS_VERSION)
+}
+
+/// Files written by hand before settings were versioned share the version 1
+/// layout; they only lack the version.
+fn from_unversioned(_settings: &mut Map<String, Value>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_unversioned_settings() {
+        let mut document = json!({ "map": { "maxCachedReaders": 4 } });
+
+        assert_eq!(migrate(&mut document), Ok(true));
+        assert_eq!(document["version"], SETTINGS_VERSION);
+        assert_eq!(document["map"]["maxCachedReaders"], 4);
+
+        assert_eq!(migrate(&mut document), Ok(false));
+    }
+
+    #[test]
+    fn rejects_newer_and_malformed_settings() {
+        assert!(migrate(&mut json!({ "version": SETTINGS_VERSION + 1 })).is_err());
+        assert!(migrate(&mut json!({ "version": "1" })).is_err());
+        assert!(migrate(&mut json!([])).is_err());
+    }
+}
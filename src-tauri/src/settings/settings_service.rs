@@ -0,0 +1,183 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use super::settings_migrate::migrate;
+use super::settings_state::SettingsState;
+use super::settings_types::Settings;
+use crate::map::{map_service, MapState};
+use crate::storage::storage_config::create_storage_config;
+use crate::storage::storage_service::{get_catalog_dir, load_catalog};
+use crate::storage::StorageState;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+pub fn settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .map_err(|e| format!("Failed to get app config directory: {}", e))
+}
+
+/// Loads the settings saved at `path`, or the defaults when there are none.
+/// A file written by an older version is migrated and saved back.
+pub fn load_settings(path: &Path) -> Result<Settings, String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(e) => return Err(format!("Failed to read '{}': {}", path.display(), e)),
+    };
+
+    let mut document: Value = serde_json::from_slice(&bytes)
+        .map_err(|e| format!("Invalid settings file '{}': {}", path.display(), e))?;
+    let migrated = migrate(&mut document)?;
+    let settings: Settings = serde_json::from_value(document)
+        .map_err(|e| format!("Invalid settings file '{}': {}", path.display(), e))?;
+    settings.validate()?;
+
+    if migrated {
+        if let Err(e) = save_settings(path, &settings) {
+            eprintln!("Warning: Failed to save the migrated settings: {}", e);
+        }
+    }
+    Ok(settings)
+}
+
+/// Writes `settings` to `path`, replacing the previous file atomically.
+pub fn save_settings(path: &Path, settings: &Settings) -> Result<(), String> {
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec_pretty(settings)?;
+        let temp = path.with_extension("json.tmp");
+        std::fs::write(&temp, json)?;
+        std::fs::rename(&temp, path)
+    };
+    write().map_err(|e| format!("Failed to save settings to '{}': {}", path.display(), e))
+}
+
+/// Validates and saves `settings`, then applies them to the running app.
+/// Returns whether some of them only take effect once the app restarts.
+pub async fn replace_settings(app: &tauri::AppHandle, settings: &Settings) -> Result<bool, String> {
+    let state = app.state::<SettingsState>();
+    let mut current = state.settings.write().await;
+    commit_settings(app, &state.path, &mut current, settings.clone()).await
+}
+
+/// Saves and applies the current settings with `change` made to them.
+pub async fn change_settings(
+    app: &tauri::AppHandle,
+    change: impl FnOnce(&mut Settings),
+) -> Result<bool, String> {
+    let state = app.state::<SettingsState>();
+    let mut current = state.settings.write().await;
+    let mut settings = current.clone();
+    change(&mut settings);
+    commit_settings(app, &state.path, &mut current, settings).await
+}
+
+async fn commit_settings(
+    app: &tauri::AppHandle,
+    path: &Path,
+    current: &mut Settings,
+    settings: Settings,
+) -> Result<bool, String> {
+    settings.validate()?;
+    save_settings(path, &settings)?;
+    let restart_required = apply_settings(app, current, &settings).await;
+    *current = settings;
+    Ok(restart_required)
+}
+
+/// Applies `settings` to the running storage and map. Returns whether the
+/// Storage node keeps its previous configuration until the app restarts.
+pub async fn apply_settings(
+    app: &tauri::AppHandle,
+    previous: &Settings,
+    settings: &Settings,
+) -> bool {
+    let storage = app.state::<StorageState>();
+    *storage.download_options.write().await = settings.storage.download;
+    *storage.quota_options.write().await = settings.storage.quota;
+    *storage.peers.write().await = settings.storage.peers.clone();
+    if previous.storage.trusted_catalog_keys != settings.storage.trusted_catalog_keys {
+        apply_trusted_catalog_keys(app, &settings.storage.trusted_catalog_keys).await;
+    }
+
+    let restart_required = previous.storage.node_config_differs(&settings.storage)
+        && storage
+            .storage_manager()
+            .set_config(create_storage_config(app, &settings.storage))
+            .await;
+
+    let map = app.state::<MapState>();
+    map.reader_cache
+        .write()
+        .await
+        .resize(settings.map.max_cached_readers);
+    map.tile_cache
+        .write()
+        .await
+        .set_budget(settings.map.tile_cache_budget_bytes);
+    if previous.map.tile != settings.map.tile {
+        *map.tile_options.write().await = settings.map.tile;
+        map.tile_cache.write().await.clear();
+    }
+    if previous.map.priority_overrides != settings.map.priority_overrides {
+        map_service::set_priority_overrides(&settings.map.priority_overrides, &map).await;
+    }
+
+    restart_required
+}
+
+/// Trusts `keys` from now on, re-verifying the saved catalog against them.
+async fn apply_trusted_catalog_keys(app: &tauri::AppHandle, keys: &[String]) {
+    let storage = app.state::<StorageState>();
+    *storage.trusted_catalog_keys.write().await = keys.to_vec();
+
+    let catalog = get_catalog_dir(app).and_then(|dir| load_catalog(&dir, keys));
+    *storage.catalog.write().await = catalog.unwrap_or_else(|e| {
+        eprintln!("Warning: Ignoring the saved region catalog: {}", e);
+        None
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config").join(SETTINGS_FILE);
+        assert_eq!(load_settings(&path).unwrap(), Settings::default());
+
+        let mut settings = Settings::default();
+        settings.storage.max_peers = 12;
+        settings.map.tile.composite = false;
+        settings
+            .map
+            .priority_overrides
+            .insert("ottawa".to_string(), 5);
+        save_settings(&path, &settings).unwrap();
+        assert_eq!(load_settings(&path).unwrap(), settings);
+
+        std::fs::write(&path, r#"{ "storage": { "discoveryPort": 9000 } }"#).unwrap();
+        let migrated = load_settings(&path).unwrap();
+        assert_eq!(migrated.storage.discovery_port, 9000);
+        assert_eq!(migrated.map, Settings::default().map);
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"version\""));
+
+        std::fs::write(&path, r#"{ "version": 1, "storage": { "maxPeers": 0 } }"#).unwrap();
+        assert!(load_settings(&path).is_err());
+        std::fs::write(
+            &path,
+            r#"{ "version": 1, "storage": { "trustedCatalogKeys": ["abc"] } }"#,
+        )
+        .unwrap();
+        assert!(load_settings(&path).is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+
+use super::settings_types::Settings;
+
+pub struct SettingsState {
+    /// Where the settings are saved.
+    pub path: PathBuf,
+    pub settings: RwLock<Settings>,
+}
+
+impl SettingsState {
+    pub fn new(path: PathBuf, settings: Settings) -> Self {
+        Self {
+            path,
+            settings: RwLock::new(settings),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use storage_bindings::LogLevel;
+
+use crate::map::map_state::{MAX_CACHED_READERS, TILE_CACHE_BUDGET_BYTES};
+use crate::map::map_types::TileOptions;
+use crate::storage::parse_peer;
+use crate::storage::storage_config::{
+    BOOTSTRAP_NODES, DISCOVERY_PORT, MAX_PEERS, PEER_ADDRESS, PEER_ID, TRUSTED_CATALOG_KEYS,
+};
+use crate::storage::storage_types::{DownloadOptions, QuotaOptions};
+
+/// Version of the settings file written by this build.
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Upper bound for `MapSettings::max_cached_readers`.
+pub const MAX_CACHED_READERS_LIMIT: usize = 100;
+
+/// Missing fields take their default, so a settings file only needs the
+/// values the user changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    pub storage: StorageSettings,
+    pub map: MapSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            storage: StorageSettings::default(),
+            map: MapSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != SETTINGS_VERSION {
+            return Err(format!(
+                "Settings version {} is not {}",
+                self.version, SETTINGS_VERSION
+            ));
+        }
+        self.storage.validate()?;
+        self.map.validate()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StorageSettings {
+    pub log_level: LogLevel,
+    /// Signed peer records (`spr:…`) the node bootstraps from.
+    pub bootstrap_nodes: Vec<String>,
+    /// Peers `connect_to_peer` connects to, as `peerId:address`.
+    pub peers: Vec<String>,
+    /// Hex-encoded ed25519 keys of the publishers whose region catalogs are
+    /// trusted.
+    pub trusted_catalog_keys: Vec<String>,
+    pub discovery_port: u16,
+    pub max_peers: u32,
+    pub quota: QuotaOptions,
+    pub download: DownloadOptions,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            log_level: LogLevel::Info,
+            bootstrap_nodes: BOOTSTRAP_NODES.iter().map(|n| n.to_string()).collect(),
+            peers: vec![format!("{}:{}", PEER_ID, PEER_ADDRESS)],
+            trusted_catalog_keys: TRUSTED_CATALOG_KEYS.iter().map(|k| k.to_string()).collect(),
+            discovery_port: DISCOVERY_PORT,
+            max_peers: MAX_PEERS,
+            quota: QuotaOptions::default(),
+            download: DownloadOptions::default(),
+        }
+    }
+}
+
+impl StorageSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(node) = self.bootstrap_nodes.iter().find(|n| !n.starts_with("spr:")) {
+            return Err(format!(
+                "Bootstrap node '{}' is not a signed peer record",
+                node
+            ));
+        }
+        for peer in &self.peers {
+            parse_peer(peer).map_err(|e| e.to_string())?;
+        }
+        if let Some(key) = self
+            .trusted_catalog_keys
+            .iter()
+            .find(|k| k.len() != 64 || !k.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return Err(format!(
+                "Trusted catalog key '{}' is not a hex-encoded ed25519 key",
+                key
+            ));
+        }
+        if self.discovery_port == 0 {
+            return Err("The discovery port must not be 0".to_string());
+        }
+        if self.max_peers == 0 {
+            return Err("The node must be allowed at least one peer".to_string());
+        }
+        self.quota.validate()?;
+        self.download.validate()
+    }
+
+    /// Whether `other` changes the configuration the node is created with.
+    pub fn node_config_differs(&self, other: &StorageSettings) -> bool {
+        self.log_level != other.log_level
+            || self.bootstrap_nodes != other.bootstrap_nodes
+            || self.discovery_port != other.discovery_port
+            || self.max_peers != other.max_peers
+            || self.quota.max_bytes != other.quota.max_bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MapSettings {
+    /// Archives kept open at once.
+    pub max_cached_readers: NonZeroUsize,
+    /// Bytes of decompressed tiles kept in memory.
+    pub tile_cache_budget_bytes: usize,
+    pub tile: TileOptions,
+    /// User-set locality priorities keyed by locality id, taking precedence
+    /// over the archive metadata.
+    pub priority_overrides: BTreeMap<String, i32>,
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        Self {
+            max_cached_readers: MAX_CACHED_READERS,
+            tile_cache_budget_bytes: TILE_CACHE_BUDGET_BYTES,
+            tile: TileOptions::default(),
+            priority_overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl MapSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_cached_readers.get() > MAX_CACHED_READERS_LIMIT {
+            return Err(format!(
+                "At most {} archives can be kept open",
+                MAX_CACHED_READERS_LIMIT
+            ));
+        }
+        self.tile.validate()
+    }
+}
+
+/// Settings saved by `update_settings`, and whether some of them only take
+/// effect once the app restarts.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsUpdate {
+    pub settings: Settings,
+    pub restart_required: bool,
+}
@@ -2,17 +2,33 @@
 //! 
 //! This module provides:
 //! - Storage node lifecycle management (on-demand start/stop)
-//! - File download from Storage network using CIDs
+//! - File download from Storage network using CIDs, as cancellable
+//!   background jobs reporting their progress
+//! - Resumable downloads, validated before they are installed
+//! - Integrity verification and repair of the installed archives
+//! - Listing, updating and deleting installed localities
+//! - A storage quota, evicting the least recently viewed localities when
+//!   allowed
 //! - Configuration for bootstrap nodes and pmtiles CIDs
+//! - Signed region catalogs listing the archives available for download
 //! - Peer connection management
+//! - Node status and diagnostics, on demand and as a periodic event
 
+mod storage_archive;
+mod storage_catalog;
 pub mod storage_cmd;
-mod storage_config;
+pub(crate) mod storage_config;
+mod storage_jobs;
 mod storage_lifecycle;
-mod storage_service;
+mod storage_localities;
+mod storage_quota;
+pub(crate) mod storage_service;
 mod storage_state;
+mod storage_status;
 pub mod storage_types;
+mod storage_verify;
 
 pub use storage_lifecycle::StorageManager;
 pub use storage_service::{parse_peer, parse_peers};
 pub use storage_state::StorageState;
+pub use storage_status::spawn_status_events;
@@ -0,0 +1,247 @@
+//! Validation and installation of downloaded archives.
+//!
+//! Archives are downloaded next to their final path with a `.part` suffix,
+//! which the map ignores, and only renamed into place once their header,
+//! size and hash check out. The SHA-256 of every installed archive is kept
+//! in a `.sha256` sidecar so it can be verified again later, and the CID and
+//! version it was downloaded from in a `.source.json` one.
+
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::storage_types::{InstalledSource, StorageError};
+
+const PMTILES_MAGIC: &[u8] = b"PMTiles";
+const PMTILES_VERSION: u8 = 3;
+const PMTILES_HEADER_SIZE: usize = 127;
+
+/// Where an archive is written while it downloads.
+pub fn part_path(path: &Path) -> PathBuf {
+    with_suffix(path, "part")
+}
+
+/// Where the SHA-256 of an installed archive is kept.
+pub fn hash_path(path: &Path) -> PathBuf {
+    with_suffix(path, "sha256")
+}
+
+/// Where the source of an installed archive is recorded.
+pub fn source_path(path: &Path) -> PathBuf {
+    with_suffix(path, "source.json")
+}
+
+/// The archive at `path` and every file kept alongside it.
+pub fn archive_files(path: &Path) -> [PathBuf; 4] {
+    [
+        path.to_path_buf(),
+        part_path(path),
+        hash_path(path),
+        source_path(path),
+    ]
+}
+
+/// Deletes the archive at `path` along with its sidecars and partial
+/// download; missing files are ignored.
+pub fn remove_archive(path: &Path) -> Result<(), StorageError> {
+    for file in archive_files(path) {
+        match std::fs::remove_file(&file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+pub fn record_source(path: &Path, source: &InstalledSource) -> Result<(), StorageError> {
+    let json = serde_json::to_vec(source)
+        .map_err(|e| StorageError::Configuration(e.to_string()))?;
+    std::fs::write(source_path(path), json)?;
+    Ok(())
+}
+
+/// The recorded source of the archive at `path`, if any.
+pub fn read_source(path: &Path) -> Option<InstalledSource> {
+    let json = std::fs::read(source_path(path)).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Checks that `path` holds a version 3 PMTiles archive whose directories
+/// and tile data all lie within the file.
+pub fn validate_archive(path: &Path, expected_size: Option<u64>) -> Result<(), StorageError> {
+    let invalid = |reason: String| {
+        Err(StorageError::Download(format!(
+            "{} is not a valid archive: {}",
+            path.display(),
+            reason
+        )))
+    };
+
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    if let Some(expected) = expected_size {
+        if size != expected {
+            return invalid(format!("{} bytes instead of {}", size, expected));
+        }
+    }
+
+    let mut header = [0u8; PMTILES_HEADER_SIZE];
+    if file.read_exact(&mut header).is_err() {
+        return invalid("truncated header".to_string());
+    }
+    if &header[..PMTILES_MAGIC.len()] != PMTILES_MAGIC {
+        return invalid("not a PMTiles file".to_string());
+    }
+    if header[7] != PMTILES_VERSION {
+        return invalid(format!("unsupported PMTiles version {}", header[7]));
+    }
+
+    let field = |index: usize| {
+        let start = 8 + index * 8;
+        u64::from_le_bytes(header[start..start + 8].try_into().unwrap())
+    };
+    let sections = [
+        ("root directory", field(0), field(1)),
+        ("metadata", field(2), field(3)),
+        ("leaf directories", field(4), field(5)),
+        ("tile data", field(6), field(7)),
+    ];
+    for (name, offset, length) in sections {
+        let end = offset.checked_add(length);
+        if length > 0 && (offset < PMTILES_HEADER_SIZE as u64 || end.is_none_or(|end| end > size)) {
+            return invalid(format!("{} lies outside the file", name));
+        }
+    }
+    Ok(())
+}
+
+/// Hex-encoded SHA-256 of the file at `path`.
+pub fn hash_file(path: &Path) -> Result<String, StorageError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Validates the downloaded `part` and atomically renames it to `path`,
+/// returning its SHA-256. An invalid download is deleted.
+pub fn install_archive(
+    part: &Path,
+    path: &Path,
+    expected_size: Option<u64>,
+    expected_sha256: Option<&str>,
+) -> Result<String, StorageError> {
+    let checked = validate_archive(part, expected_size).and_then(|_| {
+        let sha256 = hash_file(part)?;
+        match expected_sha256 {
+            Some(expected) if !expected.eq_ignore_ascii_case(&sha256) => {
+                Err(StorageError::Download(format!(
+                    "{} does not match its SHA-256 {}",
+                    part.display(),
+                    expected
+                )))
+            }
+            _ => Ok(sha256),
+        }
+    });
+    let sha256 = match checked {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            let _ = std::fs::remove_file(part);
+            return Err(e);
+        }
+    };
+
+    File::open(part)?.sync_all()?;
+    std::fs::write(hash_path(path), &sha256)?;
+    std::fs::rename(part, path)?;
+    Ok(sha256)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A header-only archive followed by `data_length` bytes of tile data.
+    pub(crate) fn archive_bytes(data_length: u64) -> Vec<u8> {
+        let mut bytes = PMTILES_MAGIC.to_vec();
+        bytes.push(PMTILES_VERSION);
+        let offset = PMTILES_HEADER_SIZE as u64;
+        for value in [offset, 0, offset, 0, offset, 0, offset, data_length] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(PMTILES_HEADER_SIZE + data_length as usize, 0);
+        bytes
+    }
+
+    #[test]
+    fn validates_header_and_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ottawa.pmtiles");
+
+        std::fs::write(&path, archive_bytes(64)).unwrap();
+        assert!(validate_archive(&path, None).is_ok());
+        assert!(validate_archive(&path, Some(191)).is_ok());
+        assert!(validate_archive(&path, Some(200)).is_err());
+
+        let mut truncated = archive_bytes(64);
+        truncated.truncate(150);
+        std::fs::write(&path, truncated).unwrap();
+        assert!(validate_archive(&path, None).is_err());
+
+        std::fs::write(&path, b"<html>Not found</html>").unwrap();
+        assert!(validate_archive(&path, None).is_err());
+    }
+
+    #[test]
+    fn installs_only_matching_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ottawa.pmtiles");
+        let part = part_path(&path);
+
+        std::fs::write(&part, archive_bytes(64)).unwrap();
+        assert!(install_archive(&part, &path, None, Some("00")).is_err());
+        assert!(!part.exists() && !path.exists());
+
+        std::fs::write(&part, archive_bytes(64)).unwrap();
+        let sha256 = install_archive(&part, &path, Some(191), None).unwrap();
+        assert!(!part.exists());
+        assert_eq!(hash_file(&path).unwrap(), sha256);
+        assert_eq!(std::fs::read_to_string(hash_path(&path)).unwrap(), sha256);
+    }
+
+    #[test]
+    fn removes_archive_with_its_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ottawa.pmtiles");
+        let source = InstalledSource {
+            cid: "zDvZRwzm".to_string(),
+            version: Some("2026-02-15".to_string()),
+        };
+
+        std::fs::write(&path, archive_bytes(64)).unwrap();
+        std::fs::write(hash_path(&path), "00").unwrap();
+        record_source(&path, &source).unwrap();
+        assert_eq!(read_source(&path), Some(source));
+
+        remove_archive(&path).unwrap();
+        assert!(archive_files(&path).iter().all(|file| !file.exists()));
+        assert_eq!(read_source(&path), None);
+    }
+}
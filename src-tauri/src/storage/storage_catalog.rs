@@ -0,0 +1,258 @@
+//! Signed region catalogs.
+//!
+//! A catalog lists the archives available on the Storage network. It is
+//! fetched by CID like any other file, so the publisher signs it with an
+//! ed25519 key and only catalogs signed by a trusted key are used.
+
+use std::collections::HashSet;
+
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use super::storage_types::{
+    CatalogRegion, RegionCatalog, SignedCatalog, StorageError, VerifiedCatalog,
+};
+use crate::map::map_types::BoundingBox;
+
+/// Newest catalog format this version understands.
+pub const CATALOG_FORMAT: u32 = 1;
+
+const MAX_ZOOM: u8 = 30;
+
+/// Fails when no publisher key is trusted, as every catalog would be rejected.
+pub fn check_trusted_keys<K>(trusted_keys: &[K]) -> Result<(), StorageError> {
+    if trusted_keys.is_empty() {
+        return Err(StorageError::Configuration(
+            "No trusted catalog keys are configured".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a signed catalog and checks its signature against `trusted_keys`
+/// and its regions for consistency.
+pub fn verify_catalog(
+    bytes: &[u8],
+    trusted_keys: &[&str],
+    cid: Option<&str>,
+) -> Result<VerifiedCatalog, StorageError> {
+    check_trusted_keys(trusted_keys)?;
+    let signed: SignedCatalog = serde_json::from_slice(bytes)
+        .map_err(|e| StorageError::Catalog(format!("Malformed catalog: {}", e)))?;
+
+    let public_key = signed.public_key.to_ascii_lowercase();
+    if !trusted_keys
+        .iter()
+        .any(|key| key.eq_ignore_ascii_case(&public_key))
+    {
+        return Err(StorageError::Catalog(format!(
+            "Catalog is signed by an untrusted key {}",
+            public_key
+        )));
+    }
+
+    let key_bytes: [u8; 32] = decode_hex(&public_key, "public key")?;
+    let signature_bytes: [u8; 64] = decode_hex(&signed.signature, "signature")?;
+    let key = VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|e| StorageError::Catalog(format!("Invalid public key: {}", e)))?;
+    key.verify_strict(
+        signed.payload.as_bytes(),
+        &Signature::from_bytes(&signature_bytes),
+    )
+    .map_err(|_| StorageError::Catalog("Signature does not match the catalog".to_string()))?;
+
+    let catalog: RegionCatalog = serde_json::from_str(&signed.payload)
+        .map_err(|e| StorageError::Catalog(format!("Malformed catalog payload: {}", e)))?;
+    validate_catalog(&catalog)?;
+
+    Ok(VerifiedCatalog {
+        cid: cid.map(|c| c.to_string()),
+        public_key,
+        catalog,
+    })
+}
+
+fn decode_hex<const N: usize>(value: &str, what: &str) -> Result<[u8; N], StorageError> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| StorageError::Catalog(format!("Invalid {} encoding", what)))
+}
+
+fn validate_catalog(catalog: &RegionCatalog) -> Result<(), StorageError> {
+    if catalog.format > CATALOG_FORMAT {
+        return Err(StorageError::Catalog(format!(
+            "Unsupported catalog format {}",
+            catalog.format
+        )));
+    }
+
+    let mut ids = HashSet::new();
+    for region in &catalog.regions {
+        validate_region(region)?;
+        if !ids.insert(region.id.as_str()) {
+            return Err(StorageError::Catalog(format!(
+                "Duplicate region '{}'",
+                region.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_region(region: &CatalogRegion) -> Result<(), StorageError> {
+    let invalid = |reason: &str| {
+        Err(StorageError::Catalog(format!(
+            "Region '{}' {}",
+            region.id, reason
+        )))
+    };
+
+    // The id becomes a filename, so keep it to a safe alphabet.
+    let valid_id = !region.id.is_empty()
+        && region
+            .id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_id {
+        return invalid("has an invalid id");
+    }
+    if region.cid.trim().is_empty() {
+        return invalid("has no CID");
+    }
+
+    let b = &region.bounds;
+    let valid_bounds = (-180.0..=180.0).contains(&b.min_lon)
+        && (-180.0..=180.0).contains(&b.max_lon)
+        && (-90.0..=90.0).contains(&b.min_lat)
+        && (-90.0..=90.0).contains(&b.max_lat)
+        && b.min_lon <= b.max_lon
+        && b.min_lat <= b.max_lat;
+    if !valid_bounds {
+        return invalid("has invalid bounds");
+    }
+    if region.min_zoom > region.max_zoom || region.max_zoom > MAX_ZOOM {
+        return invalid("has an invalid zoom range");
+    }
+    Ok(())
+}
+
+/// Regions intersecting `bounds` whose name contains `query`, ignoring case.
+pub fn filter_regions<'a>(
+    catalog: &'a RegionCatalog,
+    bounds: Option<&BoundingBox>,
+    query: Option<&str>,
+) -> Vec<&'a CatalogRegion> {
+    let query = query
+        .map(|q| q.trim().to_lowercase())
+        .filter(|q| !q.is_empty());
+
+    catalog
+        .regions
+        .iter()
+        .filter(|region| bounds.is_none_or(|b| b.intersects(&region.bounds)))
+        .filter(|region| {
+            query
+                .as_deref()
+                .is_none_or(|q| region.name.to_lowercase().contains(q))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    pub(crate) fn region(id: &str, bounds: BoundingBox) -> CatalogRegion {
+        CatalogRegion {
+            id: id.to_string(),
+            name: id.replace('_', " "),
+            bounds,
+            min_zoom: 0,
+            max_zoom: 14,
+            size: 1024,
+            version: "2026-02-15".to_string(),
+            cid: format!("zDv{}", id),
+            sha256: None,
+        }
+    }
+
+    pub(crate) fn test_catalog() -> RegionCatalog {
+        RegionCatalog {
+            format: CATALOG_FORMAT,
+            published_at: "2026-03-01".to_string(),
+            regions: vec![
+                region("ottawa", BoundingBox::new(-76.0, 45.2, -75.5, 45.5)),
+                region("gatineau", BoundingBox::new(-75.9, 45.4, -75.6, 45.6)),
+                region("montreal", BoundingBox::new(-73.9, 45.4, -73.4, 45.7)),
+            ],
+        }
+    }
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    pub(crate) fn trusted_key() -> String {
+        hex::encode(signing_key().verifying_key().as_bytes())
+    }
+
+    pub(crate) fn sign(catalog: &RegionCatalog) -> Vec<u8> {
+        let payload = serde_json::to_string(catalog).unwrap();
+        let signature = signing_key().sign(payload.as_bytes());
+        serde_json::to_vec(&SignedCatalog {
+            payload,
+            public_key: trusted_key(),
+            signature: hex::encode(signature.to_bytes()),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_catalog_signed_by_trusted_key() {
+        let key = trusted_key();
+        let verified = verify_catalog(&sign(&test_catalog()), &[&key], Some("zDvCatalog")).unwrap();
+
+        assert_eq!(verified.catalog.regions.len(), 3);
+        assert_eq!(verified.public_key, key);
+        assert_eq!(verified.cid.as_deref(), Some("zDvCatalog"));
+    }
+
+    #[test]
+    fn rejects_untrusted_or_tampered_catalogs() {
+        let key = trusted_key();
+        let bytes = sign(&test_catalog());
+        assert!(verify_catalog(&bytes, &[], None).is_err());
+
+        let mut signed: SignedCatalog = serde_json::from_slice(&bytes).unwrap();
+        signed.payload = signed.payload.replace("ottawa", "toronto");
+        let tampered = serde_json::to_vec(&signed).unwrap();
+        assert!(verify_catalog(&tampered, &[&key], None).is_err());
+
+        let mut catalog = test_catalog();
+        catalog.regions[1].id = "ottawa".to_string();
+        assert!(verify_catalog(&sign(&catalog), &[&key], None).is_err());
+
+        let mut catalog = test_catalog();
+        catalog.regions[0].id = "../ottawa".to_string();
+        assert!(verify_catalog(&sign(&catalog), &[&key], None).is_err());
+    }
+
+    #[test]
+    fn filters_regions_by_area_and_name() {
+        let catalog = test_catalog();
+        let around_ottawa = BoundingBox::new(-75.8, 45.3, -75.7, 45.45);
+
+        let ids =
+            |regions: Vec<&CatalogRegion>| regions.iter().map(|r| r.id.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            ids(filter_regions(&catalog, Some(&around_ottawa), None)),
+            vec!["ottawa", "gatineau"]
+        );
+        assert_eq!(
+            ids(filter_regions(&catalog, Some(&around_ottawa), Some("GAT"))),
+            vec!["gatineau"]
+        );
+        assert_eq!(filter_regions(&catalog, None, Some(" ")).len(), 3);
+    }
+}
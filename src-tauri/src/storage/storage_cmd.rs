@@ -1,8 +1,25 @@
 use tauri::{Manager, State};
 
-use super::storage_config::{PEER_ADDRESS, PEER_ID};
+use super::storage_catalog::filter_regions;
+use super::storage_config::{storage_data_dir, PMTILES_CIDS};
+use super::storage_quota::storage_usage;
+use super::storage_jobs::{
+    accept_download_job, reload_map, spawn_download_job, start_download_job, JobFile,
+};
+use super::storage_localities::{list_localities, locality_updates, remove_locality};
+use super::storage_service::{get_catalog_dir, load_catalog};
 use super::storage_state::StorageState;
+use super::storage_status::get_storage_status;
+use super::storage_archive::remove_archive;
+use super::storage_types::{
+    CatalogInfo, CatalogRegion, DownloadOptions, InstalledLocality, LocalityHealth, QuotaOptions,
+    StorageError, StorageStatus, StorageUsage,
+};
 use super::{parse_peer, parse_peers};
+use crate::map::map_service::get_pmtiles_data_dir;
+use crate::map::map_types::BoundingBox;
+use crate::map::MapState;
+use crate::settings::change_settings;
 
 #[tauri::command]
 pub async fn start_storage_node(
@@ -32,19 +49,35 @@ pub async fn stop_storage_node(
         .map_err(|e| e.to_string())
 }
 
+/// State of the Storage node, its peers, repo and downloads.
+#[tauri::command]
+pub async fn storage_status(state: State<'_, StorageState>) -> Result<StorageStatus, String> {
+    Ok(get_storage_status(&state).await)
+}
+
+/// Connects to the configured peers, succeeding once any of them is reached.
 #[tauri::command]
 pub async fn connect_to_peer(
     state: State<'_, StorageState>,
 ) -> Result<(), String> {
-    let peer_spec = format!("{}:{}", PEER_ID, PEER_ADDRESS);
-    let (peer_id, address) = parse_peer(&peer_spec)
-        .map_err(|e| e.to_string())?;
-    
-    state
-        .storage_manager()
-        .connect_to_peer(peer_id, vec![address])
-        .await
-        .map_err(|e| e.to_string())
+    let peers = state.peers.read().await.clone();
+    let mut last_error = "No peers are configured".to_string();
+
+    for peer_spec in &peers {
+        let (peer_id, address) = parse_peer(peer_spec)
+            .map_err(|e| e.to_string())?;
+
+        match state
+            .storage_manager()
+            .connect_to_peer(peer_id, vec![address])
+            .await
+        {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+
+    Err(last_error)
 }
 
 #[tauri::command]
@@ -73,29 +106,374 @@ pub async fn connect_to_peers(
     Ok(connected_count)
 }
 
+/// Starts checking the installed archives, or downloading the legacy
+/// archives when no catalog has been fetched, and returns the download job
+/// id. Catalog regions are only downloaded on request, with
+/// `download_region` or `download_regions_in_bbox`, and outdated ones with
+/// `update_localities`.
 #[tauri::command]
 pub async fn download_pmtiles_files(
     state: State<'_, StorageState>,
-) -> Result<usize, String> {
-    let app = state.app_handle();
-    let pmtiles_dir = app.path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data directory: {}", e))?
-        .join("pmtiles");
-    
+) -> Result<String, String> {
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+
+    let files: Vec<JobFile> = match state.catalog.read().await.as_ref() {
+        Some(verified) if pmtiles_dir.exists() => {
+            list_localities(&pmtiles_dir, Some(&verified.catalog))
+                .map_err(|e| format!("Failed to list localities: {}", e))?
+                .into_iter()
+                .filter(|l| !l.update_available)
+                .filter_map(|l| JobFile::for_locality(&l.locality_id, Some(&verified.catalog)))
+                .collect()
+        }
+        Some(_) => Vec::new(),
+        None => PMTILES_CIDS.iter().map(|cid| JobFile::legacy(cid)).collect(),
+    };
+
+    start_download_job(&state, files, pmtiles_dir)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn fetch_catalog(
+    cid: String,
+    state: State<'_, StorageState>,
+) -> Result<CatalogInfo, String> {
+    let catalog_dir = get_catalog_dir(state.app_handle()).map_err(|e| e.to_string())?;
     let storage_manager = state.storage_manager();
-    
+
     storage_manager.initialize()
         .await
         .map_err(|e| format!("Failed to initialize storage node: {}", e))?;
-    
+
     storage_manager.start_node()
         .await
         .map_err(|e| format!("Failed to start storage node: {}", e))?;
-    
-    let files = super::storage_service::ensure_pmtiles_files(pmtiles_dir, storage_manager.as_ref())
+
+    let trusted_keys = state.trusted_catalog_keys.read().await.clone();
+    let verified = super::storage_service::fetch_catalog(
+        cid.trim(),
+        &catalog_dir,
+        &trusted_keys,
+        storage_manager.as_ref(),
+    )
+    .await
+    .map_err(|e| format!("Failed to fetch region catalog: {}", e))?;
+
+    let info = CatalogInfo::from(&verified);
+    *state.catalog.write().await = Some(verified);
+    Ok(info)
+}
+
+/// Re-reads the saved catalog and checks its signature again.
+#[tauri::command]
+pub async fn verify_catalog(
+    state: State<'_, StorageState>,
+) -> Result<CatalogInfo, String> {
+    let catalog_dir = get_catalog_dir(state.app_handle()).map_err(|e| e.to_string())?;
+
+    let result = load_catalog(&catalog_dir, &state.trusted_catalog_keys.read().await);
+    let mut catalog = state.catalog.write().await;
+    match result {
+        Ok(Some(verified)) => {
+            let info = CatalogInfo::from(&verified);
+            *catalog = Some(verified);
+            Ok(info)
+        }
+        Ok(None) => Err("No region catalog has been fetched".to_string()),
+        Err(e) => {
+            *catalog = None;
+            Err(e.to_string())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn list_catalog_regions(
+    bounds: Option<BoundingBox>,
+    query: Option<String>,
+    state: State<'_, StorageState>,
+) -> Result<Vec<CatalogRegion>, String> {
+    let catalog = state.catalog.read().await;
+    let verified = catalog
+        .as_ref()
+        .ok_or_else(|| "No region catalog has been fetched".to_string())?;
+
+    Ok(filter_regions(&verified.catalog, bounds.as_ref(), query.as_deref())
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+#[tauri::command]
+pub async fn download_region(
+    region_id: String,
+    state: State<'_, StorageState>,
+) -> Result<String, String> {
+    let files = {
+        let catalog = state.catalog.read().await;
+        let verified = catalog
+            .as_ref()
+            .ok_or_else(|| "No region catalog has been fetched".to_string())?;
+        let region = verified
+            .catalog
+            .regions
+            .iter()
+            .find(|r| r.id == region_id)
+            .ok_or_else(|| format!("Region '{}' is not in the catalog", region_id))?;
+        vec![JobFile::from_region(region)]
+    };
+
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+    start_download_job(&state, files, pmtiles_dir)
         .await
-        .map_err(|e| format!("Failed to download pmtiles files: {}", e))?;
-    
-    Ok(files.len())
+        .map_err(|e| e.to_string())
+}
+
+/// Starts downloading every catalog region intersecting `bounds`.
+#[tauri::command]
+pub async fn download_regions_in_bbox(
+    bounds: BoundingBox,
+    state: State<'_, StorageState>,
+) -> Result<String, String> {
+    if bounds.min_lon > bounds.max_lon || bounds.min_lat > bounds.max_lat {
+        return Err("Invalid bounding box".to_string());
+    }
+
+    let files: Vec<JobFile> = {
+        let catalog = state.catalog.read().await;
+        let verified = catalog
+            .as_ref()
+            .ok_or_else(|| "No region catalog has been fetched".to_string())?;
+        filter_regions(&verified.catalog, Some(&bounds), None)
+            .into_iter()
+            .map(JobFile::from_region)
+            .collect()
+    };
+    if files.is_empty() {
+        return Err("No catalog region intersects the area".to_string());
+    }
+
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+    start_download_job(&state, files, pmtiles_dir)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_download_options(
+    state: State<'_, StorageState>,
+) -> Result<DownloadOptions, String> {
+    Ok(*state.download_options.read().await)
+}
+
+/// Changes how the next download jobs are scheduled and saves it with the
+/// settings.
+#[tauri::command]
+pub async fn set_download_options(
+    app: tauri::AppHandle,
+    options: DownloadOptions,
+) -> Result<(), String> {
+    change_settings(&app, |settings| settings.storage.download = options).await?;
+    Ok(())
+}
+
+/// Aborts a download job; the partially downloaded file is removed.
+#[tauri::command]
+pub async fn cancel_download(
+    job_id: String,
+    state: State<'_, StorageState>,
+) -> Result<(), String> {
+    let job = state
+        .jobs
+        .get(&job_id)
+        .await
+        .ok_or_else(|| format!("No download job '{}'", job_id))?;
+
+    job.cancel(state.storage_manager())
+        .await
+        .map_err(|e| format!("Failed to cancel download: {}", e))
+}
+
+/// Checks the structure and content of every installed archive.
+#[tauri::command]
+pub async fn verify_localities(
+    state: State<'_, StorageState>,
+) -> Result<Vec<LocalityHealth>, String> {
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+    let catalog = state.catalog.read().await;
+
+    super::storage_verify::verify_localities(&pmtiles_dir, catalog.as_ref().map(|c| &c.catalog))
+        .await
+        .map_err(|e| format!("Failed to verify localities: {}", e))
+}
+
+/// Deletes the given archives and starts downloading them again; returns
+/// the download job id. The archives are only deleted once the job fits in
+/// the quota and overlaps no running download.
+#[tauri::command]
+pub async fn repair_localities(
+    locality_ids: Vec<String>,
+    state: State<'_, StorageState>,
+) -> Result<String, String> {
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+
+    let files = {
+        let catalog = state.catalog.read().await;
+        locality_ids
+            .iter()
+            .map(|id| {
+                JobFile::for_locality(id, catalog.as_ref().map(|c| &c.catalog))
+                    .ok_or_else(|| format!("No known source for locality '{}'", id))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let job = accept_download_job(&state, files, &pmtiles_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Release the archives' memory maps before their files go away.
+    let app = state.app_handle();
+    let map_state = app.state::<MapState>();
+    {
+        let mut readers = map_state.reader_cache.write().await;
+        for id in &locality_ids {
+            readers.pop(id);
+        }
+    }
+    let removed = job.files().await.iter().try_for_each(|file| {
+        remove_archive(&pmtiles_dir.join(&file.filename))
+            .map_err(|e| format!("Failed to remove {}: {}", file.filename, e))
+    });
+    reload_map(app).await;
+    if let Err(e) = removed {
+        state.jobs.remove(&job.id).await;
+        return Err(e);
+    }
+
+    Ok(spawn_download_job(&state, job, pmtiles_dir).await)
+}
+
+/// Lists the installed localities with their disk usage and source.
+#[tauri::command]
+pub async fn get_installed_localities(
+    state: State<'_, StorageState>,
+) -> Result<Vec<InstalledLocality>, String> {
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+    let catalog = state.catalog.read().await;
+
+    list_localities(&pmtiles_dir, catalog.as_ref().map(|c| &c.catalog))
+        .map_err(|e| format!("Failed to list localities: {}", e))
+}
+
+/// Deletes an installed locality and drops it from the map.
+#[tauri::command]
+pub async fn delete_locality(
+    locality_id: String,
+    state: State<'_, StorageState>,
+) -> Result<(), String> {
+    let app = state.app_handle();
+    let pmtiles_dir = get_pmtiles_data_dir(app)?;
+
+    // An update or repair would lose its partial download; holding the
+    // quota lock keeps one from starting meanwhile.
+    let _quota_check = state.quota_lock.lock().await;
+    let filename = format!("{}.pmtiles", locality_id);
+    if let Some(job_id) = state.jobs.downloading().await.get(&filename) {
+        return Err(StorageError::Busy(format!(
+            "{} is being downloaded by {}",
+            filename, job_id
+        ))
+        .to_string());
+    }
+
+    // Release the archive's memory map before its file goes away.
+    app.state::<MapState>()
+        .reader_cache
+        .write()
+        .await
+        .pop(&locality_id);
+    remove_locality(&pmtiles_dir, &locality_id)
+        .map_err(|e| format!("Failed to delete locality: {}", e))?;
+
+    reload_map(app).await;
+    Ok(())
+}
+
+/// Lists the installed localities the catalog holds a newer version of.
+#[tauri::command]
+pub async fn check_locality_updates(
+    state: State<'_, StorageState>,
+) -> Result<Vec<InstalledLocality>, String> {
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+    let catalog = state.catalog.read().await;
+    let verified = catalog
+        .as_ref()
+        .ok_or_else(|| "No region catalog has been fetched".to_string())?;
+
+    let localities = list_localities(&pmtiles_dir, Some(&verified.catalog))
+        .map_err(|e| format!("Failed to list localities: {}", e))?;
+    Ok(localities
+        .into_iter()
+        .filter(|l| l.update_available)
+        .collect())
+}
+
+/// Starts downloading the catalog version of the given localities, or of
+/// every outdated one; each replaces the installed archive once it is
+/// validated. Returns the download job id.
+#[tauri::command]
+pub async fn update_localities(
+    locality_ids: Option<Vec<String>>,
+    state: State<'_, StorageState>,
+) -> Result<String, String> {
+    let pmtiles_dir = get_pmtiles_data_dir(state.app_handle())?;
+
+    let files = {
+        let catalog = state.catalog.read().await;
+        let verified = catalog
+            .as_ref()
+            .ok_or_else(|| "No region catalog has been fetched".to_string())?;
+        let localities = list_localities(&pmtiles_dir, Some(&verified.catalog))
+            .map_err(|e| format!("Failed to list localities: {}", e))?;
+        locality_updates(&localities, &verified.catalog, locality_ids.as_deref())
+            .map_err(|e| e.to_string())?
+    };
+    if files.is_empty() {
+        return Err("Every locality is up to date".to_string());
+    }
+
+    start_download_job(&state, files, pmtiles_dir)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Disk space used by the archives and the Storage node, against the quota.
+#[tauri::command]
+pub async fn get_storage_usage(state: State<'_, StorageState>) -> Result<StorageUsage, String> {
+    let app = state.app_handle();
+    let pmtiles_dir = get_pmtiles_data_dir(app)?;
+    let quota = state.quota_options.read().await.max_bytes;
+
+    storage_usage(&pmtiles_dir, &storage_data_dir(app), quota)
+        .map_err(|e| format!("Failed to measure storage usage: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_quota_options(state: State<'_, StorageState>) -> Result<QuotaOptions, String> {
+    Ok(*state.quota_options.read().await)
+}
+
+/// Changes the storage quota checked before the next download jobs and
+/// saves it with the settings. A running Storage node keeps its previous
+/// quota until the app restarts.
+#[tauri::command]
+pub async fn set_quota_options(
+    app: tauri::AppHandle,
+    options: QuotaOptions,
+) -> Result<(), String> {
+    change_settings(&app, |settings| settings.storage.quota = options).await?;
+    Ok(())
 }
@@ -1,18 +1,26 @@
+use std::path::PathBuf;
 use storage_bindings::node::config::RepoKind;
-use storage_bindings::{LogLevel, StorageConfig};
+use storage_bindings::StorageConfig;
 use tauri::{AppHandle, Manager};
 
-/// Hardcoded peer configuration (until discovery issues are solved)
+use crate::settings::settings_types::StorageSettings;
+
+/// Default peer configuration (until discovery issues are solved)
 pub const PEER_ID: &str = "16Uiu2HAmLFwze8Y4pydjKyveeRjjotpospuEHLcGyVhw1mp1XBbM";
 pub const PEER_ADDRESS: &str = "/ip4/127.0.0.1/tcp/43101";
 
-const BOOTSTRAP_NODES: &[&str] = &[
+pub const BOOTSTRAP_NODES: &[&str] = &[
     "spr:CiUIAhIhAiJvIcA_ZwPZ9ugVKDbmqwhJZaig5zKyLiuaicRcCGqLEgIDARo8CicAJQgCEiECIm8hwD9nA9n26BUoNuarCEllqKDnMrIuK5qJxFwIaosQ3d6esAYaCwoJBJ_f8zKRAnU6KkYwRAIgM0MvWNJL296kJ9gWvfatfmVvT-A7O2s8Mxp8l9c8EW0CIC-h-H-jBVSgFjg3Eny2u33qF7BDnWFzo7fGfZ7_qc9P",
     "spr:CiUIAhIhAlNJ7ary8eOK5GcwQ6q4U8brR7iWjwhMwzHb8BzzmCEDEgIDARpJCicAJQgCEiECU0ntqvLx44rkZzBDqrhTxutHuJaPCEzDMdvwHPOYIQMQsZ67vgYaCwoJBK6Kf1-RAnVEGgsKCQSuin9fkQJ1RCpGMEQCIDxd6lXDvj1PcHgQYnNpHGfgCO5a7fejg3WhSjh2wTimAiB7YHsL1WZYU_zkHcNDWhRgMbkb3C5yRuvUhjBjGOYJYQ",
     "spr:CiUIAhIhAyUvcPkKoGE7-gh84RmKIPHJPdsX5Ugm_IHVJgF-Mmu_EgIDARo8CicAJQgCEiEDJS9w-QqgYTv6CHzhGYog8ck92xflSCb8gdUmAX4ya78QoemesAYaCwoJBES39Q2RAnVOKkYwRAIgLi3rouyaZFS_Uilx8k99ySdQCP1tsmLR21tDb9p8LcgCIG30o5YnEooQ1n6tgm9fCT7s53k6XlxyeSkD_uIO9mb3"
 ];
 
+/// Default hex-encoded ed25519 keys of the publishers whose region catalogs
+/// are trusted. None is published yet, so catalogs can only be fetched once a
+/// key is added to the `trustedCatalogKeys` setting.
+pub const TRUSTED_CATALOG_KEYS: &[&str] = &[];
 
+/// Legacy archives, downloaded when no region catalog has been fetched yet.
 pub const PMTILES_CIDS: &[&str] = &[
     "zDvZRwzm7wjV114xNBCHNM5EnbqWJd8NPje6EA1tywaTVLeVzY9o",
     "zDvZRwzm1ux2We2ztLDz5NGVBsjGEM5JBPb6qw5PyRcDECZobpuJ",
@@ -26,12 +34,25 @@ pub const PMTILES_CIDS: &[&str] = &[
     "zDvZRwzm5juUWq4K1MeK6fgmLhNwnbu2tu2aEfurXsonNbP8pg2k",
 ];
 
-pub fn create_storage_config(app_handle: &AppHandle) -> StorageConfig {
-    let data_dir = app_handle
+pub const DISCOVERY_PORT: u16 = 8089;
+
+pub const MAX_PEERS: u32 = 50;
+
+/// Default disk space the Storage node's repo and the installed archives may
+/// use together.
+pub const STORAGE_QUOTA_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Where the Storage node keeps its repo.
+pub fn storage_data_dir(app_handle: &AppHandle) -> PathBuf {
+    app_handle
         .path()
         .app_data_dir()
         .expect("Failed to get app data directory")
-        .join("storage_data");
+        .join("storage_data")
+}
+
+pub fn create_storage_config(app_handle: &AppHandle, settings: &StorageSettings) -> StorageConfig {
+    let data_dir = storage_data_dir(app_handle);
 
     if let Err(e) = std::fs::create_dir_all(&data_dir) {
         panic!(
@@ -42,15 +63,15 @@ pub fn create_storage_config(app_handle: &AppHandle) -> StorageConfig {
     }
 
     let mut config = StorageConfig::new()
-        .log_level(LogLevel::Info)
+        .log_level(settings.log_level)
         .data_dir(&data_dir)
-        .storage_quota(10 * 1024 * 1024 * 1024) // 1 GB
-        .max_peers(50)
-        .discovery_port(8089)
+        .storage_quota(settings.quota.max_bytes)
+        .max_peers(settings.max_peers)
+        .discovery_port(settings.discovery_port)
         .repo_kind(RepoKind::LevelDb);
 
-    for node in BOOTSTRAP_NODES {
-        config = config.add_bootstrap_node(*node);
+    for node in &settings.bootstrap_nodes {
+        config = config.add_bootstrap_node(node);
     }
 
     config
@@ -0,0 +1,840 @@
+//! Background download jobs.
+//!
+//! Starting a job returns its id right away; the archives are then
+//! downloaded a few at a time, each retried with exponential backoff, while
+//! progress events are emitted until the job completes, fails or is
+//! cancelled.
+
+use futures_util::future::join_all;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use storage_bindings::{download_cancel, download_manifest};
+use tauri::{Emitter, Manager};
+use tokio::sync::{Mutex, Notify, Semaphore};
+
+use super::storage_archive::{part_path, read_source, record_source, validate_archive};
+use super::storage_config::PMTILES_CIDS;
+use super::storage_lifecycle::StorageManager;
+use super::storage_quota::{bytes_needed, check_free_space, reserve_space};
+use super::storage_service::download_archive;
+use super::storage_state::StorageState;
+use super::storage_types::{
+    ActiveDownload, CatalogRegion, DownloadFinishedEvent, DownloadOptions, DownloadProgressEvent,
+    DownloadResult, DownloadStatus, FileProgress, InstalledSource, RegionCatalog, StorageError,
+};
+use crate::map::MapState;
+
+/// Emitted with a `DownloadProgressEvent` while a job runs.
+pub const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+
+/// Emitted with a `DownloadFinishedEvent` when a job ends.
+pub const DOWNLOAD_FINISHED_EVENT: &str = "download-finished";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Longest wait between two attempts at the same file.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Longest wait for a dataset manifest when a file's size is unknown.
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Weight of the latest interval in the smoothed download rate.
+const RATE_SMOOTHING: f64 = 0.3;
+
+/// An archive to download as part of a job.
+#[derive(Debug, Clone)]
+pub struct JobFile {
+    pub cid: String,
+    pub filename: String,
+    pub total_bytes: Option<u64>,
+    pub sha256: Option<String>,
+    pub version: Option<String>,
+}
+
+impl JobFile {
+    pub fn from_region(region: &CatalogRegion) -> Self {
+        Self {
+            cid: region.cid.clone(),
+            filename: format!("{}.pmtiles", region.id),
+            total_bytes: Some(region.size),
+            sha256: region.sha256.clone(),
+            version: Some(region.version.clone()),
+        }
+    }
+
+    /// An archive of the legacy CID list, named after its CID.
+    pub fn legacy(cid: &str) -> Self {
+        Self {
+            cid: cid.to_string(),
+            filename: format!("{}.pmtiles", cid),
+            total_bytes: None,
+            sha256: None,
+            version: None,
+        }
+    }
+
+    /// Where the archive of an installed locality comes from: its catalog
+    /// region, or the legacy list when the locality is named after a CID.
+    pub fn for_locality(locality_id: &str, catalog: Option<&RegionCatalog>) -> Option<Self> {
+        catalog
+            .and_then(|c| c.regions.iter().find(|r| r.id == locality_id))
+            .map(Self::from_region)
+            .or_else(|| {
+                PMTILES_CIDS
+                    .contains(&locality_id)
+                    .then(|| Self::legacy(locality_id))
+            })
+    }
+
+    /// Whether downloading both files at once would write the same `.part`
+    /// file or share a stream, which cancelling either would abort.
+    fn overlaps(&self, other: &JobFile) -> bool {
+        self.filename == other.filename || self.cid == other.cid
+    }
+
+    fn source(&self) -> InstalledSource {
+        InstalledSource {
+            cid: self.cid.clone(),
+            version: self.version.clone(),
+        }
+    }
+}
+
+struct FileState {
+    file: JobFile,
+    written: Arc<AtomicU64>,
+    status: Mutex<FileStatus>,
+}
+
+struct FileStatus {
+    status: DownloadStatus,
+    total_bytes: Option<u64>,
+    attempts: u32,
+    error: Option<String>,
+}
+
+pub struct DownloadJob {
+    pub id: String,
+    files: Vec<FileState>,
+    cancelled: Arc<AtomicBool>,
+    /// Wakes up downloads waiting to retry when the job is cancelled.
+    cancel_notify: Notify,
+    /// CIDs being downloaded, so cancelling can abort their streams.
+    current_cids: Mutex<HashSet<String>>,
+}
+
+impl DownloadJob {
+    /// Stops the job; the files being downloaded are aborted and removed.
+    pub async fn cancel(&self, storage_manager: &StorageManager) -> Result<(), StorageError> {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.cancel_notify.notify_waiters();
+
+        let current: Vec<String> = self.current_cids.lock().await.iter().cloned().collect();
+        if current.is_empty() {
+            return Ok(());
+        }
+        let node = storage_manager.get_node().await?;
+        for cid in current {
+            download_cancel(&node, &cid)
+                .await
+                .map_err(|e| StorageError::Download(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// The job's files, with the sizes read from their manifests since.
+    pub async fn files(&self) -> Vec<JobFile> {
+        let mut files = Vec::with_capacity(self.files.len());
+        for state in &self.files {
+            files.push(JobFile {
+                total_bytes: state.status.lock().await.total_bytes,
+                ..state.file.clone()
+            });
+        }
+        files
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    async fn set_status(&self, index: usize, status: DownloadStatus) {
+        self.files[index].status.lock().await.status = status;
+    }
+
+    async fn progress(&self) -> Vec<FileProgress> {
+        let mut files = Vec::with_capacity(self.files.len());
+        for state in &self.files {
+            let status = state.status.lock().await;
+            files.push(FileProgress {
+                cid: state.file.cid.clone(),
+                filename: state.file.filename.clone(),
+                bytes_downloaded: state.written.load(Ordering::Relaxed),
+                total_bytes: status.total_bytes,
+                status: status.status,
+                attempts: status.attempts,
+                error: status.error.clone(),
+            });
+        }
+        files
+    }
+}
+
+#[derive(Default)]
+pub struct DownloadJobs {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<String, Arc<DownloadJob>>>,
+}
+
+impl DownloadJobs {
+    pub async fn get(&self, job_id: &str) -> Option<Arc<DownloadJob>> {
+        self.jobs.lock().await.get(job_id).cloned()
+    }
+
+    /// Filenames the running jobs download, with the id of the job
+    /// downloading each.
+    pub async fn downloading(&self) -> HashMap<String, String> {
+        self.jobs
+            .lock()
+            .await
+            .values()
+            .flat_map(|job| {
+                job.files
+                    .iter()
+                    .map(|state| (state.file.filename.clone(), job.id.clone()))
+            })
+            .collect()
+    }
+
+    /// Progress of every running job, oldest first.
+    pub async fn active(&self) -> Vec<ActiveDownload> {
+        let mut jobs: Vec<_> = self.jobs.lock().await.values().cloned().collect();
+        jobs.sort_by_key(|job| job_number(&job.id));
+
+        let mut active = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let files = job.progress().await;
+            active.push(ActiveDownload {
+                job_id: job.id.clone(),
+                files: files.len(),
+                bytes_downloaded: files.iter().map(|f| f.bytes_downloaded).sum(),
+                total_bytes: files.iter().map(|f| f.total_bytes).sum(),
+            });
+        }
+        active
+    }
+
+    /// Bytes the jobs other than `job_id` still have to write into
+    /// `pmtiles_dir`; they are released as the archives land on disk and
+    /// when a job ends.
+    pub async fn bytes_reserved(&self, pmtiles_dir: &Path, job_id: &str) -> u64 {
+        let jobs: Vec<_> = self
+            .jobs
+            .lock()
+            .await
+            .values()
+            .filter(|job| job.id != job_id)
+            .cloned()
+            .collect();
+        let mut reserved = 0;
+        for job in jobs {
+            reserved += bytes_needed(pmtiles_dir, &job.files().await);
+        }
+        reserved
+    }
+
+    /// Registers a job for `files`, listed once each. Files a running job is
+    /// already downloading are refused.
+    async fn insert(&self, mut files: Vec<JobFile>) -> Result<Arc<DownloadJob>, StorageError> {
+        let mut filenames = HashSet::new();
+        files.retain(|file| filenames.insert(file.filename.clone()));
+
+        let mut jobs = self.jobs.lock().await;
+        for file in &files {
+            let running = jobs
+                .values()
+                .find(|job| job.files.iter().any(|state| state.file.overlaps(file)));
+            if let Some(job) = running {
+                return Err(StorageError::Busy(format!(
+                    "{} is already being downloaded by {}",
+                    file.filename, job.id
+                )));
+            }
+        }
+
+        let id = format!(
+            "download-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        let job = Arc::new(DownloadJob {
+            id: id.clone(),
+            files: files
+                .into_iter()
+                .map(|file| FileState {
+                    status: Mutex::new(FileStatus {
+                        status: DownloadStatus::Queued,
+                        total_bytes: file.total_bytes,
+                        attempts: 0,
+                        error: None,
+                    }),
+                    file,
+                    written: Arc::default(),
+                })
+                .collect(),
+            cancelled: Arc::default(),
+            cancel_notify: Notify::new(),
+            current_cids: Mutex::new(HashSet::new()),
+        });
+        jobs.insert(id, Arc::clone(&job));
+        Ok(job)
+    }
+
+    pub async fn remove(&self, job_id: &str) {
+        self.jobs.lock().await.remove(job_id);
+    }
+}
+
+fn job_number(job_id: &str) -> u64 {
+    job_id
+        .strip_prefix("download-")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Starts downloading `files` into `pmtiles_dir` in the background and
+/// returns the job id. A job that doesn't fit in the storage quota, or that
+/// overlaps a running job, is refused.
+pub async fn start_download_job(
+    state: &StorageState,
+    files: Vec<JobFile>,
+    pmtiles_dir: PathBuf,
+) -> Result<String, StorageError> {
+    let job = accept_download_job(state, files, &pmtiles_dir).await?;
+    Ok(spawn_download_job(state, job, pmtiles_dir).await)
+}
+
+/// Registers a job for `files` once it fits in the storage quota and
+/// overlaps no running job. It only downloads once passed to
+/// `spawn_download_job`, or is dropped with `DownloadJobs::remove`.
+pub async fn accept_download_job(
+    state: &StorageState,
+    files: Vec<JobFile>,
+    pmtiles_dir: &Path,
+) -> Result<Arc<DownloadJob>, StorageError> {
+    let _quota_check = state.quota_lock.lock().await;
+    let job = state.jobs.insert(files).await?;
+    let reserved = state.jobs.bytes_reserved(pmtiles_dir, &job.id).await;
+    let quota = *state.quota_options.read().await;
+    let reservation = reserve_space(
+        state.app_handle(),
+        pmtiles_dir,
+        &job.files().await,
+        reserved,
+        quota,
+    )
+    .await;
+    if let Err(e) = reservation {
+        state.jobs.remove(&job.id).await;
+        return Err(e);
+    }
+    Ok(job)
+}
+
+/// Downloads an accepted job's files in the background; returns its id.
+pub async fn spawn_download_job(
+    state: &StorageState,
+    job: Arc<DownloadJob>,
+    pmtiles_dir: PathBuf,
+) -> String {
+    let app = state.app_handle().clone();
+    let storage_manager = Arc::clone(state.storage_manager());
+    let options = *state.download_options.read().await;
+    let job_id = job.id.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let done = Arc::new(AtomicBool::new(false));
+        let reporter = tauri::async_runtime::spawn(report_progress(
+            app.clone(),
+            Arc::clone(&job),
+            Arc::clone(&done),
+        ));
+
+        let (results, outcome) = run_job(&app, &job, &pmtiles_dir, &storage_manager, options).await;
+        done.store(true, Ordering::Relaxed);
+        let _ = reporter.await;
+
+        if !results.is_empty() {
+            reload_map(&app).await;
+        }
+
+        let (status, error) = match outcome {
+            Ok(()) => (DownloadStatus::Completed, None),
+            Err(StorageError::Cancelled) => (DownloadStatus::Cancelled, None),
+            Err(e) => (DownloadStatus::Failed, Some(e.to_string())),
+        };
+        emit(
+            &app,
+            DOWNLOAD_FINISHED_EVENT,
+            DownloadFinishedEvent {
+                job_id: job.id.clone(),
+                status,
+                results,
+                files: job.progress().await,
+                error,
+            },
+        );
+
+        app.state::<StorageState>().jobs.remove(&job.id).await;
+    });
+
+    job_id
+}
+
+/// Rescans the pmtiles directory once the map has been initialized, so the
+/// map, search and routing pick up the archives installed or removed.
+pub async fn reload_map(app: &tauri::AppHandle) {
+    let map_ready = app.state::<MapState>().pmtiles_dir.read().await.is_some();
+    if map_ready {
+        crate::map::reload_and_notify(app).await;
+    }
+}
+
+/// Downloads the job's files, `options.concurrency` at a time. A file that
+/// keeps failing is reported without holding up the others.
+async fn run_job(
+    app: &tauri::AppHandle,
+    job: &DownloadJob,
+    pmtiles_dir: &Path,
+    storage_manager: &StorageManager,
+    options: DownloadOptions,
+) -> (Vec<DownloadResult>, Result<(), StorageError>) {
+    let prepared = async {
+        storage_manager.initialize().await?;
+        storage_manager.start_node().await?;
+        std::fs::create_dir_all(pmtiles_dir)?;
+        Ok::<_, StorageError>(())
+    };
+    if let Err(e) = prepared.await {
+        return (Vec::new(), Err(e));
+    }
+
+    let slots = Semaphore::new(options.concurrency.max(1));
+    let outcomes = join_all((0..job.files.len()).map(|index| {
+        download_with_retries(
+            app,
+            job,
+            index,
+            pmtiles_dir,
+            storage_manager,
+            &options,
+            &slots,
+        )
+    }))
+    .await;
+
+    let failed = outcomes.iter().filter(|o| o.is_err()).count();
+    let results = outcomes
+        .into_iter()
+        .filter_map(|o| o.ok().flatten())
+        .collect();
+    let outcome = if job.is_cancelled() {
+        Err(StorageError::Cancelled)
+    } else if failed > 0 {
+        Err(StorageError::Download(format!(
+            "{} of {} downloads failed",
+            failed,
+            job.files.len()
+        )))
+    } else {
+        Ok(())
+    };
+    (results, outcome)
+}
+
+/// Downloads one file of the job, retrying with exponential backoff.
+/// Returns `None` when a valid archive was already installed.
+async fn download_with_retries(
+    app: &tauri::AppHandle,
+    job: &DownloadJob,
+    index: usize,
+    pmtiles_dir: &Path,
+    storage_manager: &StorageManager,
+    options: &DownloadOptions,
+    slots: &Semaphore,
+) -> Result<Option<DownloadResult>, StorageError> {
+    let state = &job.files[index];
+    let file_path = pmtiles_dir.join(&state.file.filename);
+    let max_attempts = options.max_attempts.max(1);
+
+    for attempt in 1..=max_attempts {
+        let permit = slots.acquire().await;
+        if job.is_cancelled() || permit.is_err() {
+            remove_partial_download(&part_path(&file_path));
+            job.set_status(index, DownloadStatus::Cancelled).await;
+            return Err(StorageError::Cancelled);
+        }
+
+        let total_bytes = total_bytes(state, storage_manager).await;
+        if is_installed(&file_path, &state.file, total_bytes) {
+            let size = std::fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+            state.written.store(size, Ordering::Relaxed);
+            job.set_status(index, DownloadStatus::Completed).await;
+            return Ok(None);
+        }
+
+        let result = match total_bytes {
+            Some(total) => {
+                // Sizes missing from the catalog are only checked against the
+                // quota once their manifest is in.
+                let space = match state.file.total_bytes {
+                    Some(_) => check_free_space(pmtiles_dir, total),
+                    None => reserve_file_space(app, job, index, pmtiles_dir).await,
+                };
+                if let Err(e) = space {
+                    let mut status = state.status.lock().await;
+                    status.status = DownloadStatus::Failed;
+                    status.error = Some(e.to_string());
+                    return Err(e);
+                }
+
+                {
+                    let mut status = state.status.lock().await;
+                    status.status = DownloadStatus::Downloading;
+                    status.attempts = attempt;
+                }
+                state.written.store(0, Ordering::Relaxed);
+                job.current_cids.lock().await.insert(state.file.cid.clone());
+                let result = download_archive(
+                    &state.file.cid,
+                    &file_path,
+                    Some(total),
+                    state.file.sha256.as_deref(),
+                    storage_manager,
+                    Arc::clone(&state.written),
+                    Arc::clone(&job.cancelled),
+                )
+                .await;
+                job.current_cids.lock().await.remove(&state.file.cid);
+                result
+            }
+            None => Err(StorageError::Download(format!(
+                "Failed to read the size of {}",
+                state.file.cid
+            ))),
+        };
+        drop(permit);
+
+        let error = match result {
+            Ok(result) => {
+                if let Err(e) = record_source(&file_path, &state.file.source()) {
+                    eprintln!(
+                        "Warning: Failed to record the source of {}: {}",
+                        file_path.display(),
+                        e
+                    );
+                }
+                job.set_status(index, DownloadStatus::Completed).await;
+                return Ok(Some(result));
+            }
+            // A cancelled download doesn't keep its partial file; a failed
+            // one does, so the next attempt resumes from it.
+            Err(StorageError::Cancelled) => {
+                remove_partial_download(&part_path(&file_path));
+                job.set_status(index, DownloadStatus::Cancelled).await;
+                return Err(StorageError::Cancelled);
+            }
+            Err(e) => e,
+        };
+
+        let last = attempt == max_attempts;
+        {
+            let mut status = state.status.lock().await;
+            status.error = Some(error.to_string());
+            status.status = if last {
+                DownloadStatus::Failed
+            } else {
+                DownloadStatus::Retrying
+            };
+        }
+        if last {
+            return Err(error);
+        }
+
+        // Listen before checking the flag, so a cancel arriving in between
+        // still cuts the wait short; the next attempt then stops.
+        let cancelled = job.cancel_notify.notified();
+        tokio::pin!(cancelled);
+        cancelled.as_mut().enable();
+        if job.is_cancelled() {
+            continue;
+        }
+        let delay = retry_delay(options.retry_delay_ms, attempt);
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancelled => {}
+        }
+    }
+
+    unreachable!("the last attempt always returns")
+}
+
+/// Makes sure a file whose size was read from its manifest fits in the
+/// quota, next to what the job's other files and the other jobs still have
+/// to write.
+async fn reserve_file_space(
+    app: &tauri::AppHandle,
+    job: &DownloadJob,
+    index: usize,
+    pmtiles_dir: &Path,
+) -> Result<(), StorageError> {
+    let state = app.state::<StorageState>();
+    let _quota_check = state.quota_lock.lock().await;
+    let mut files = job.files().await;
+    let file = files.remove(index);
+    let reserved =
+        state.jobs.bytes_reserved(pmtiles_dir, &job.id).await + bytes_needed(pmtiles_dir, &files);
+    let quota = *state.quota_options.read().await;
+    reserve_space(app, pmtiles_dir, &[file], reserved, quota).await
+}
+
+/// Whether a valid archive of `file` is already at `path`. An archive
+/// recorded as coming from another CID is an older version to replace.
+fn is_installed(path: &Path, file: &JobFile, total_bytes: Option<u64>) -> bool {
+    path.exists()
+        && read_source(path).is_none_or(|source| source.cid == file.cid)
+        && validate_archive(path, total_bytes).is_ok()
+}
+
+/// The file's size from the catalog or, failing that, its dataset manifest.
+/// The manifest is fetched without holding the file's status, which the
+/// progress reports and the storage status read meanwhile.
+async fn total_bytes(state: &FileState, storage_manager: &StorageManager) -> Option<u64> {
+    if let Some(total) = state.status.lock().await.total_bytes {
+        return Some(total);
+    }
+
+    let node = storage_manager.get_node().await.ok()?;
+    let manifest =
+        tokio::time::timeout(MANIFEST_TIMEOUT, download_manifest(&node, &state.file.cid))
+            .await
+            .ok()?
+            .ok()?;
+    let total = manifest.size as u64;
+    state.status.lock().await.total_bytes = Some(total);
+    Some(total)
+}
+
+/// Delay before retrying after failed attempt number `attempt`: the base
+/// delay, doubled after every further failure, up to `MAX_RETRY_DELAY`.
+fn retry_delay(base_ms: u64, attempt: u32) -> Duration {
+    let factor = 1u64 << (attempt - 1).min(16);
+    Duration::from_millis(base_ms.saturating_mul(factor)).min(MAX_RETRY_DELAY)
+}
+
+fn remove_partial_download(part: &Path) {
+    if let Err(e) = std::fs::remove_file(part) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            eprintln!(
+                "Warning: Failed to remove partial download {}: {}",
+                part.display(),
+                e
+            );
+        }
+    }
+}
+
+async fn report_progress(app: tauri::AppHandle, job: Arc<DownloadJob>, done: Arc<AtomicBool>) {
+    let mut rate = RateEstimate::default();
+
+    loop {
+        let finished = done.load(Ordering::Relaxed);
+        let files = job.progress().await;
+        let bytes_downloaded = files.iter().map(|f| f.bytes_downloaded).sum();
+        let total_bytes = files.iter().map(|f| f.total_bytes).sum::<Option<u64>>();
+        rate.update(bytes_downloaded, Instant::now());
+
+        emit(
+            &app,
+            DOWNLOAD_PROGRESS_EVENT,
+            DownloadProgressEvent {
+                job_id: job.id.clone(),
+                files,
+                bytes_downloaded,
+                total_bytes,
+                rate_bps: rate.bytes_per_second,
+                eta_s: total_bytes.and_then(|total| rate.eta_s(total, bytes_downloaded)),
+            },
+        );
+
+        if finished {
+            break;
+        }
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+    }
+}
+
+/// Download rate smoothed over the progress intervals.
+#[derive(Debug, Default)]
+struct RateEstimate {
+    last: Option<(u64, Instant)>,
+    bytes_per_second: f64,
+}
+
+impl RateEstimate {
+    fn update(&mut self, bytes: u64, now: Instant) {
+        if let Some((last_bytes, last_time)) = self.last {
+            let elapsed = now.duration_since(last_time).as_secs_f64();
+            if elapsed > 0.0 {
+                let current = bytes.saturating_sub(last_bytes) as f64 / elapsed;
+                self.bytes_per_second = if self.bytes_per_second == 0.0 {
+                    current
+                } else {
+                    RATE_SMOOTHING * current + (1.0 - RATE_SMOOTHING) * self.bytes_per_second
+                };
+            }
+        }
+        self.last = Some((bytes, now));
+    }
+
+    fn eta_s(&self, total: u64, downloaded: u64) -> Option<f64> {
+        (self.bytes_per_second > 0.0)
+            .then(|| total.saturating_sub(downloaded) as f64 / self.bytes_per_second)
+    }
+}
+
+fn emit<T: serde::Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: T) {
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("Warning: Failed to emit {}: {}", event, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lists_active_jobs_in_order() {
+        let jobs = DownloadJobs::default();
+        let mut ids = Vec::new();
+        for i in 0..11 {
+            let job = jobs
+                .insert(vec![JobFile::legacy(&format!("zDvA{}", i))])
+                .await
+                .unwrap();
+            ids.push(job.id.clone());
+        }
+        let job = jobs
+            .insert(vec![JobFile::legacy("zDvB"), JobFile::legacy("zDvC")])
+            .await
+            .unwrap();
+        job.files[0].written.store(40, Ordering::Relaxed);
+        jobs.remove(&ids[0]).await;
+
+        let active = jobs.active().await;
+        assert_eq!(active.len(), 11);
+        assert_eq!(active[0].job_id, "download-2");
+        let last = active.last().unwrap();
+        assert_eq!(last.job_id, "download-12");
+        assert_eq!(last.files, 2);
+        assert_eq!(last.bytes_downloaded, 40);
+        assert_eq!(last.total_bytes, None);
+    }
+
+    #[tokio::test]
+    async fn refuses_files_of_running_jobs() {
+        let jobs = DownloadJobs::default();
+        let region = |cid: &str, filename: &str| JobFile {
+            filename: filename.to_string(),
+            ..JobFile::legacy(cid)
+        };
+
+        let job = jobs
+            .insert(vec![
+                region("zDvA", "ottawa.pmtiles"),
+                region("zDvA", "ottawa.pmtiles"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(job.files.len(), 1);
+
+        assert!(jobs
+            .insert(vec![region("zDvB", "ottawa.pmtiles")])
+            .await
+            .is_err());
+        assert!(jobs
+            .insert(vec![region("zDvA", "gatineau.pmtiles")])
+            .await
+            .is_err());
+        assert_eq!(jobs.active().await.len(), 1);
+        assert_eq!(
+            jobs.downloading().await,
+            HashMap::from([("ottawa.pmtiles".to_string(), job.id.clone())])
+        );
+
+        jobs.remove(&job.id).await;
+        assert!(jobs
+            .insert(vec![region("zDvB", "ottawa.pmtiles")])
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn reserves_what_running_jobs_still_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let jobs = DownloadJobs::default();
+        let sized = |cid: &str, total| JobFile {
+            total_bytes: Some(total),
+            ..JobFile::legacy(cid)
+        };
+
+        let running = jobs
+            .insert(vec![
+                sized("zDvA", 100),
+                sized("zDvB", 50),
+                JobFile::legacy("zDvD"),
+            ])
+            .await
+            .unwrap();
+        std::fs::write(part_path(&dir.path().join("zDvA.pmtiles")), [0; 40]).unwrap();
+        std::fs::write(dir.path().join("zDvB.pmtiles"), [0; 50]).unwrap();
+        let new = jobs.insert(vec![sized("zDvC", 500)]).await.unwrap();
+
+        assert_eq!(jobs.bytes_reserved(dir.path(), &new.id).await, 60);
+
+        // Sizes read from a manifest count once they are known.
+        running.files[2].status.lock().await.total_bytes = Some(200);
+        assert_eq!(jobs.bytes_reserved(dir.path(), &new.id).await, 260);
+        assert_eq!(jobs.bytes_reserved(dir.path(), &running.id).await, 500);
+
+        jobs.remove(&running.id).await;
+        assert_eq!(jobs.bytes_reserved(dir.path(), &new.id).await, 0);
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        assert_eq!(retry_delay(2000, 1), Duration::from_secs(2));
+        assert_eq!(retry_delay(2000, 2), Duration::from_secs(4));
+        assert_eq!(retry_delay(2000, 4), Duration::from_secs(16));
+        assert_eq!(retry_delay(2000, 10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u64::MAX, 30), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn rate_is_smoothed_and_gives_an_eta() {
+        let start = Instant::now();
+        let mut rate = RateEstimate::default();
+        rate.update(0, start);
+        assert_eq!(rate.eta_s(1000, 0), None);
+
+        rate.update(1000, start + Duration::from_secs(1));
+        assert!((rate.bytes_per_second - 1000.0).abs() < 1e-9);
+        assert!((rate.eta_s(5000, 1000).unwrap() - 4.0).abs() < 1e-9);
+
+        // A stalled interval slows the estimate down without zeroing it.
+        rate.update(1000, start + Duration::from_secs(2));
+        assert!((rate.bytes_per_second - 700.0).abs() < 1e-9);
+    }
+}
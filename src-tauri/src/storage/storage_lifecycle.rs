@@ -1,22 +1,82 @@
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use storage_bindings::{connect, StorageNode};
 use tokio::sync::Mutex;
 
-use super::storage_types::StorageError;
+use super::storage_types::{NodeState, StorageError};
+
+/// What the manager last did with the node, readable while an operation
+/// holds the node.
+struct Lifecycle {
+    state: NodeState,
+    last_error: Option<String>,
+    /// Peers connected to since the node started.
+    peers: BTreeSet<String>,
+}
 
 pub struct StorageManager {
     node: Arc<Mutex<Option<StorageNode>>>,
-    config: storage_bindings::StorageConfig,
+    config: Arc<Mutex<storage_bindings::StorageConfig>>,
+    lifecycle: Arc<std::sync::Mutex<Lifecycle>>,
 }
 
 impl StorageManager {
     pub fn new(config: storage_bindings::StorageConfig) -> Self {
         Self {
             node: Arc::new(Mutex::new(None)),
-            config,
+            config: Arc::new(Mutex::new(config)),
+            lifecycle: Arc::new(std::sync::Mutex::new(Lifecycle {
+                state: NodeState::Uninitialized,
+                last_error: None,
+                peers: BTreeSet::new(),
+            })),
         }
     }
 
+    pub fn state(&self) -> NodeState {
+        self.lifecycle().state
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.lifecycle().last_error.clone()
+    }
+
+    /// Peers connected to since the node started.
+    pub fn peers(&self) -> Vec<String> {
+        self.lifecycle().peers.iter().cloned().collect()
+    }
+
+    /// The node, unless it doesn't exist or an operation is using it.
+    pub fn idle_node(&self) -> Option<StorageNode> {
+        self.node.try_lock().ok()?.clone()
+    }
+
+    fn lifecycle(&self) -> std::sync::MutexGuard<'_, Lifecycle> {
+        self.lifecycle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_state(&self, state: NodeState) {
+        self.lifecycle().state = state;
+    }
+
+    /// Records a failed operation; `state` is `Some` when it leaves the
+    /// node in error.
+    fn record_error(&self, error: &StorageError, state: Option<NodeState>) {
+        let mut lifecycle = self.lifecycle();
+        lifecycle.last_error = Some(error.to_string());
+        if let Some(state) = state {
+            lifecycle.state = state;
+        }
+    }
+
+    /// Replaces the node configuration, used the next time a node is
+    /// created. Returns whether a node already exists and keeps running with
+    /// the previous configuration until the app restarts.
+    pub async fn set_config(&self, config: storage_bindings::StorageConfig) -> bool {
+        *self.config.lock().await = config;
+        self.node.lock().await.is_some()
+    }
+
     pub async fn initialize(&self) -> Result<(), StorageError> {
         let mut node_guard = self.node.lock().await;
         
@@ -25,11 +85,14 @@ impl StorageManager {
             return Ok(());
         }
 
-        let node = StorageNode::new(self.config.clone())
+        let config = self.config.lock().await.clone();
+        let node = StorageNode::new(config)
             .await
-            .map_err(|e| StorageError::NodeCreation(e.to_string()))?;
+            .map_err(|e| StorageError::NodeCreation(e.to_string()))
+            .inspect_err(|e| self.record_error(e, Some(NodeState::Error)))?;
 
         *node_guard = Some(node);
+        self.set_state(NodeState::Stopped);
         Ok(())
     }
 
@@ -44,10 +107,13 @@ impl StorageManager {
             return Ok(());
         }
 
+        self.set_state(NodeState::Starting);
         node.start()
             .await
-            .map_err(|e| StorageError::NodeStart(e.to_string()))?;
+            .map_err(|e| StorageError::NodeStart(e.to_string()))
+            .inspect_err(|e| self.record_error(e, Some(NodeState::Error)))?;
 
+        self.set_state(NodeState::Started);
         Ok(())
     }
 
@@ -58,7 +124,11 @@ impl StorageManager {
             if node.is_started() {
                 node.stop()
                     .await
-                    .map_err(|e| StorageError::NodeStop(e.to_string()))?;
+                    .map_err(|e| StorageError::NodeStop(e.to_string()))
+                    .inspect_err(|e| self.record_error(e, Some(NodeState::Error)))?;
+                let mut lifecycle = self.lifecycle();
+                lifecycle.state = NodeState::Stopped;
+                lifecycle.peers.clear();
             }
         }
 
@@ -93,8 +163,10 @@ impl StorageManager {
 
         connect(&node, &peer_id, &addresses)
             .await
-            .map_err(|e| StorageError::Connection(e.to_string()))?;
+            .map_err(|e| StorageError::Connection(e.to_string()))
+            .inspect_err(|e| self.record_error(e, None))?;
 
+        self.lifecycle().peers.insert(peer_id);
         Ok(())
     }
 
@@ -104,7 +176,8 @@ impl Clone for StorageManager {
     fn clone(&self) -> Self {
         Self {
             node: Arc::clone(&self.node),
-            config: self.config.clone(),
+            config: Arc::clone(&self.config),
+            lifecycle: Arc::clone(&self.lifecycle),
         }
     }
 }
@@ -0,0 +1,166 @@
+//! Installed localities: what is on disk, where it came from and whether the
+//! catalog holds a newer version.
+
+use std::path::Path;
+
+use super::storage_archive::{archive_files, hash_path, read_source, remove_archive};
+use super::storage_jobs::JobFile;
+use super::storage_types::{CatalogRegion, InstalledLocality, RegionCatalog, StorageError};
+
+/// Lists the localities installed in `pmtiles_dir`, compared with `catalog`.
+pub fn list_localities(
+    pmtiles_dir: &Path,
+    catalog: Option<&RegionCatalog>,
+) -> Result<Vec<InstalledLocality>, StorageError> {
+    let mut localities: Vec<_> = std::fs::read_dir(pmtiles_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pmtiles"))
+        .map(|path| installed_locality(&path, catalog))
+        .collect();
+    localities.sort_by(|a, b| a.locality_id.cmp(&b.locality_id));
+    Ok(localities)
+}
+
+fn installed_locality(path: &Path, catalog: Option<&RegionCatalog>) -> InstalledLocality {
+    let filename = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let locality_id = filename
+        .strip_suffix(".pmtiles")
+        .unwrap_or(&filename)
+        .to_string();
+    let region = catalog.and_then(|c| c.regions.iter().find(|r| r.id == locality_id));
+    let source = read_source(path);
+    let update_available = region.is_some_and(|region| is_outdated(path, region));
+
+    InstalledLocality {
+        name: region.map(|r| r.name.clone()),
+        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        disk_usage: archive_files(path)
+            .iter()
+            .filter_map(|file| std::fs::metadata(file).ok())
+            .map(|m| m.len())
+            .sum(),
+        cid: source.as_ref().map(|s| s.cid.clone()),
+        version: source.and_then(|s| s.version),
+        latest_version: region.map(|r| r.version.clone()),
+        update_available,
+        locality_id,
+        filename,
+    }
+}
+
+/// Whether the archive at `path` is an older version than `region`. Archives
+/// installed before sources were recorded are compared by hash.
+pub fn is_outdated(path: &Path, region: &CatalogRegion) -> bool {
+    match read_source(path) {
+        Some(source) => source.cid != region.cid,
+        None => {
+            let recorded = std::fs::read_to_string(hash_path(path)).ok();
+            matches!(
+                (recorded, &region.sha256),
+                (Some(recorded), Some(latest)) if !recorded.trim().eq_ignore_ascii_case(latest)
+            )
+        }
+    }
+}
+
+/// Deletes an installed locality's archive and everything kept alongside it.
+pub fn remove_locality(pmtiles_dir: &Path, locality_id: &str) -> Result<(), StorageError> {
+    let installed = list_localities(pmtiles_dir, None)?;
+    let locality = installed
+        .iter()
+        .find(|l| l.locality_id == locality_id)
+        .ok_or_else(|| {
+            StorageError::Configuration(format!("Locality '{}' is not installed", locality_id))
+        })?;
+
+    remove_archive(&pmtiles_dir.join(&locality.filename))
+}
+
+/// The catalog archives replacing the given localities, or every locality
+/// with an update when `locality_ids` is `None`.
+pub fn locality_updates(
+    localities: &[InstalledLocality],
+    catalog: &RegionCatalog,
+    locality_ids: Option<&[String]>,
+) -> Result<Vec<JobFile>, StorageError> {
+    let Some(ids) = locality_ids else {
+        return Ok(localities
+            .iter()
+            .filter(|l| l.update_available)
+            .filter_map(|l| JobFile::for_locality(&l.locality_id, Some(catalog)))
+            .collect());
+    };
+
+    ids.iter()
+        .map(|id| {
+            if !localities.iter().any(|l| &l.locality_id == id) {
+                return Err(StorageError::Configuration(format!(
+                    "Locality '{}' is not installed",
+                    id
+                )));
+            }
+            catalog
+                .regions
+                .iter()
+                .find(|r| &r.id == id)
+                .map(JobFile::from_region)
+                .ok_or_else(|| {
+                    StorageError::Configuration(format!("Locality '{}' is not in the catalog", id))
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage_archive::record_source;
+    use crate::storage::storage_archive::tests::archive_bytes;
+    use crate::storage::storage_catalog::tests::test_catalog;
+    use crate::storage::storage_types::InstalledSource;
+
+    #[test]
+    fn lists_updates_and_deletes_localities() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut catalog = test_catalog();
+        let region = catalog.regions[0].clone();
+        let path = dir.path().join(format!("{}.pmtiles", region.id));
+
+        std::fs::write(&path, archive_bytes(64)).unwrap();
+        std::fs::write(dir.path().join("local.pmtiles"), archive_bytes(8)).unwrap();
+        record_source(
+            &path,
+            &InstalledSource {
+                cid: region.cid.clone(),
+                version: Some(region.version.clone()),
+            },
+        )
+        .unwrap();
+
+        let listed = list_localities(dir.path(), Some(&catalog)).unwrap();
+        assert_eq!(listed.len(), 2);
+        let installed = listed.iter().find(|l| l.locality_id == region.id).unwrap();
+        assert_eq!(installed.size, 191);
+        assert!(installed.disk_usage > installed.size);
+        assert!(!installed.update_available);
+        assert!(locality_updates(&listed, &catalog, None)
+            .unwrap()
+            .is_empty());
+
+        catalog.regions[0].cid = "zDvZRwzmNewer".to_string();
+        catalog.regions[0].version = "2026-03-01".to_string();
+        let listed = list_localities(dir.path(), Some(&catalog)).unwrap();
+        let updates = locality_updates(&listed, &catalog, None).unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].cid, "zDvZRwzmNewer");
+        assert!(locality_updates(&listed, &catalog, Some(&["local".to_string()])).is_err());
+
+        remove_locality(dir.path(), &region.id).unwrap();
+        assert!(!path.exists());
+        assert!(remove_locality(dir.path(), &region.id).is_err());
+        assert_eq!(list_localities(dir.path(), None).unwrap().len(), 1);
+    }
+}
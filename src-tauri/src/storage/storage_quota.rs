@@ -0,0 +1,296 @@
+//! Disk quota for downloads.
+//!
+//! The installed archives and the Storage node's repo share one quota.
+//! Before a job starts, the bytes it adds, on top of those the running jobs
+//! still have to write, are checked against that quota and the free disk
+//! space; a job that doesn't fit is refused, unless the least recently
+//! viewed localities, outside the current view, may be evicted to make room
+//! for it. Archives missing from the catalog are checked the same way once
+//! their size is read from their manifest.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::Manager;
+
+use super::storage_archive::part_path;
+use super::storage_config::storage_data_dir;
+use super::storage_jobs::{reload_map, JobFile};
+use super::storage_localities::{list_localities, remove_locality};
+use super::storage_state::StorageState;
+use super::storage_types::{QuotaOptions, StorageError, StorageUsage};
+use crate::map::MapState;
+
+/// Free space left on the disk after any download.
+const FREE_SPACE_RESERVE: u64 = 256 * 1024 * 1024;
+
+/// Localities that served a map tile this recently are taken to cover the
+/// current view, and are never evicted.
+const CURRENT_VIEW_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Total size of the files under `dir`; a missing directory is empty.
+pub fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(if metadata.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+pub fn storage_usage(
+    pmtiles_dir: &Path,
+    repo_dir: &Path,
+    quota_bytes: u64,
+) -> Result<StorageUsage, StorageError> {
+    Ok(StorageUsage {
+        pmtiles_bytes: dir_size(pmtiles_dir),
+        repo_bytes: dir_size(repo_dir),
+        quota_bytes,
+        available_bytes: fs4::available_space(pmtiles_dir)?,
+    })
+}
+
+/// Fails when writing `bytes` to `dir` would leave less than the reserve
+/// free on its disk.
+pub fn check_free_space(dir: &Path, bytes: u64) -> Result<(), StorageError> {
+    let available = fs4::available_space(dir)?;
+    if bytes.saturating_add(FREE_SPACE_RESERVE) > available {
+        return Err(StorageError::Quota(format!(
+            "{} bytes are needed but only {} are free",
+            bytes, available
+        )));
+    }
+    Ok(())
+}
+
+/// Makes sure the archives of `files` fit in the quota and on the disk
+/// next to the `reserved` bytes running jobs still have to write, evicting
+/// the least recently viewed localities if `options` allow it. Localities a
+/// job downloads are never evicted, as that would delete its partial file.
+/// Called with the quota lock held and the job already registered.
+pub async fn reserve_space(
+    app: &tauri::AppHandle,
+    pmtiles_dir: &Path,
+    files: &[JobFile],
+    reserved: u64,
+    options: QuotaOptions,
+) -> Result<(), StorageError> {
+    let needed = bytes_needed(pmtiles_dir, files);
+    if needed == 0 {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(pmtiles_dir)?;
+    let usage = storage_usage(pmtiles_dir, &storage_data_dir(app), options.max_bytes)?;
+    let missing = shortfall(&usage, needed + reserved);
+    if missing == 0 {
+        return Ok(());
+    }
+    let refused = || {
+        StorageError::Quota(format!(
+            "downloading {} bytes, with {} reserved by running downloads, needs {} more bytes \
+             than the quota and the disk allow",
+            needed, reserved, missing
+        ))
+    };
+    if !options.evict_least_recently_viewed {
+        return Err(refused());
+    }
+
+    let map_state = app.state::<MapState>();
+    let last_viewed = map_state
+        .last_viewed
+        .lock()
+        .map(|viewed| viewed.clone())
+        .unwrap_or_default();
+    let downloading = app.state::<StorageState>().jobs.downloading().await;
+    let now = SystemTime::now();
+    let candidates = list_localities(pmtiles_dir, None)?
+        .into_iter()
+        .filter(|l| !downloading.contains_key(&l.filename))
+        .filter(|l| !in_current_view(&last_viewed, &l.locality_id, now))
+        .map(|l| EvictionCandidate {
+            viewed_at: viewed_at(&last_viewed, &l.locality_id, &pmtiles_dir.join(&l.filename)),
+            bytes: l.disk_usage,
+            locality_id: l.locality_id,
+        })
+        .collect();
+    let evicted = plan_eviction(candidates, missing).ok_or_else(refused)?;
+
+    {
+        let mut readers = map_state.reader_cache.write().await;
+        for id in &evicted {
+            readers.pop(id);
+        }
+    }
+    for id in &evicted {
+        remove_locality(pmtiles_dir, id)?;
+    }
+    reload_map(app).await;
+    Ok(())
+}
+
+/// Bytes the job's known-size archives still have to write. An archive
+/// replacing an installed one needs its full size while both are on disk.
+pub fn bytes_needed(pmtiles_dir: &Path, files: &[JobFile]) -> u64 {
+    files
+        .iter()
+        .filter_map(|file| {
+            let total = file.total_bytes?;
+            let path = pmtiles_dir.join(&file.filename);
+            let installed = std::fs::metadata(&path).is_ok_and(|m| m.len() == total);
+            let partial = std::fs::metadata(part_path(&path)).map_or(0, |m| m.len());
+            (!installed).then(|| total.saturating_sub(partial))
+        })
+        .sum()
+}
+
+/// Bytes to free before `needed` more bytes fit in the quota and leave the
+/// reserve free on the disk.
+fn shortfall(usage: &StorageUsage, needed: u64) -> u64 {
+    let over_quota = (usage.used_bytes() + needed).saturating_sub(usage.quota_bytes);
+    let over_disk = (needed + FREE_SPACE_RESERVE).saturating_sub(usage.available_bytes);
+    over_quota.max(over_disk)
+}
+
+/// When a locality was last viewed; localities not viewed since the app
+/// started fall back to when their archive was installed.
+fn viewed_at(
+    last_viewed: &HashMap<String, SystemTime>,
+    locality_id: &str,
+    path: &Path,
+) -> SystemTime {
+    last_viewed
+        .get(locality_id)
+        .copied()
+        .or_else(|| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .unwrap_or(UNIX_EPOCH)
+}
+
+fn in_current_view(
+    last_viewed: &HashMap<String, SystemTime>,
+    locality_id: &str,
+    now: SystemTime,
+) -> bool {
+    last_viewed.get(locality_id).is_some_and(|viewed| {
+        now.duration_since(*viewed)
+            .map_or(true, |age| age < CURRENT_VIEW_WINDOW)
+    })
+}
+
+struct EvictionCandidate {
+    locality_id: String,
+    bytes: u64,
+    viewed_at: SystemTime,
+}
+
+/// The least recently viewed localities freeing at least `bytes`, or
+/// `None` if evicting all of them isn't enough.
+fn plan_eviction(mut candidates: Vec<EvictionCandidate>, bytes: u64) -> Option<Vec<String>> {
+    candidates.sort_by_key(|c| c.viewed_at);
+
+    let mut freed = 0;
+    let mut evicted = Vec::new();
+    for candidate in candidates {
+        if freed >= bytes {
+            break;
+        }
+        freed += candidate.bytes;
+        evicted.push(candidate.locality_id);
+    }
+    (freed >= bytes).then_some(evicted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, bytes: u64, viewed_secs: u64) -> EvictionCandidate {
+        EvictionCandidate {
+            locality_id: id.to_string(),
+            bytes,
+            viewed_at: UNIX_EPOCH + Duration::from_secs(viewed_secs),
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_viewed_first() {
+        let candidates = || {
+            vec![
+                candidate("ottawa", 300, 30),
+                candidate("gatineau", 200, 10),
+                candidate("montreal", 500, 20),
+            ]
+        };
+
+        assert_eq!(plan_eviction(candidates(), 0), Some(vec![]));
+        assert_eq!(
+            plan_eviction(candidates(), 200),
+            Some(vec!["gatineau".to_string()])
+        );
+        assert_eq!(
+            plan_eviction(candidates(), 600),
+            Some(vec!["gatineau".to_string(), "montreal".to_string()])
+        );
+        assert_eq!(plan_eviction(candidates(), 1001), None);
+    }
+
+    #[test]
+    fn keeps_localities_in_the_current_view() {
+        let now = SystemTime::now();
+        let last_viewed = HashMap::from([
+            ("ottawa".to_string(), now - Duration::from_secs(10)),
+            ("gatineau".to_string(), now - CURRENT_VIEW_WINDOW * 2),
+        ]);
+
+        assert!(in_current_view(&last_viewed, "ottawa", now));
+        assert!(!in_current_view(&last_viewed, "gatineau", now));
+        assert!(!in_current_view(&last_viewed, "montreal", now));
+    }
+
+    #[test]
+    fn shortfall_covers_quota_and_disk() {
+        let usage = StorageUsage {
+            pmtiles_bytes: 600,
+            repo_bytes: 300,
+            quota_bytes: 1000,
+            available_bytes: FREE_SPACE_RESERVE + 10_000,
+        };
+
+        assert_eq!(shortfall(&usage, 100), 0);
+        assert_eq!(shortfall(&usage, 150), 50);
+        assert_eq!(shortfall(&usage, 20_000), 19_900);
+    }
+
+    #[test]
+    fn counts_only_missing_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, total| JobFile {
+            cid: name.to_string(),
+            filename: format!("{}.pmtiles", name),
+            total_bytes: Some(total),
+            sha256: None,
+            version: None,
+        };
+
+        std::fs::write(dir.path().join("ottawa.pmtiles"), [0; 100]).unwrap();
+        std::fs::write(part_path(&dir.path().join("gatineau.pmtiles")), [0; 40]).unwrap();
+
+        let files = [
+            file("ottawa", 100),
+            file("gatineau", 100),
+            file("montreal", 100),
+        ];
+        assert_eq!(bytes_needed(dir.path(), &files), 60 + 100);
+        assert_eq!(dir_size(dir.path()), 140);
+    }
+}
@@ -1,8 +1,18 @@
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use storage_bindings::{download_stream, DownloadStreamOptions};
+use tauri::Manager;
 
+use super::storage_archive::{install_archive, part_path};
+use super::storage_catalog::{check_trusted_keys, verify_catalog};
 use super::storage_lifecycle::StorageManager;
-use super::storage_types::{DownloadResult, StorageError};
+use super::storage_types::{DownloadResult, StorageError, VerifiedCatalog};
+
+const CATALOG_FILE: &str = "catalog.json";
+const CATALOG_CID_FILE: &str = "catalog.cid";
 
 pub fn parse_peer(peer_str: &str) -> Result<(String, String), StorageError> {
     let parts: Vec<&str> = peer_str.splitn(2, ':').collect();
@@ -26,10 +36,32 @@ pub fn parse_peers(peers_str: &str) -> Result<Vec<(String, String)>, StorageErro
         .collect()
 }
 
-pub async fn download_pmtiles_file(
+pub async fn download_file(
+    cid: &str,
+    save_path: PathBuf,
+    storage_manager: &StorageManager,
+) -> Result<DownloadResult, StorageError> {
+    download_file_tracked(
+        cid,
+        save_path,
+        storage_manager,
+        Arc::default(),
+        Arc::default(),
+    )
+    .await
+}
+
+/// Downloads `cid` to `save_path`, adding the bytes received to `written`.
+/// Bytes left in `save_path` by an interrupted download are compared with
+/// the stream and kept up to the first difference; the Storage node serves
+/// the blocks it already fetched from its local repo. Setting `cancelled`
+/// stops writing; the download is aborted separately with `download_cancel`.
+pub async fn download_file_tracked(
     cid: &str,
     save_path: PathBuf,
     storage_manager: &StorageManager,
+    written: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
 ) -> Result<DownloadResult, StorageError> {
     if cid.is_empty() {
         return Err(StorageError::InvalidCid("CID cannot be empty".to_string()));
@@ -41,12 +73,32 @@ pub async fn download_pmtiles_file(
         return Err(StorageError::NodeNotStarted);
     }
 
-    let download_options = DownloadStreamOptions::new(cid)
-        .filepath(&save_path);
+    let writer = DownloadWriter::open(&save_path, Arc::clone(&written), Arc::clone(&cancelled))?;
+    // The node checks every block against the dataset's CID.
+    let download_options = DownloadStreamOptions::new(cid).writer(writer).verify(true);
 
-    let result = download_stream(&node, cid, download_options)
-        .await
-        .map_err(|e| StorageError::Download(e.to_string()))?;
+    let result = download_stream(&node, cid, download_options).await;
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(StorageError::Cancelled);
+    }
+    let result = result.map_err(|e| StorageError::Download(e.to_string()))?;
+
+    // Write errors are only logged by the stream, so compare what reached
+    // the file with what was received.
+    let size = written.load(Ordering::Relaxed);
+    if size != result.size as u64 {
+        return Err(StorageError::Download(format!(
+            "Wrote {} of {} bytes to {}",
+            size,
+            result.size,
+            save_path.display()
+        )));
+    }
+    // Drop whatever an earlier, longer attempt left past the end.
+    OpenOptions::new()
+        .write(true)
+        .open(&save_path)?
+        .set_len(size)?;
 
     Ok(DownloadResult {
         cid: cid.to_string(),
@@ -55,35 +107,196 @@ pub async fn download_pmtiles_file(
     })
 }
 
-pub async fn ensure_pmtiles_files(
-    pmtiles_dir: PathBuf,
+/// Downloads an archive to its `.part` file, then validates it and renames
+/// it to `path`.
+pub async fn download_archive(
+    cid: &str,
+    path: &Path,
+    expected_size: Option<u64>,
+    expected_sha256: Option<&str>,
     storage_manager: &StorageManager,
-) -> Result<Vec<(String, PathBuf)>, StorageError> {
-    std::fs::create_dir_all(&pmtiles_dir)?;
+    written: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
+) -> Result<DownloadResult, StorageError> {
+    let part = part_path(path);
+    let mut result =
+        download_file_tracked(cid, part.clone(), storage_manager, written, cancelled).await?;
 
-    let mut files = Vec::new();
+    install_archive(&part, path, expected_size, expected_sha256)?;
 
-    for cid in super::storage_config::PMTILES_CIDS {
-        let filename = format!("{}.pmtiles", cid);
-        let file_path = pmtiles_dir.join(&filename);
+    result.filepath = path.to_string_lossy().to_string();
+    Ok(result)
+}
+
+/// Writer of a download that resumes a partial file, counting the bytes
+/// received and refusing further writes once the download is cancelled.
+struct DownloadWriter {
+    file: File,
+    /// Length of the partial file not yet compared with the stream.
+    resume_len: u64,
+    position: u64,
+    written: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl DownloadWriter {
+    fn open(
+        path: &Path,
+        written: Arc<AtomicU64>,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<Self, StorageError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let resume_len = file.metadata()?.len();
+        Ok(Self {
+            file,
+            resume_len,
+            position: 0,
+            written,
+            cancelled,
+        })
+    }
+}
 
-        if file_path.exists() {
-            files.push((filename, file_path));
-            continue;
+impl Write for DownloadWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Err(std::io::Error::other("Download cancelled"));
         }
 
-        download_pmtiles_file(cid, file_path.clone(), storage_manager).await?;
+        let mut rest = buf;
+        if self.position < self.resume_len {
+            let overlap = rest.len().min((self.resume_len - self.position) as usize);
+            let mut existing = vec![0u8; overlap];
+            self.file.seek(SeekFrom::Start(self.position))?;
+            self.file.read_exact(&mut existing)?;
 
-        files.push((filename, file_path));
+            let same = existing
+                .iter()
+                .zip(rest)
+                .take_while(|(a, b)| a == b)
+                .count();
+            self.position += same as u64;
+            rest = &rest[same..];
+            if same < overlap {
+                self.file.set_len(self.position)?;
+                self.resume_len = self.position;
+            }
+        }
+
+        if !rest.is_empty() {
+            self.file.seek(SeekFrom::Start(self.position))?;
+            self.file.write_all(rest)?;
+            self.position += rest.len() as u64;
+        }
+
+        self.written.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        Ok(buf.len())
     }
 
-    Ok(files)
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+pub fn get_catalog_dir(app: &tauri::AppHandle) -> Result<PathBuf, StorageError> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("catalog"))
+        .map_err(|e| StorageError::Configuration(format!("Failed to get app data directory: {}", e)))
+}
+
+/// Downloads the catalog published under `cid` and keeps it in `catalog_dir`
+/// once its signature is verified.
+pub async fn fetch_catalog(
+    cid: &str,
+    catalog_dir: &Path,
+    trusted_keys: &[String],
+    storage_manager: &StorageManager,
+) -> Result<VerifiedCatalog, StorageError> {
+    check_trusted_keys(trusted_keys)?;
+    std::fs::create_dir_all(catalog_dir)?;
+
+    let download_path = catalog_dir.join(format!("{}.download", cid));
+    download_file(cid, download_path.clone(), storage_manager).await?;
+    let bytes = std::fs::read(&download_path);
+    std::fs::remove_file(&download_path)?;
+    let bytes = bytes?;
+
+    let keys: Vec<&str> = trusted_keys.iter().map(String::as_str).collect();
+    let verified = verify_catalog(&bytes, &keys, Some(cid))?;
+
+    std::fs::write(catalog_dir.join(CATALOG_FILE), &bytes)?;
+    std::fs::write(catalog_dir.join(CATALOG_CID_FILE), cid)?;
+
+    Ok(verified)
+}
+
+/// Loads and re-verifies the catalog kept in `catalog_dir`, if any.
+pub fn load_catalog(
+    catalog_dir: &Path,
+    trusted_keys: &[String],
+) -> Result<Option<VerifiedCatalog>, StorageError> {
+    let bytes = match std::fs::read(catalog_dir.join(CATALOG_FILE)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let cid = std::fs::read_to_string(catalog_dir.join(CATALOG_CID_FILE)).ok();
+
+    let keys: Vec<&str> = trusted_keys.iter().map(String::as_str).collect();
+    verify_catalog(&bytes, &keys, cid.as_deref().map(str::trim)).map(Some)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn writer(path: &Path, cancelled: &Arc<AtomicBool>) -> DownloadWriter {
+        DownloadWriter::open(path, Arc::default(), Arc::clone(cancelled)).unwrap()
+    }
+
+    #[test]
+    fn download_writer_counts_until_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("region.pmtiles.part");
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut writer = writer(&path, &cancelled);
+
+        writer.write_all(b"PMTiles").unwrap();
+        cancelled.store(true, Ordering::Relaxed);
+        assert!(writer.write_all(b"more").is_err());
+
+        assert_eq!(writer.written.load(Ordering::Relaxed), 7);
+        assert_eq!(std::fs::read(&path).unwrap(), b"PMTiles");
+    }
+
+    #[test]
+    fn download_writer_resumes_matching_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("region.pmtiles.part");
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        std::fs::write(&path, b"PMTiles v3").unwrap();
+        let mut resumed = writer(&path, &cancelled);
+        resumed.write_all(b"PMTi").unwrap();
+        resumed.write_all(b"les v3 data").unwrap();
+        drop(resumed);
+        assert_eq!(std::fs::read(&path).unwrap(), b"PMTiles v3 data");
+
+        // A partial file from different content is rewritten from where it
+        // diverges.
+        std::fs::write(&path, b"PMTiles v2 data").unwrap();
+        let mut rewritten = writer(&path, &cancelled);
+        rewritten.write_all(b"PMTiles v3 data").unwrap();
+        assert_eq!(rewritten.written.load(Ordering::Relaxed), 15);
+        assert_eq!(std::fs::read(&path).unwrap(), b"PMTiles v3 data");
+    }
+
     #[test]
     fn parse_peer_valid() {
         let result = parse_peer("16Uiu2HAmGxKj5uXvPvH8yqL5fQJzN3jKd8X9vR2tY1wZ4pL6mN7o:/ip4/194.60.86.122/tcp/4001");
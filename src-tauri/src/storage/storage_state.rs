@@ -1,22 +1,59 @@
 use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
 
 use super::storage_config::create_storage_config;
+use super::storage_jobs::DownloadJobs;
 use super::storage_lifecycle::StorageManager;
-use super::storage_types::StorageError;
+use super::storage_service::{get_catalog_dir, load_catalog};
+use super::storage_types::{DownloadOptions, QuotaOptions, StorageError, VerifiedCatalog};
+use crate::settings::settings_types::StorageSettings;
 
 pub struct StorageState {
     storage_manager: Arc<StorageManager>,
     app_handle: tauri::AppHandle,
+    /// The region catalog, once fetched and verified.
+    pub catalog: RwLock<Option<VerifiedCatalog>>,
+    /// Download jobs still running.
+    pub jobs: DownloadJobs,
+    pub download_options: RwLock<DownloadOptions>,
+    pub quota_options: RwLock<QuotaOptions>,
+    /// Serializes the quota checks of new download jobs.
+    pub quota_lock: Mutex<()>,
+    /// Peers `connect_to_peer` connects to, as `peerId:address`.
+    pub peers: RwLock<Vec<String>>,
+    /// Keys of the publishers whose catalogs are trusted.
+    pub trusted_catalog_keys: RwLock<Vec<String>>,
 }
 
 impl StorageState {
-    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, StorageError> {
-        let config = create_storage_config(app_handle);
+    pub fn new(
+        app_handle: &tauri::AppHandle,
+        settings: &StorageSettings,
+    ) -> Result<Self, StorageError> {
+        let config = create_storage_config(app_handle, settings);
         let storage_manager = Arc::new(StorageManager::new(config));
+
+        let catalog = match load_catalog(
+            &get_catalog_dir(app_handle)?,
+            &settings.trusted_catalog_keys,
+        ) {
+            Ok(catalog) => catalog,
+            Err(e) => {
+                eprintln!("Warning: Ignoring the saved region catalog: {}", e);
+                None
+            }
+        };
         
         Ok(Self { 
             storage_manager,
             app_handle: app_handle.clone(),
+            catalog: RwLock::new(catalog),
+            jobs: DownloadJobs::default(),
+            download_options: RwLock::new(settings.download),
+            quota_options: RwLock::new(settings.quota),
+            quota_lock: Mutex::new(()),
+            peers: RwLock::new(settings.peers.clone()),
+            trusted_catalog_keys: RwLock::new(settings.trusted_catalog_keys.clone()),
         })
     }
 
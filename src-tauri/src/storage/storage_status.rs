@@ -0,0 +1,119 @@
+//! Status of the Storage node, on demand and as a periodic event.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+use storage_bindings::{debug, get_peer_info, space, StorageNode};
+use tauri::{Emitter, Manager};
+
+use super::storage_state::StorageState;
+use super::storage_types::{RepoSpace, StorageStatus};
+
+/// Emitted with a `StorageStatus` every `STATUS_INTERVAL`.
+pub const STORAGE_STATUS_EVENT: &str = "storage-status";
+
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+
+pub async fn get_storage_status(state: &StorageState) -> StorageStatus {
+    let manager = state.storage_manager();
+    let mut status = StorageStatus {
+        state: manager.state(),
+        peer_id: None,
+        listen_addresses: Vec::new(),
+        announce_addresses: Vec::new(),
+        discovery_nodes: 0,
+        connected_peers: Vec::new(),
+        repo: None,
+        active_downloads: state.jobs.active().await,
+        last_error: manager.last_error(),
+    };
+
+    // A node busy starting or stopping is left alone rather than waited for.
+    let node = manager.idle_node().filter(StorageNode::is_started);
+    let Some(node) = node else {
+        return status;
+    };
+    // Peers the node knows of, whether found through discovery or dialed by
+    // this session.
+    let mut known_peers: BTreeSet<String> = manager.peers().into_iter().collect();
+    match debug(&node).await {
+        Ok(info) => {
+            known_peers.extend(table_peer_ids(&info.table.nodes));
+            status.peer_id = Some(info.id);
+            status.listen_addresses = info.addrs;
+            status.announce_addresses = info.announce_addresses;
+            status.discovery_nodes = info.table.nodes.len();
+        }
+        Err(e) => eprintln!(
+            "Warning: Failed to read the Storage node's debug info: {}",
+            e
+        ),
+    }
+
+    match space(&node).await {
+        Ok(space) => {
+            status.repo = Some(RepoSpace {
+                total_blocks: space.total_blocks,
+                used_bytes: space.quota_used_bytes,
+                reserved_bytes: space.quota_reserved_bytes,
+                quota_bytes: space.quota_max_bytes,
+            })
+        }
+        Err(e) => eprintln!("Warning: Failed to read the Storage node's space: {}", e),
+    }
+
+    for peer_id in known_peers {
+        if let Ok(peer) = get_peer_info(&node, &peer_id).await {
+            if peer.connected {
+                status.connected_peers.push(peer_id);
+            }
+        }
+    }
+
+    status
+}
+
+/// Peer ids of the nodes in the discovery table.
+fn table_peer_ids(nodes: &[serde_json::Value]) -> impl Iterator<Item = String> + '_ {
+    nodes
+        .iter()
+        .filter_map(|node| node.get("peerId")?.as_str())
+        .filter(|peer_id| !peer_id.is_empty())
+        .map(str::to_string)
+}
+
+/// Emits `STORAGE_STATUS_EVENT` periodically for the lifetime of the app.
+pub fn spawn_status_events(app: &tauri::AppHandle) {
+    let app = app.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(STATUS_INTERVAL);
+        loop {
+            interval.tick().await;
+            let status = get_storage_status(&app.state::<StorageState>()).await;
+            if let Err(e) = app.emit(STORAGE_STATUS_EVENT, &status) {
+                eprintln!("Warning: Failed to emit {}: {}", STORAGE_STATUS_EVENT, e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_peer_ids_from_the_discovery_table() {
+        let nodes = vec![
+            json!({ "nodeId": "a1", "peerId": "16Uiu2HAmA", "seen": true }),
+            json!({ "nodeId": "b2", "peerId": "", "seen": false }),
+            json!({ "nodeId": "c3" }),
+            json!({ "nodeId": "d4", "peerId": "16Uiu2HAmD", "seen": false }),
+        ];
+
+        assert_eq!(
+            table_peer_ids(&nodes).collect::<Vec<_>>(),
+            vec!["16Uiu2HAmA", "16Uiu2HAmD"]
+        );
+    }
+}
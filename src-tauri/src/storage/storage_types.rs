@@ -1,5 +1,8 @@
+use super::storage_config::STORAGE_QUOTA_BYTES;
 use serde::{Deserialize, Serialize};
 
+use crate::map::map_types::BoundingBox;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StorageError {
     NodeCreation(String),
@@ -12,6 +15,10 @@ pub enum StorageError {
     Configuration(String),
     Io(String),
     Connection(String),
+    Catalog(String),
+    Cancelled,
+    Quota(String),
+    Busy(String),
 }
 
 impl std::fmt::Display for StorageError {
@@ -27,6 +34,10 @@ impl std::fmt::Display for StorageError {
             StorageError::Configuration(msg) => write!(f, "Configuration error: {}", msg),
             StorageError::Io(msg) => write!(f, "I/O error: {}", msg),
             StorageError::Connection(msg) => write!(f, "Connection error: {}", msg),
+            StorageError::Catalog(msg) => write!(f, "Invalid region catalog: {}", msg),
+            StorageError::Cancelled => write!(f, "Download cancelled"),
+            StorageError::Quota(msg) => write!(f, "Not enough storage: {}", msg),
+            StorageError::Busy(msg) => write!(f, "Download in progress: {}", msg),
         }
     }
 }
@@ -45,3 +56,332 @@ pub struct DownloadResult {
     pub size: usize,
     pub filepath: String,
 }
+
+/// A catalog as published: the catalog JSON and the publisher's signature
+/// over its exact bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedCatalog {
+    /// The `RegionCatalog`, serialized as JSON.
+    pub payload: String,
+    /// Hex-encoded ed25519 public key of the publisher.
+    pub public_key: String,
+    /// Hex-encoded ed25519 signature of `payload`.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionCatalog {
+    /// Version of the catalog format.
+    pub format: u32,
+    /// Publication date, e.g. `2026-03-01`.
+    pub published_at: String,
+    pub regions: Vec<CatalogRegion>,
+}
+
+/// A downloadable pmtiles archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogRegion {
+    /// Stable identifier, also the archive's filename without `.pmtiles`.
+    pub id: String,
+    pub name: String,
+    pub bounds: BoundingBox,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    /// Archive size in bytes.
+    pub size: u64,
+    /// Data version of the archive, e.g. `2026-02-15`.
+    pub version: String,
+    pub cid: String,
+    /// Hex-encoded SHA-256 of the archive, checked once it is downloaded.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+/// A catalog whose signature checked out.
+#[derive(Debug, Clone)]
+pub struct VerifiedCatalog {
+    pub cid: Option<String>,
+    pub public_key: String,
+    pub catalog: RegionCatalog,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogInfo {
+    /// CID the catalog was fetched from, if known.
+    pub cid: Option<String>,
+    pub public_key: String,
+    pub published_at: String,
+    pub region_count: usize,
+    /// Combined size of every region, in bytes.
+    pub total_size: u64,
+}
+
+impl From<&VerifiedCatalog> for CatalogInfo {
+    fn from(verified: &VerifiedCatalog) -> Self {
+        Self {
+            cid: verified.cid.clone(),
+            public_key: verified.public_key.clone(),
+            published_at: verified.catalog.published_at.clone(),
+            region_count: verified.catalog.regions.len(),
+            total_size: verified.catalog.regions.iter().map(|r| r.size).sum(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    /// Waiting to try again after a failed attempt.
+    Retrying,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileProgress {
+    pub cid: String,
+    pub filename: String,
+    pub bytes_downloaded: u64,
+    /// Size of the file, when the catalog or the network reported it.
+    pub total_bytes: Option<u64>,
+    pub status: DownloadStatus,
+    /// Attempts made so far.
+    pub attempts: u32,
+    /// Error of the last failed attempt.
+    pub error: Option<String>,
+}
+
+/// Payload of the download progress event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgressEvent {
+    pub job_id: String,
+    pub files: Vec<FileProgress>,
+    pub bytes_downloaded: u64,
+    /// Size of the whole job, unknown while any file's size is.
+    pub total_bytes: Option<u64>,
+    /// Recent download rate, in bytes per second.
+    pub rate_bps: f64,
+    /// Estimated seconds left, once the rate and total are known.
+    pub eta_s: Option<f64>,
+}
+
+/// Payload of the download finished event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadFinishedEvent {
+    pub job_id: String,
+    /// `completed`, `cancelled` or `failed`.
+    pub status: DownloadStatus,
+    /// The files downloaded, leaving out those already present.
+    pub results: Vec<DownloadResult>,
+    /// Outcome of every file of the job.
+    pub files: Vec<FileProgress>,
+    pub error: Option<String>,
+}
+
+/// Upper bound for `DownloadOptions::concurrency`.
+pub const MAX_DOWNLOAD_CONCURRENCY: usize = 8;
+
+/// Upper bound for `DownloadOptions::max_attempts`.
+pub const MAX_DOWNLOAD_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadOptions {
+    /// Archives downloaded at the same time.
+    pub concurrency: usize,
+    /// Attempts per archive before it is reported as failed.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after every further failure.
+    pub retry_delay_ms: u64,
+}
+
+impl DownloadOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_DOWNLOAD_CONCURRENCY).contains(&self.concurrency) {
+            return Err(format!(
+                "Concurrency must be between 1 and {}",
+                MAX_DOWNLOAD_CONCURRENCY
+            ));
+        }
+        if !(1..=MAX_DOWNLOAD_ATTEMPTS).contains(&self.max_attempts) {
+            return Err(format!(
+                "Attempts must be between 1 and {}",
+                MAX_DOWNLOAD_ATTEMPTS
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 3,
+            max_attempts: 4,
+            retry_delay_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveHealth {
+    /// Structure and content hash check out.
+    Healthy,
+    /// The structure is broken or the content doesn't match its hash.
+    Corrupt,
+    /// The structure is fine but there is no hash to check the content with.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaOptions {
+    /// Bytes the installed archives and the Storage node's repo may use.
+    pub max_bytes: u64,
+    /// Delete the least recently viewed localities to make room for a
+    /// download instead of refusing it.
+    pub evict_least_recently_viewed: bool,
+}
+
+impl QuotaOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_bytes == 0 {
+            return Err("The storage quota must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for QuotaOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: STORAGE_QUOTA_BYTES,
+            evict_least_recently_viewed: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    /// Bytes used by the installed archives and their sidecars.
+    pub pmtiles_bytes: u64,
+    /// Bytes used by the Storage node's repo.
+    pub repo_bytes: u64,
+    pub quota_bytes: u64,
+    /// Free space on the disk holding the archives.
+    pub available_bytes: u64,
+}
+
+impl StorageUsage {
+    pub fn used_bytes(&self) -> u64 {
+        self.pmtiles_bytes + self.repo_bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeState {
+    Uninitialized,
+    Stopped,
+    Starting,
+    Started,
+    /// The last attempt to create, start or stop the node failed.
+    Error,
+}
+
+/// The Storage node's repo, as reported by the node.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoSpace {
+    pub total_blocks: usize,
+    pub used_bytes: u64,
+    pub reserved_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveDownload {
+    pub job_id: String,
+    pub files: usize,
+    pub bytes_downloaded: u64,
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStatus {
+    pub state: NodeState,
+    /// Details below are only known while the node is started.
+    pub peer_id: Option<String>,
+    pub listen_addresses: Vec<String>,
+    pub announce_addresses: Vec<String>,
+    /// Nodes in the discovery table.
+    pub discovery_nodes: usize,
+    /// Peers from the discovery table or dialed by this session that are
+    /// currently connected.
+    pub connected_peers: Vec<String>,
+    pub repo: Option<RepoSpace>,
+    pub active_downloads: Vec<ActiveDownload>,
+    pub last_error: Option<String>,
+}
+
+/// Where an installed archive was downloaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledSource {
+    pub cid: String,
+    /// Catalog version of the archive; `None` for the legacy CID list.
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledLocality {
+    pub locality_id: String,
+    pub filename: String,
+    /// Name of the locality's catalog region.
+    pub name: Option<String>,
+    /// Size of the archive in bytes.
+    pub size: u64,
+    /// Bytes used on disk by the archive, its sidecars and any partial
+    /// download.
+    pub disk_usage: u64,
+    /// CID the archive was downloaded from, when it was recorded.
+    pub cid: Option<String>,
+    pub version: Option<String>,
+    /// Version of the locality in the current catalog.
+    pub latest_version: Option<String>,
+    /// Whether the catalog holds a different archive for the locality.
+    pub update_available: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalityHealth {
+    pub locality_id: String,
+    pub filename: String,
+    pub size: u64,
+    pub status: ArchiveHealth,
+    /// What is wrong with the archive, or why it couldn't be fully checked.
+    pub problem: Option<String>,
+    /// CID the archive is downloaded from, when the catalog or the legacy
+    /// list knows it.
+    pub cid: Option<String>,
+    /// Whether the archive is corrupt and can be downloaded again.
+    pub repairable: bool,
+    /// Whether the catalog lists a newer version of the archive.
+    pub outdated: bool,
+}
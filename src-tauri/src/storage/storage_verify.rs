@@ -0,0 +1,208 @@
+//! Integrity checks of the installed archives.
+//!
+//! Every archive's header and directories are read back, and its content
+//! is hashed and compared with the catalog's SHA-256 when the archive comes
+//! from the catalog's CID or, failing that, the one recorded when it was
+//! downloaded (after the Storage node checked it against its CID).
+//! An archive of an older version is reported as outdated, not corrupt.
+
+use futures_util::StreamExt;
+use pmtiles::{AsyncPmTilesReader, MmapBackend};
+use std::path::Path;
+
+use super::storage_archive::{hash_file, hash_path, read_source, validate_archive};
+use super::storage_jobs::JobFile;
+use super::storage_localities::is_outdated;
+use super::storage_types::{ArchiveHealth, LocalityHealth, RegionCatalog, StorageError};
+
+/// Checks every `.pmtiles` file of `pmtiles_dir`.
+pub async fn verify_localities(
+    pmtiles_dir: &Path,
+    catalog: Option<&RegionCatalog>,
+) -> Result<Vec<LocalityHealth>, StorageError> {
+    let mut paths: Vec<_> = std::fs::read_dir(pmtiles_dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pmtiles"))
+        .collect();
+    paths.sort();
+
+    let mut report = Vec::with_capacity(paths.len());
+    for path in paths {
+        report.push(verify_locality(&path, catalog).await);
+    }
+    Ok(report)
+}
+
+async fn verify_locality(path: &Path, catalog: Option<&RegionCatalog>) -> LocalityHealth {
+    let filename = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let locality_id = filename
+        .strip_suffix(".pmtiles")
+        .unwrap_or(&filename)
+        .to_string();
+    let source = JobFile::for_locality(&locality_id, catalog);
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    // The catalog's size and hash only describe the archive it lists.
+    let region = catalog.and_then(|c| c.regions.iter().find(|r| r.id == locality_id));
+    let outdated = region.is_some_and(|region| is_outdated(path, region));
+    let expected = region
+        .filter(|region| read_source(path).is_some_and(|s| s.cid == region.cid))
+        .map(JobFile::from_region);
+
+    let (status, problem) = match check_archive(path, expected.as_ref()).await {
+        Ok(true) => (ArchiveHealth::Healthy, None),
+        Ok(false) => (
+            ArchiveHealth::Unknown,
+            Some("No recorded hash to compare the content with".to_string()),
+        ),
+        Err(problem) => (ArchiveHealth::Corrupt, Some(problem)),
+    };
+
+    LocalityHealth {
+        repairable: status == ArchiveHealth::Corrupt && source.is_some(),
+        cid: source.map(|s| s.cid),
+        locality_id,
+        filename,
+        size,
+        status,
+        problem,
+        outdated,
+    }
+}
+
+/// Whether the archive's content could be checked against a known hash;
+/// errors describe what is wrong with it. `expected` is the catalog entry
+/// the archive was downloaded from, if it is still listed.
+async fn check_archive(path: &Path, expected: Option<&JobFile>) -> Result<bool, String> {
+    validate_archive(path, expected.and_then(|s| s.total_bytes)).map_err(|e| e.to_string())?;
+    check_directories(path).await?;
+
+    let recorded = std::fs::read_to_string(hash_path(path))
+        .ok()
+        .map(|hash| hash.trim().to_string());
+    let Some(expected) = expected.and_then(|s| s.sha256.clone()).or(recorded) else {
+        return Ok(false);
+    };
+
+    let hashed = path.to_path_buf();
+    let sha256 = tokio::task::spawn_blocking(move || hash_file(&hashed))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    if !sha256.eq_ignore_ascii_case(&expected) {
+        return Err(format!(
+            "Content hash {} does not match {}",
+            sha256, expected
+        ));
+    }
+    Ok(true)
+}
+
+/// Reads the metadata and walks every directory of the archive.
+async fn check_directories(path: &Path) -> Result<(), String> {
+    let backend = MmapBackend::try_from(path)
+        .await
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let reader = AsyncPmTilesReader::try_from_source(backend)
+        .await
+        .map_err(|e| format!("Invalid root directory: {}", e))?;
+    reader
+        .get_metadata()
+        .await
+        .map_err(|e| format!("Invalid metadata: {}", e))?;
+
+    let mut entries = std::sync::Arc::new(reader).entries();
+    while let Some(entry) = entries.next().await {
+        entry.map_err(|e| format!("Invalid tile directory: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage_archive::record_source;
+    use crate::storage::storage_catalog::tests::test_catalog;
+    use crate::storage::storage_types::InstalledSource;
+
+    /// An archive with an empty root directory, `{}` metadata and four
+    /// bytes of tile data.
+    fn minimal_archive() -> Vec<u8> {
+        let mut bytes = b"PMTiles".to_vec();
+        bytes.push(3);
+        for value in [127u64, 1, 128, 2, 130, 0, 130, 4, 0, 0, 0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        // Clustered, no internal compression, no tile compression, MVT.
+        bytes.extend_from_slice(&[1, 1, 1, 1]);
+        bytes.resize(127, 0);
+        bytes.push(0);
+        bytes.extend_from_slice(b"{}");
+        bytes.extend_from_slice(b"tile");
+        bytes
+    }
+
+    async fn status(dir: &Path) -> Vec<ArchiveHealth> {
+        verify_localities(dir, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|health| health.status)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn reports_healthy_unknown_and_corrupt_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ottawa.pmtiles");
+
+        std::fs::write(&path, minimal_archive()).unwrap();
+        assert_eq!(status(dir.path()).await, vec![ArchiveHealth::Unknown]);
+
+        std::fs::write(hash_path(&path), hash_file(&path).unwrap()).unwrap();
+        assert_eq!(status(dir.path()).await, vec![ArchiveHealth::Healthy]);
+
+        let mut changed = minimal_archive();
+        *changed.last_mut().unwrap() = b'x';
+        std::fs::write(&path, changed).unwrap();
+        assert_eq!(status(dir.path()).await, vec![ArchiveHealth::Corrupt]);
+
+        let mut truncated = minimal_archive();
+        truncated.truncate(129);
+        std::fs::write(&path, truncated).unwrap();
+        let report = verify_localities(dir.path(), None).await.unwrap();
+        assert_eq!(report[0].status, ArchiveHealth::Corrupt);
+        assert!(!report[0].repairable);
+    }
+
+    #[tokio::test]
+    async fn checks_against_the_catalog_only_for_its_own_cid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ottawa.pmtiles");
+        std::fs::write(&path, minimal_archive()).unwrap();
+        std::fs::write(hash_path(&path), hash_file(&path).unwrap()).unwrap();
+
+        let mut catalog = test_catalog();
+        catalog.regions[0].size = minimal_archive().len() as u64;
+        catalog.regions[0].sha256 = Some("00".repeat(32));
+        let installed = |cid: &str| InstalledSource {
+            cid: cid.to_string(),
+            version: None,
+        };
+
+        record_source(&path, &installed("zDvOlder")).unwrap();
+        let report = verify_localities(dir.path(), Some(&catalog)).await.unwrap();
+        assert_eq!(report[0].status, ArchiveHealth::Healthy);
+        assert!(report[0].outdated);
+        assert!(!report[0].repairable);
+
+        record_source(&path, &installed(&catalog.regions[0].cid)).unwrap();
+        let report = verify_localities(dir.path(), Some(&catalog)).await.unwrap();
+        assert_eq!(report[0].status, ArchiveHealth::Corrupt);
+        assert!(!report[0].outdated);
+        assert!(report[0].repairable);
+    }
+}
@@ -22,12 +22,17 @@ const MapView = () => {
 
     mapInitialized.current = true;
 
-    initializeStorage().then(() => {
-      initializeMap(container).catch((err) => {
-        $mapLoadingState.set('error');
-        console.error('Failed to initialize map:', err);
+    // The archives already installed still load when storage fails.
+    initializeStorage()
+      .catch((err) => {
+        console.error('Failed to initialize storage:', err);
+      })
+      .then(() => {
+        initializeMap(container).catch((err) => {
+          $mapLoadingState.set('error');
+          console.error('Failed to initialize map:', err);
+        });
       });
-    });
 
     return () => {
       cleanupMap();
@@ -1,15 +1,37 @@
 import { layers, namedFlavor } from '@protomaps/basemaps';
-import maplibregl from 'maplibre-gl';
+import maplibregl, {
+  type LayerSpecification,
+  type RasterTileSource,
+  type SourceSpecification,
+  type VectorTileSource,
+} from 'maplibre-gl';
 import {
   $mapError,
   $mapInstance,
   $mapLoadingState,
   resetMapState,
 } from '../states/map-state';
-import type { MapInstance } from '../types/map-types';
-import { createPmtilesProtocol, initPmtilesReader } from './pmtiles-service';
+import type {
+  MapInstance,
+  MultiPmtilesInfo,
+  SourceKind,
+} from '../types/map-types';
+import {
+  createPmtilesProtocol,
+  getSourceUrl,
+  initPmtilesReader,
+  onLocalitiesChanged,
+} from './pmtiles-service';
 
 const InitZoomLevel = 10;
+const SourceId = 'protomaps';
+const RasterSourceId = 'raster';
+const TerrainSourceId = 'terrain';
+const SourceIds: Record<SourceKind, string> = {
+  vector: SourceId,
+  raster: RasterSourceId,
+  terrain: TerrainSourceId,
+};
 
 export async function initializeMap(
   container: HTMLElement,
@@ -20,7 +42,8 @@ export async function initializeMap(
     const protocol = createPmtilesProtocol();
     maplibregl.addProtocol('pmtiles', protocol);
 
-    const { combinedBounds, combinedCenter, minZoom, maxZoom } = pmtilesInfo;
+    const { combinedBounds, combinedCenter, minZoom, overzoomMaxZoom } =
+      pmtilesInfo;
     const centerLng = combinedCenter.longitude;
     const centerLat = combinedCenter.latitude;
 
@@ -29,11 +52,11 @@ export async function initializeMap(
       style: {
         version: 8,
         sources: {
-          protomaps: {
+          [SourceId]: {
             type: 'vector',
-            url: 'pmtiles://local',
+            url: getSourceUrl('vector'),
             minzoom: minZoom,
-            maxzoom: maxZoom,
+            maxzoom: overzoomMaxZoom,
             bounds: [
               combinedBounds.minLon,
               combinedBounds.minLat,
@@ -41,8 +64,12 @@ export async function initializeMap(
               combinedBounds.maxLat,
             ] as [number, number, number, number],
           },
+          ...createImageSources(pmtilesInfo),
         },
-        layers: layers('protomaps', namedFlavor('dark'), { lang: 'en' }),
+        layers: withImageLayers(
+          layers(SourceId, namedFlavor('dark'), { lang: 'en' }),
+          pmtilesInfo,
+        ),
         sprite: 'https://protomaps.github.io/basemaps-assets/sprites/v4/dark',
         glyphs:
           'https://protomaps.github.io/basemaps-assets/fonts/{fontstack}/{range}.pbf',
@@ -65,12 +92,75 @@ export async function initializeMap(
   }
 }
 
+function hasSource(info: MultiPmtilesInfo, source: SourceKind): boolean {
+  return info.localities.some((locality) => locality.sourceKind === source);
+}
+
+function createImageSources(
+  info: MultiPmtilesInfo,
+): Record<string, SourceSpecification> {
+  const sources: Record<string, SourceSpecification> = {};
+  if (hasSource(info, 'raster')) {
+    sources[RasterSourceId] = { type: 'raster', url: getSourceUrl('raster') };
+  }
+  if (hasSource(info, 'terrain')) {
+    sources[TerrainSourceId] = {
+      type: 'raster-dem',
+      url: getSourceUrl('terrain'),
+    };
+  }
+  return sources;
+}
+
+/**
+ * Slots the raster and hillshade layers right above the basemap background,
+ * below every vector layer.
+ */
+function withImageLayers(
+  basemapLayers: LayerSpecification[],
+  info: MultiPmtilesInfo,
+): LayerSpecification[] {
+  const imageLayers: LayerSpecification[] = [];
+  if (hasSource(info, 'raster')) {
+    imageLayers.push({
+      id: RasterSourceId,
+      type: 'raster',
+      source: RasterSourceId,
+    });
+  }
+  if (hasSource(info, 'terrain')) {
+    imageLayers.push({
+      id: 'hillshade',
+      type: 'hillshade',
+      source: TerrainSourceId,
+    });
+  }
+
+  const [background, ...rest] = basemapLayers;
+  return background?.type === 'background'
+    ? [background, ...imageLayers, ...rest]
+    : [...imageLayers, ...basemapLayers];
+}
+
 function setupMapEventListeners(map: maplibregl.Map): void {
   map.on('load', () => {
     $mapLoadingState.set('ready');
     $mapError.set(null);
   });
 
+  void onLocalitiesChanged(async () => {
+    try {
+      await initPmtilesReader();
+      for (const [source, id] of Object.entries(SourceIds)) {
+        map
+          .getSource<VectorTileSource | RasterTileSource>(id)
+          ?.setUrl(getSourceUrl(source as SourceKind));
+      }
+    } catch (error) {
+      console.error('Failed to reload localities:', error);
+    }
+  });
+
   map.on('error', (e) => {
     console.error('Map error:', e);
     $mapLoadingState.set('error');
@@ -0,0 +1,55 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type {
+  Language,
+  NavigationProgress,
+  NavigationReroute,
+  PositionFix,
+  Route,
+  RoutingProfile,
+} from '../types/map-types';
+
+export async function startNavigation(
+  from: { lon: number; lat: number },
+  to: { lon: number; lat: number },
+  profile: RoutingProfile = 'car',
+  language: Language = 'en',
+): Promise<Route> {
+  return await invoke<Route>('start_navigation', { from, to, profile, language });
+}
+
+export async function updatePosition(fix: PositionFix): Promise<NavigationProgress> {
+  return await invoke<NavigationProgress>('update_position', { fix });
+}
+
+export async function stopNavigation(): Promise<void> {
+  await invoke('stop_navigation');
+}
+
+export async function getActiveRoute(): Promise<Route | null> {
+  return await invoke<Route | null>('get_active_route');
+}
+
+export async function onNavigationProgress(
+  callback: (progress: NavigationProgress) => void,
+): Promise<UnlistenFn> {
+  return await listen<NavigationProgress>('navigation-progress', (event) =>
+    callback(event.payload),
+  );
+}
+
+export async function onNavigationRerouted(
+  callback: (reroute: NavigationReroute) => void,
+): Promise<UnlistenFn> {
+  return await listen<NavigationReroute>('navigation-rerouted', (event) =>
+    callback(event.payload),
+  );
+}
+
+export async function onNavigationArrived(
+  callback: (progress: NavigationProgress) => void,
+): Promise<UnlistenFn> {
+  return await listen<NavigationProgress>('navigation-arrived', (event) =>
+    callback(event.payload),
+  );
+}
@@ -1,29 +1,99 @@
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type { FeatureCollection } from 'geojson';
 import type { GetResourceResponse, RequestParameters } from 'maplibre-gl';
 import { $pmtilesInitialized } from '../states/map-state';
-import type { LocalityMetadata, MultiPmtilesInfo } from '../types/map-types';
+import type {
+  LocalityChanges,
+  LocalityMetadata,
+  MultiPmtilesInfo,
+  ReverseGeocodeResult,
+  SourceKind,
+  TileCacheStats,
+  TileJson,
+} from '../types/map-types';
 
-let cachedMetadata: MultiPmtilesInfo | null = null;
+const TileScheme = 'anymaps';
+const ProtocolPrefix = 'pmtiles://';
+const SourcePaths: Record<SourceKind, string> = {
+  vector: 'tiles',
+  raster: 'raster',
+  terrain: 'terrain',
+};
+const LocalitiesChangedEvent = 'localities-changed';
 
 export async function initPmtilesReader(): Promise<MultiPmtilesInfo> {
   const info = await invoke<MultiPmtilesInfo>('init_pmtiles_reader');
-  cachedMetadata = info;
   $pmtilesInitialized.set(true);
   return info;
 }
 
-export async function getPmtilesTile(
-  z: number,
-  x: number,
-  y: number,
-): Promise<number[] | null> {
-  return await invoke<number[] | null>('get_pmtiles_tile', { z, x, y });
+export function getSourceUrl(source: SourceKind): string {
+  return `${ProtocolPrefix}${source}`;
+}
+
+export function getTileUrlTemplate(source: SourceKind): string {
+  return `${convertFileSrc(SourcePaths[source], TileScheme)}/{z}/{x}/{y}`;
+}
+
+export async function getTileJson(source: SourceKind): Promise<TileJson> {
+  return await invoke<TileJson>('get_tilejson', {
+    source,
+    tileUrl: getTileUrlTemplate(source),
+  });
 }
 
 export async function getLocalities(): Promise<LocalityMetadata[]> {
   return await invoke<LocalityMetadata[]>('get_localities');
 }
 
+export async function reloadLocalities(): Promise<LocalityChanges> {
+  return await invoke<LocalityChanges>('reload_localities');
+}
+
+export async function onLocalitiesChanged(
+  handler: (changes: LocalityChanges) => void,
+): Promise<UnlistenFn> {
+  return await listen<LocalityChanges>(LocalitiesChangedEvent, (event) =>
+    handler(event.payload),
+  );
+}
+
+export async function queryFeaturesAt(
+  lon: number,
+  lat: number,
+  zoom: number,
+  radiusPx = 8,
+): Promise<FeatureCollection> {
+  return await invoke<FeatureCollection>('query_features_at', {
+    lon,
+    lat,
+    zoom: Math.max(0, Math.floor(zoom)),
+    radiusPx,
+  });
+}
+
+export async function reverseGeocode(
+  lon: number,
+  lat: number,
+): Promise<ReverseGeocodeResult> {
+  return await invoke<ReverseGeocodeResult>('reverse_geocode', { lon, lat });
+}
+
+export async function getTileCacheStats(): Promise<TileCacheStats> {
+  return await invoke<TileCacheStats>('get_tile_cache_stats');
+}
+
+export async function setLocalityPriority(
+  localityId: string,
+  priority: number | null,
+): Promise<LocalityMetadata> {
+  return await invoke<LocalityMetadata>('set_locality_priority', {
+    localityId,
+    priority,
+  });
+}
+
 export function createPmtilesProtocol() {
   return async (
     request: RequestParameters,
@@ -39,47 +109,13 @@ export function createPmtilesProtocol() {
     }
 
     if (request.type === 'json') {
-      const metadata = cachedMetadata;
-      if (!metadata) {
-        throw new Error('PMTiles metadata not available');
+      const source = request.url.slice(ProtocolPrefix.length);
+      if (!(source in SourcePaths)) {
+        throw new Error(`Unknown tile source: ${request.url}`);
       }
-
-      return {
-        data: {
-          tiles: [`${request.url}/{z}/{x}/{y}`],
-          minzoom: metadata.minZoom,
-          maxzoom: metadata.maxZoom,
-          bounds: [
-            metadata.combinedBounds.minLon,
-            metadata.combinedBounds.minLat,
-            metadata.combinedBounds.maxLon,
-            metadata.combinedBounds.maxLat,
-          ],
-        },
-      };
-    }
-
-    const re = new RegExp(/pmtiles:\/\/.+\/(\d+)\/(\d+)\/(\d+)/);
-    const result = request.url.match(re);
-
-    if (!result) {
-      throw new Error('Invalid PMTiles protocol URL');
-    }
-
-    const z = parseInt(result[1], 10);
-    const x = parseInt(result[2], 10);
-    const y = parseInt(result[3], 10);
-
-    const data = await getPmtilesTile(z, x, y);
-
-    if (data) {
-      return {
-        data: new Uint8Array(data),
-      };
+      return { data: await getTileJson(source as SourceKind) };
     }
 
-    return {
-      data: new Uint8Array(),
-    };
+    throw new Error('Tiles are served through the anymaps:// scheme');
   };
 }
@@ -0,0 +1,11 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Language, Route, RoutingProfile } from '../types/map-types';
+
+export async function route(
+  from: { lon: number; lat: number },
+  to: { lon: number; lat: number },
+  profile: RoutingProfile = 'car',
+  language: Language = 'en',
+): Promise<Route> {
+  return await invoke<Route>('route', { from, to, profile, language });
+}
@@ -0,0 +1,14 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { PlaceResult } from '../types/map-types';
+
+export async function searchPlaces(
+  query: string,
+  near?: { lon: number; lat: number },
+  limit?: number,
+): Promise<PlaceResult[]> {
+  return await invoke<PlaceResult[]>('search_places', {
+    query,
+    near: near ?? null,
+    limit: limit ?? null,
+  });
+}
@@ -0,0 +1,12 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { Settings, SettingsUpdate } from '../types/map-types';
+
+export async function getSettings(): Promise<Settings> {
+  return await invoke<Settings>('get_settings');
+}
+
+export async function updateSettings(
+  settings: Settings,
+): Promise<SettingsUpdate> {
+  return await invoke<SettingsUpdate>('update_settings', { settings });
+}
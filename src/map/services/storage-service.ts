@@ -1,4 +1,18 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import type {
+  BoundingBox,
+  CatalogInfo,
+  CatalogRegion,
+  DownloadFinishedEvent,
+  DownloadOptions,
+  DownloadProgressEvent,
+  InstalledLocality,
+  LocalityHealth,
+  QuotaOptions,
+  StorageStatus,
+  StorageUsage,
+} from '../types/map-types';
 
 export async function startStorageNode(): Promise<void> {
   await invoke('start_storage_node');
@@ -12,10 +26,159 @@ export async function connectToPeer(): Promise<void> {
   await invoke('connect_to_peer');
 }
 
-export async function downloadPmtilesFiles(): Promise<number> {
-  const count = await invoke<number>('download_pmtiles_files');
-  console.log(`Downloaded ${count} PMTiles file(s)`);
-  return count;
+export async function storageStatus(): Promise<StorageStatus> {
+  return await invoke<StorageStatus>('storage_status');
+}
+
+export async function onStorageStatus(
+  callback: (status: StorageStatus) => void,
+): Promise<UnlistenFn> {
+  return await listen<StorageStatus>('storage-status', (event) =>
+    callback(event.payload),
+  );
+}
+
+/**
+ * Checks the installed archives, or downloads the legacy archives when no
+ * catalog has been fetched; returns the job id.
+ */
+export async function downloadPmtilesFiles(): Promise<string> {
+  return await invoke<string>('download_pmtiles_files');
+}
+
+export async function fetchCatalog(cid: string): Promise<CatalogInfo> {
+  return await invoke<CatalogInfo>('fetch_catalog', { cid });
+}
+
+export async function verifyCatalog(): Promise<CatalogInfo> {
+  return await invoke<CatalogInfo>('verify_catalog');
+}
+
+export async function listCatalogRegions(
+  bounds?: BoundingBox,
+  query?: string,
+): Promise<CatalogRegion[]> {
+  return await invoke<CatalogRegion[]>('list_catalog_regions', {
+    bounds: bounds ?? null,
+    query: query ?? null,
+  });
+}
+
+export async function downloadRegion(regionId: string): Promise<string> {
+  return await invoke<string>('download_region', { regionId });
+}
+
+export async function downloadRegionsInBbox(
+  bounds: BoundingBox,
+): Promise<string> {
+  return await invoke<string>('download_regions_in_bbox', { bounds });
+}
+
+export async function cancelDownload(jobId: string): Promise<void> {
+  await invoke('cancel_download', { jobId });
+}
+
+export async function getDownloadOptions(): Promise<DownloadOptions> {
+  return await invoke<DownloadOptions>('get_download_options');
+}
+
+export async function setDownloadOptions(
+  options: DownloadOptions,
+): Promise<void> {
+  await invoke('set_download_options', { options });
+}
+
+export async function verifyLocalities(): Promise<LocalityHealth[]> {
+  return await invoke<LocalityHealth[]>('verify_localities');
+}
+
+/** Downloads the given corrupt archives again; returns the job id. */
+export async function repairLocalities(localityIds: string[]): Promise<string> {
+  return await invoke<string>('repair_localities', { localityIds });
+}
+
+export async function getInstalledLocalities(): Promise<InstalledLocality[]> {
+  return await invoke<InstalledLocality[]>('get_installed_localities');
+}
+
+export async function deleteLocality(localityId: string): Promise<void> {
+  await invoke('delete_locality', { localityId });
+}
+
+export async function checkLocalityUpdates(): Promise<InstalledLocality[]> {
+  return await invoke<InstalledLocality[]>('check_locality_updates');
+}
+
+/** Updates the given localities, or every outdated one; returns the job id. */
+export async function updateLocalities(localityIds?: string[]): Promise<string> {
+  return await invoke<string>('update_localities', {
+    localityIds: localityIds ?? null,
+  });
+}
+
+export async function getStorageUsage(): Promise<StorageUsage> {
+  return await invoke<StorageUsage>('get_storage_usage');
+}
+
+export async function getQuotaOptions(): Promise<QuotaOptions> {
+  return await invoke<QuotaOptions>('get_quota_options');
+}
+
+export async function setQuotaOptions(options: QuotaOptions): Promise<void> {
+  await invoke('set_quota_options', { options });
+}
+
+export async function onDownloadProgress(
+  callback: (progress: DownloadProgressEvent) => void,
+): Promise<UnlistenFn> {
+  return await listen<DownloadProgressEvent>('download-progress', (event) =>
+    callback(event.payload),
+  );
+}
+
+export async function onDownloadFinished(
+  callback: (finished: DownloadFinishedEvent) => void,
+): Promise<UnlistenFn> {
+  return await listen<DownloadFinishedEvent>('download-finished', (event) =>
+    callback(event.payload),
+  );
+}
+
+/**
+ * Runs a download job to completion. The listener is registered before the
+ * job starts so a quick job can't finish unnoticed. A failed job only
+ * rejects when none of its files completed; otherwise the failed ones are
+ * listed in `files`.
+ */
+export async function runDownload(
+  start: () => Promise<string>,
+): Promise<DownloadFinishedEvent> {
+  const finished = new Map<string, DownloadFinishedEvent>();
+  let notify: (() => void) | undefined;
+  const unlisten = await onDownloadFinished((event) => {
+    finished.set(event.jobId, event);
+    notify?.();
+  });
+
+  try {
+    const jobId = await start();
+    const result = await new Promise<DownloadFinishedEvent>((resolve) => {
+      notify = () => {
+        const event = finished.get(jobId);
+        if (event) {
+          resolve(event);
+        }
+      };
+      notify();
+    });
+    const completed = result.files.some((file) => file.status === 'completed');
+    if (result.status === 'failed' && !completed) {
+      throw new Error(result.error ?? 'Download failed');
+    }
+    return result;
+  } finally {
+    unlisten();
+  }
 }
 
 export async function initializeStorage(): Promise<void> {
@@ -29,8 +192,11 @@ export async function initializeStorage(): Promise<void> {
     console.log('Successfully connected to peer');
 
     console.log('Downloading PMTiles files...');
-    const fileCount = await downloadPmtilesFiles();
-    console.log(`Successfully downloaded ${fileCount} PMTiles file(s)`);
+    const { results, files } = await runDownload(downloadPmtilesFiles);
+    console.log(`Successfully downloaded ${results.length} PMTiles file(s)`);
+    for (const file of files.filter((f) => f.status === 'failed')) {
+      console.warn(`Failed to download ${file.filename}:`, file.error);
+    }
   } catch (error) {
     console.error('Failed to initialize storage:', error);
     throw error;
@@ -17,6 +17,8 @@ export interface CenterPoint {
   zoom: number;
 }
 
+export type SourceKind = 'vector' | 'raster' | 'terrain';
+
 export interface LocalityMetadata {
   id: string;
   filename: string;
@@ -26,6 +28,54 @@ export interface LocalityMetadata {
   center: CenterPoint;
   minZoom: number;
   maxZoom: number;
+  priority: number;
+  buildDate?: string;
+  fileSize: number;
+  modifiedAt?: number;
+  vectorLayers: VectorLayerInfo[];
+  attribution?: string;
+  sourceKind: SourceKind;
+  tileType: string;
+  tileCompression: string;
+  version?: string;
+  encoding?: string;
+}
+
+export interface VectorLayerInfo {
+  id: string;
+  fields: Record<string, string>;
+  description?: string;
+  minZoom?: number;
+  maxZoom?: number;
+}
+
+export interface TileJsonVectorLayer {
+  id: string;
+  fields: Record<string, string>;
+  description?: string;
+  minzoom?: number;
+  maxzoom?: number;
+}
+
+export interface TileJson {
+  tilejson: string;
+  tiles: string[];
+  name?: string;
+  description?: string;
+  version?: string;
+  attribution?: string;
+  scheme?: string;
+  minzoom?: number;
+  maxzoom?: number;
+  bounds?: [number, number, number, number];
+  center?: [number, number, number];
+  vector_layers?: TileJsonVectorLayer[];
+}
+
+export interface LocalityChanges {
+  added: LocalityMetadata[];
+  updated: LocalityMetadata[];
+  removed: string[];
 }
 
 export interface MultiPmtilesInfo {
@@ -34,4 +84,314 @@ export interface MultiPmtilesInfo {
   combinedCenter: CenterPoint;
   minZoom: number;
   maxZoom: number;
+  overzoomMaxZoom: number;
+}
+
+export interface TileCacheStats {
+  entries: number;
+  usedBytes: number;
+  budgetBytes: number;
+  hits: number;
+  misses: number;
+  evictions: number;
+  hitRate: number;
+}
+
+export interface ReverseGeocodeResult {
+  address: string | null;
+  street: string | null;
+  neighbourhood: string | null;
+  locality: string | null;
+  region: string | null;
+  localityId: string | null;
+  label: string | null;
+}
+
+export type PlaceKind = 'place' | 'poi' | 'road';
+
+export interface PlaceResult {
+  name: string;
+  kind: PlaceKind;
+  class: string | null;
+  lon: number;
+  lat: number;
+  localityId: string;
+  distanceM: number | null;
+  score: number;
+}
+
+export type RoutingProfile = 'car' | 'bike' | 'foot';
+
+export type RoadClass =
+  | 'motorway'
+  | 'trunk'
+  | 'primary'
+  | 'secondary'
+  | 'tertiary'
+  | 'minor'
+  | 'service'
+  | 'track'
+  | 'path'
+  | 'footway'
+  | 'cycleway'
+  | 'steps'
+  | 'pedestrian';
+
+export interface RouteSegment {
+  name: string | null;
+  roadRef: string | null;
+  class: RoadClass;
+  roundabout: boolean;
+  distanceM: number;
+  durationS: number;
+  startIndex: number;
+  endIndex: number;
+  endsAtJunction: boolean;
+}
+
+export type Language = 'en' | 'fr';
+
+export type ManeuverType =
+  | 'depart'
+  | 'continue'
+  | 'slightLeft'
+  | 'left'
+  | 'sharpLeft'
+  | 'slightRight'
+  | 'right'
+  | 'sharpRight'
+  | 'uTurn'
+  | 'roundabout'
+  | 'arrive';
+
+export interface Instruction {
+  maneuver: ManeuverType;
+  text: string;
+  roadName: string | null;
+  distanceM: number;
+  distanceText: string;
+  durationS: number;
+  bearingBefore: number;
+  bearingAfter: number;
+  roundaboutExit: number | null;
+  coordinateIndex: number;
+  location: [number, number];
+}
+
+export interface Route {
+  profile: RoutingProfile;
+  distanceM: number;
+  durationS: number;
+  coordinates: [number, number][];
+  segments: RouteSegment[];
+  instructions: Instruction[];
+}
+
+export interface PositionFix {
+  lon: number;
+  lat: number;
+  accuracyM?: number | null;
+  timestampMs: number;
+}
+
+export interface NavigationProgress {
+  snapped: [number, number];
+  distanceFromRouteM: number;
+  distanceTravelledM: number;
+  distanceRemainingM: number;
+  durationRemainingS: number;
+  etaMs: number;
+  nextInstructionIndex: number | null;
+  nextInstruction: Instruction | null;
+  distanceToNextInstructionM: number | null;
+}
+
+export interface NavigationReroute {
+  route: Route;
+  distanceFromRouteM: number;
+}
+
+export interface CatalogRegion {
+  id: string;
+  name: string;
+  bounds: BoundingBox;
+  minZoom: number;
+  maxZoom: number;
+  size: number;
+  version: string;
+  cid: string;
+  sha256: string | null;
+}
+
+export interface CatalogInfo {
+  cid: string | null;
+  publicKey: string;
+  publishedAt: string;
+  regionCount: number;
+  totalSize: number;
+}
+
+export interface DownloadResult {
+  cid: string;
+  size: number;
+  filepath: string;
+}
+
+export type DownloadStatus =
+  | 'queued'
+  | 'downloading'
+  | 'retrying'
+  | 'completed'
+  | 'cancelled'
+  | 'failed';
+
+export interface FileProgress {
+  cid: string;
+  filename: string;
+  bytesDownloaded: number;
+  totalBytes: number | null;
+  status: DownloadStatus;
+  attempts: number;
+  error: string | null;
+}
+
+export interface DownloadProgressEvent {
+  jobId: string;
+  files: FileProgress[];
+  bytesDownloaded: number;
+  totalBytes: number | null;
+  rateBps: number;
+  etaS: number | null;
+}
+
+export interface DownloadFinishedEvent {
+  jobId: string;
+  status: DownloadStatus;
+  results: DownloadResult[];
+  files: FileProgress[];
+  error: string | null;
+}
+
+export interface DownloadOptions {
+  concurrency: number;
+  maxAttempts: number;
+  retryDelayMs: number;
+}
+
+export interface QuotaOptions {
+  maxBytes: number;
+  evictLeastRecentlyViewed: boolean;
+}
+
+export interface StorageUsage {
+  pmtilesBytes: number;
+  repoBytes: number;
+  quotaBytes: number;
+  availableBytes: number;
+}
+
+export type NodeState =
+  | 'uninitialized'
+  | 'stopped'
+  | 'starting'
+  | 'started'
+  | 'error';
+
+export interface RepoSpace {
+  totalBlocks: number;
+  usedBytes: number;
+  reservedBytes: number;
+  quotaBytes: number;
+}
+
+export interface ActiveDownload {
+  jobId: string;
+  files: number;
+  bytesDownloaded: number;
+  totalBytes: number | null;
+}
+
+export interface StorageStatus {
+  state: NodeState;
+  peerId: string | null;
+  listenAddresses: string[];
+  announceAddresses: string[];
+  discoveryNodes: number;
+  connectedPeers: string[];
+  repo: RepoSpace | null;
+  activeDownloads: ActiveDownload[];
+  lastError: string | null;
+}
+
+export interface InstalledLocality {
+  localityId: string;
+  filename: string;
+  name: string | null;
+  size: number;
+  diskUsage: number;
+  cid: string | null;
+  version: string | null;
+  latestVersion: string | null;
+  updateAvailable: boolean;
+}
+
+export type ArchiveHealth = 'healthy' | 'corrupt' | 'unknown';
+
+export interface LocalityHealth {
+  localityId: string;
+  filename: string;
+  size: number;
+  status: ArchiveHealth;
+  problem: string | null;
+  cid: string | null;
+  repairable: boolean;
+  /** Whether the catalog lists a newer version of the archive. */
+  outdated: boolean;
+}
+
+export interface TileOptions {
+  composite: boolean;
+  overzoomLevels: number;
+}
+
+export type LogLevel =
+  | 'trace'
+  | 'debug'
+  | 'info'
+  | 'notice'
+  | 'warn'
+  | 'error'
+  | 'fatal';
+
+export interface StorageSettings {
+  logLevel: LogLevel;
+  bootstrapNodes: string[];
+  /** Peers to connect to, as `peerId:address`. */
+  peers: string[];
+  /** Hex-encoded ed25519 keys of the trusted catalog publishers. */
+  trustedCatalogKeys: string[];
+  discoveryPort: number;
+  maxPeers: number;
+  quota: QuotaOptions;
+  download: DownloadOptions;
+}
+
+export interface MapSettings {
+  maxCachedReaders: number;
+  tileCacheBudgetBytes: number;
+  tile: TileOptions;
+  /** User-set locality priorities keyed by locality id. */
+  priorityOverrides: Record<string, number>;
+}
+
+export interface Settings {
+  version: number;
+  storage: StorageSettings;
+  map: MapSettings;
+}
+
+export interface SettingsUpdate {
+  settings: Settings;
+  /** Whether some changes only take effect once the app restarts. */
+  restartRequired: boolean;
 }